@@ -1,10 +1,14 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::sysvar::instructions::{
+    load_current_index_checked, load_instruction_at_checked,
+};
 use anchor_lang::solana_program::system_instruction;
 use anchor_spl::{
-    associated_token::AssociatedToken,
+    associated_token::{get_associated_token_address_with_program_id, AssociatedToken},
     token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
 };
 use pyth_solana_receiver_sdk::price_update::{get_feed_id_from_hex, PriceUpdateV2};
+use pyth_solana_receiver_sdk::PYTH_PUSH_ORACLE_ID;
 use solana_security_txt::security_txt;
 
 security_txt! {
@@ -23,6 +27,17 @@ declare_id!("SAUR5HUSEyf4LPRg3biDZ3Zxq2dbJvArnh7dwTmvfvj");
 
 
 pub const FEED_ID: &str = "ef0d8b6fda2ceba41da15d4095d1da392a0d2f8ed0c6c7bc0f4cfac8c280b56d"; // SOL/USD price feed id from https://pyth.network/developers/price-feed-ids
+pub const MAXIMUM_AGE: u64 = 600; // Oldest accepted SOL/USD price update, in seconds
+pub const BPS_DENOMINATOR: u64 = 10_000;
+pub const VAULT_VERSION: u8 = 1;
+/// Size of a `Vault` created before the layout was versioned.
+pub const LEGACY_VAULT_LEN: usize = 8 + 8 + 32 + 32 + 8 + 8 + 1 + 32;
+pub const SECONDS_PER_DAY: i64 = 86_400;
+pub const MAX_GATE_SEEDS: usize = 4;
+const ED25519_PROGRAM_ID: Pubkey = pubkey!("Ed25519SigVerify111111111111111111111111111");
+pub const MAX_GATE_SEED_LEN: usize = 32;
+pub const AUCTION_WEIGHT_SCALE: u128 = 1_000_000; // Fixed-point scale for lamports-per-price weights
+const ADMIN: Pubkey = pubkey!("3c1gFBMmZFrDTgUz2HH8yhhbfqibdwfK14QtHRiQLYE1");
 
 
 #[program]
@@ -36,9 +51,10 @@ pub mod swap {
         price_per_token: u64,
     ) -> Result<()> {
         require!(
-            ctx.accounts.authority.key() == pubkey!("3c1gFBMmZFrDTgUz2HH8yhhbfqibdwfK14QtHRiQLYE1"),
+            ctx.accounts.authority.key() == ADMIN,
             CustomError::InvalidAuth
         );
+        require!(price_per_token > 0, CustomError::InvalidPrice);
         let vault = &mut ctx.accounts.vault;
         vault.index = index;
         vault.token_mint = ctx.accounts.token_mint.key();
@@ -47,24 +63,66 @@ pub mod swap {
         vault.total_tokens = 0;
         vault.bump = ctx.bumps.vault;
         vault.owner = ctx.accounts.authority.key();
+        vault.version = VAULT_VERSION;
+
+        let stats = &mut ctx.accounts.stats;
+        stats.vault = vault.key();
+        stats.bump = ctx.bumps.stats;
         Ok(())
     }
 
 
+    /// Sets the sale price. Under a withdrawal timelock the change is queued
+    /// instead and applied by `apply_price_update` once the delay has passed, so
+    /// the admin can't drop the price and buy out the inventory; a later call
+    /// replaces the queued price.
     pub fn update_price(ctx: Context<UpdatePrice>, new_price: u64) -> Result<()> {
         require!(
-            ctx.accounts.authority.key() == pubkey!("3c1gFBMmZFrDTgUz2HH8yhhbfqibdwfK14QtHRiQLYE1"),
+            ctx.accounts.authority.key() == ADMIN,
+            CustomError::InvalidAuth
+        );
+        require!(new_price > 0, CustomError::InvalidPrice);
+        let vault = &mut ctx.accounts.vault;
+        if vault.withdrawal_delay == 0 {
+            vault.price_per_token = new_price;
+            return Ok(());
+        }
+        let eta = Clock::get()?
+            .unix_timestamp
+            .checked_add(vault.withdrawal_delay)
+            .ok_or(CustomError::Overflow)?;
+        vault.pending_price = new_price;
+        vault.price_update_eta = eta;
+        emit!(PriceUpdateQueued {
+            vault: vault.key(),
+            price: new_price,
+            eta,
+        });
+        Ok(())
+    }
+
+
+    pub fn apply_price_update(ctx: Context<ConfigureVault>) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ADMIN,
             CustomError::InvalidAuth
         );
         let vault = &mut ctx.accounts.vault;
-        vault.price_per_token = new_price;
+        require!(vault.pending_price > 0, CustomError::NoPriceUpdateQueued);
+        require!(
+            Clock::get()?.unix_timestamp >= vault.price_update_eta,
+            CustomError::TimelockNotExpired
+        );
+        vault.price_per_token = vault.pending_price;
+        vault.pending_price = 0;
+        vault.price_update_eta = 0;
         Ok(())
     }
 
 
     pub fn deposit_tokens(ctx: Context<DepositTokens>, amount: u64) -> Result<()> {
         require!(
-            ctx.accounts.authority.key() == pubkey!("3c1gFBMmZFrDTgUz2HH8yhhbfqibdwfK14QtHRiQLYE1"),
+            ctx.accounts.authority.key() == ADMIN,
             CustomError::InvalidAuth
         );
         let cpi_accounts = TransferChecked {
@@ -77,15 +135,22 @@ pub mod swap {
         let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
         transfer_checked(cpi_ctx, amount, ctx.accounts.token_mint.decimals)?;
         let vault = &mut ctx.accounts.vault;
-        vault.total_tokens += amount;
+        vault.total_tokens = vault
+            .total_tokens
+            .checked_add(amount)
+            .ok_or(CustomError::Overflow)?;
         Ok(())
     }
     pub fn withdraw_tokens(ctx: Context<WithdrawTokens>, amount: u64) -> Result<()> {
      
         require!(
-            ctx.accounts.authority.key() == pubkey!("3c1gFBMmZFrDTgUz2HH8yhhbfqibdwfK14QtHRiQLYE1"),
+            ctx.accounts.authority.key() == ADMIN,
             CustomError::InvalidAuth
         );
+        require!(
+            ctx.accounts.vault.withdrawal_delay == 0,
+            CustomError::TimelockActive
+        );
         let cpi_accounts = TransferChecked {
             mint: ctx.accounts.token_mint.to_account_info(),
             from: ctx.accounts.vault_token_account.to_account_info(),
@@ -103,15 +168,24 @@ pub mod swap {
         let cpi_program = ctx.accounts.token_program.to_account_info();
         let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
         transfer_checked(cpi_ctx, amount, ctx.accounts.token_mint.decimals)?;
-        ctx.accounts.vault.total_tokens = ctx.accounts.vault.total_tokens.saturating_sub(amount);
+        ctx.accounts.vault.total_tokens = ctx
+            .accounts
+            .vault
+            .total_tokens
+            .checked_sub(amount)
+            .ok_or(CustomError::InsufficientTokens)?;
         Ok(())
     }
 
     pub fn transfer_from_vault(ctx: Context<TransferFromVault>, amount: u64) -> Result<()> {
         require!(
-            ctx.accounts.authority.key() == pubkey!("3c1gFBMmZFrDTgUz2HH8yhhbfqibdwfK14QtHRiQLYE1"),
+            ctx.accounts.authority.key() == ADMIN,
             CustomError::InvalidAuth
         );
+        require!(
+            ctx.accounts.vault.withdrawal_delay == 0,
+            CustomError::TimelockActive
+        );
         let cpi_accounts = TransferChecked {
             mint: ctx.accounts.token_mint.to_account_info(),
             from: ctx.accounts.vault_token_account.to_account_info(),
@@ -129,62 +203,144 @@ pub mod swap {
         let cpi_program = ctx.accounts.token_program.to_account_info();
         let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
         transfer_checked(cpi_ctx, amount, ctx.accounts.token_mint.decimals)?;
-        ctx.accounts.vault.total_tokens = ctx.accounts.vault.total_tokens.saturating_sub(amount);
+        ctx.accounts.vault.total_tokens = ctx
+            .accounts
+            .vault
+            .total_tokens
+            .checked_sub(amount)
+            .ok_or(CustomError::InsufficientTokens)?;
+        Ok(())
+    }
+    /// Sends `amounts[i]` to the i-th remaining account, which must be the recipient's
+    /// associated token account for the vault mint.
+    pub fn distribute_from_vault<'info>(
+        ctx: Context<'_, '_, 'info, 'info, DistributeFromVault<'info>>,
+        amounts: Vec<u64>,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ADMIN,
+            CustomError::InvalidAuth
+        );
+        require!(
+            ctx.accounts.vault.withdrawal_delay == 0,
+            CustomError::TimelockActive
+        );
+        require!(
+            !amounts.is_empty() && amounts.len() == ctx.remaining_accounts.len(),
+            CustomError::InvalidDistribution
+        );
+        let total = amounts
+            .iter()
+            .try_fold(0u64, |total, amount| total.checked_add(*amount))
+            .ok_or(CustomError::Overflow)?;
+        let remaining_tokens = ctx
+            .accounts
+            .vault
+            .total_tokens
+            .checked_sub(total)
+            .ok_or(CustomError::InsufficientTokens)?;
+
+        let vault_key = ctx.accounts.vault.key();
+        let mint = ctx.accounts.token_mint.key();
+        let token_program = ctx.accounts.token_program.key();
+        let seeds = &[
+            b"vault",
+            mint.as_ref(),
+            &ctx.accounts.vault.index.to_le_bytes(),
+            &[ctx.accounts.vault.bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+        for (destination, amount) in ctx.remaining_accounts.iter().zip(amounts) {
+            require!(amount > 0, CustomError::InvalidDistribution);
+            let recipient = {
+                let token_account = InterfaceAccount::<TokenAccount>::try_from(destination)?;
+                require_keys_eq!(token_account.mint, mint, CustomError::InvalidRecipient);
+                token_account.owner
+            };
+            require_keys_eq!(
+                destination.key(),
+                get_associated_token_address_with_program_id(&recipient, &mint, &token_program),
+                CustomError::InvalidRecipient
+            );
+
+            let cpi_accounts = TransferChecked {
+                mint: ctx.accounts.token_mint.to_account_info(),
+                from: ctx.accounts.vault_token_account.to_account_info(),
+                to: destination.clone(),
+                authority: ctx.accounts.vault_signer.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+            transfer_checked(cpi_ctx, amount, ctx.accounts.token_mint.decimals)?;
+            emit!(TokensDistributed {
+                vault: vault_key,
+                recipient,
+                token_account: destination.key(),
+                amount,
+            });
+        }
+        ctx.accounts.vault.total_tokens = remaining_tokens;
         Ok(())
     }
+    /// Buys `amount` tokens for `recipient`, who may differ from the paying `buyer`.
+    /// The purchase counts against the recipient's `BuyerRecord`.
     pub fn purchase_tokens(ctx: Context<PurchaseTokens>, amount: u64) -> Result<()> {
         let decimals = ctx.accounts.token_mint.decimals;
-        let tkn_amount = amount / 10u64.pow(decimals as u32);
+        let tkn_amount = amount
+            / 10u64
+                .checked_pow(decimals as u32)
+                .ok_or(CustomError::Overflow)?;
         require!(
             tkn_amount <= 1000000,
             CustomError::TokenLimit
         );
         let vault = &mut ctx.accounts.vault;
         let price_update = &ctx.accounts.sol_usd_price;
-        let maximum_age: u64 = 600;
-        let price_data = price_update.get_price_no_older_than(&Clock::get()?, maximum_age, &get_feed_id_from_hex(FEED_ID)?,)?;
+        vault.check_price_account(&price_update.key())?;
+        let price_data = price_update.get_price_no_older_than(&Clock::get()?, MAXIMUM_AGE, &get_feed_id_from_hex(FEED_ID)?,)?;
         let expo: i32 = price_data.exponent;
        
         require!(
             amount <= vault.total_tokens,
             CustomError::InsufficientTokens
         );
+        require!(
+            vault.total_tokens <= ctx.accounts.vault_token_account.amount,
+            CustomError::VaultOutOfSync
+        );
+        require!(!vault.auction_active, CustomError::AuctionActive);
+        // Purchase SOL goes to the admin, who could otherwise empty a timelocked
+        // vault at no cost.
+        if vault.withdrawal_delay > 0 {
+            require!(
+                ctx.accounts.buyer.key() != ADMIN && ctx.accounts.recipient.key() != ADMIN,
+                CustomError::AdminPurchase
+            );
+        }
+        if vault.gate_enabled {
+            let gate = ctx.accounts.gate.as_ref().ok_or(CustomError::GateRequired)?;
+            gate.verify(
+                &vault.key(),
+                &ctx.accounts.recipient.key(),
+                ctx.accounts.attestation.as_deref(),
+                ctx.accounts.instructions_sysvar.as_deref(),
+            )?;
+        }
    
-       let usdt_decimals = 6u32;
-      let sol_decimals = 9u32;
-     
-      // Use u128 for all arithmetic
-      let total_price = (tkn_amount as u128)
-          .checked_mul(vault.price_per_token as u128)
-          .ok_or(CustomError::Overflow)?;
-     
-      // price as positive u128
-      let price = price_data.price.abs() as u128;
-     
-      // Calculate denominator: price * 10^usdt_decimals
-      let denominator = price
-          .checked_mul(10u128.pow(usdt_decimals))
-          .ok_or(CustomError::Overflow)?;
-     
-      // Calculate numerator: total_price * 10^sol_decimals * 10^(-expo)
-      let mut numerator = total_price
-          .checked_mul(10u128.pow(sol_decimals))
-          .ok_or(CustomError::Overflow)?;
-     
-      if expo < 0 {
-          numerator = numerator
-              .checked_mul(10u128.pow((-expo) as u32))
-              .ok_or(CustomError::Overflow)?;
-      } else {
-          numerator = numerator
-              .checked_div(10u128.pow(expo as u32))
-              .ok_or(CustomError::Overflow)?;
-      }
-     
-      // Final division, then cast to u64
-      let amount_to_pay = numerator
-          .checked_div(denominator)
-          .ok_or(CustomError::Overflow)? as u64;
+        let total_price = (amount as u128)
+            .checked_mul(vault.price_per_token as u128)
+            .ok_or(CustomError::Overflow)?
+            / 10u128
+                .checked_pow(decimals as u32)
+                .ok_or(CustomError::Overflow)?;
+        let amount_to_pay = tokens_to_lamports(
+            amount,
+            vault.price_per_token,
+            price_data.price,
+            expo,
+            decimals,
+            true,
+        )?;
         let ix = system_instruction::transfer(
             ctx.accounts.buyer.key,
             ctx.accounts.admin.key,
@@ -216,11 +372,43 @@ pub mod swap {
         let cpi_program = ctx.accounts.token_program.to_account_info();
         let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
         transfer_checked(cpi_ctx, amount, ctx.accounts.token_mint.decimals)?;
-        vault.total_tokens -= amount;
+        vault.total_tokens = vault
+            .total_tokens
+            .checked_sub(amount)
+            .ok_or(CustomError::InsufficientTokens)?;
+
+        let buyer_record = &mut ctx.accounts.buyer_record;
+        if buyer_record.vault == Pubkey::default() {
+            buyer_record.vault = vault.key();
+            buyer_record.buyer = ctx.accounts.recipient.key();
+            buyer_record.bump = ctx.bumps.buyer_record;
+        }
+        let usd_value = u64::try_from(total_price).map_err(|_| CustomError::Overflow)?;
+        let clock = Clock::get()?;
+        let stats = &mut ctx.accounts.stats;
+        stats.enforce_limits(vault, buyer_record, amount, &clock)?;
+        stats.record_purchase(
+            buyer_record,
+            amount,
+            amount_to_pay,
+            usd_value,
+            clock.unix_timestamp,
+        )?;
+
+        emit!(TokensPurchased {
+            vault: vault.key(),
+            buyer: ctx.accounts.buyer.key(),
+            recipient: ctx.accounts.recipient.key(),
+            amount,
+            lamports: amount_to_pay,
+        });
         Ok(())
     }
     pub fn close_vault(ctx: Context<CloseVault>) -> Result<()> {
-
+        require!(
+            ctx.accounts.owner.key() == ADMIN,
+            CustomError::InvalidAuth
+        );
 
         let vault = &mut ctx.accounts.vault;
         // Transfer all tokens in the vault to the admin's account
@@ -232,6 +420,10 @@ pub mod swap {
 
 
         let amount = vault_token_account.amount;
+        require!(
+            amount == 0 || vault.withdrawal_delay == 0,
+            CustomError::TimelockActive
+        );
 
 
         if amount > 0 {
@@ -255,174 +447,1998 @@ pub mod swap {
         }
         Ok(())
     }
-}
-
 
-#[derive(Accounts)]
-#[instruction(index: u64)]
-pub struct Initialize<'info> {
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    #[account(
-        init,
-        seeds = [b"vault", token_mint.key().as_ref(), index.to_le_bytes().as_ref()],
-        bump,
-        payer = authority,
-        space = 8 + Vault::INIT_SPACE
-    )]
-    pub vault: Account<'info, Vault>,
-    pub token_mint: InterfaceAccount<'info, Mint>,
-    #[account(
-        init,
-        payer = authority,
-        associated_token::mint = token_mint,
-        associated_token::authority = vault,
-        associated_token::token_program = token_program
-    )]
-    pub vault_token_account: InterfaceAccount<'info, TokenAccount>,
-    pub system_program: Program<'info, System>,
-    pub token_program: Interface<'info, TokenInterface>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
-}
 
+    /// Reconciles `total_tokens` with the actual balance of the vault token account,
+    /// e.g. after tokens were sent to the ATA directly instead of through `deposit_tokens`.
+    pub fn sync_vault(ctx: Context<SyncVault>) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ADMIN,
+            CustomError::InvalidAuth
+        );
+        let vault = &mut ctx.accounts.vault;
+        let previous_total = vault.total_tokens;
+        vault.total_tokens = ctx.accounts.vault_token_account.amount;
 
-#[derive(Accounts)]
-pub struct UpdatePrice<'info> {
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    #[account(mut)]
-    pub vault: Account<'info, Vault>,
-}
+        emit!(VaultSynced {
+            vault: vault.key(),
+            previous_total,
+            total_tokens: vault.total_tokens,
+        });
+        Ok(())
+    }
 
 
-#[derive(Accounts)]
-pub struct DepositTokens<'info> {
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    #[account(mut)]
-    pub vault: Account<'info, Vault>,
-    pub token_mint: InterfaceAccount<'info, Mint>,
-    #[account(mut)]
-    pub admin_token_account: InterfaceAccount<'info, TokenAccount>,
-    #[account(mut)]
-    pub vault_token_account: InterfaceAccount<'info, TokenAccount>,
-    pub token_program: Interface<'info, TokenInterface>,
-}
+    /// Upgrades a vault to the current layout, growing legacy accounts to the
+    /// versioned size. New settings start out zeroed, i.e. disabled.
+    pub fn migrate_vault(ctx: Context<MigrateVault>) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ADMIN,
+            CustomError::InvalidAuth
+        );
+        let vault_info = ctx.accounts.vault.to_account_info();
+        let old_len = vault_info.data_len();
+        require!(
+            old_len >= LEGACY_VAULT_LEN && vault_info.try_borrow_data()?[..8] == *Vault::DISCRIMINATOR,
+            CustomError::InvalidVaultAccount
+        );
 
+        let new_len = 8 + Vault::INIT_SPACE;
+        if old_len < new_len {
+            let rent_due = Rent::get()?
+                .minimum_balance(new_len)
+                .saturating_sub(vault_info.lamports());
+            if rent_due > 0 {
+                let ix = system_instruction::transfer(
+                    ctx.accounts.authority.key,
+                    vault_info.key,
+                    rent_due,
+                );
+                anchor_lang::solana_program::program::invoke(
+                    &ix,
+                    &[ctx.accounts.authority.to_account_info(), vault_info.clone()],
+                )?;
+            }
+            vault_info.resize(new_len)?;
+        }
 
-#[derive(Accounts)]
-pub struct WithdrawTokens<'info> {
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    #[account(mut)]
-    pub vault: Account<'info, Vault>,
-    pub token_mint: InterfaceAccount<'info, Mint>,
-    #[account(mut)]
-    pub admin_token_account: InterfaceAccount<'info, TokenAccount>,
-    #[account(mut)]
-    pub vault_token_account: InterfaceAccount<'info, TokenAccount>,
-    /// CHECK: PDA signer for vault
-    #[account(
-        seeds = [b"vault", token_mint.key().as_ref(), vault.index.to_le_bytes().as_ref()],
-        bump = vault.bump
-    )]
-    pub vault_signer: UncheckedAccount<'info>,
-    pub token_program: Interface<'info, TokenInterface>,
-}
+        let mut data = vault_info.try_borrow_mut_data()?;
+        let mut vault = Vault::try_deserialize(&mut &data[..])?;
+        require!(vault.version < VAULT_VERSION, CustomError::AlreadyMigrated);
+        let previous_version = vault.version;
+        vault.version = VAULT_VERSION;
+        vault.try_serialize(&mut &mut data[..])?;
 
+        emit!(VaultMigrated {
+            vault: vault_info.key(),
+            previous_version,
+            version: VAULT_VERSION,
+        });
+        Ok(())
+    }
 
 
+    /// Creates the statistics account for vaults initialized before sale statistics existed.
+    pub fn initialize_stats(ctx: Context<InitializeStats>) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ADMIN,
+            CustomError::InvalidAuth
+        );
+        let stats = &mut ctx.accounts.stats;
+        stats.vault = ctx.accounts.vault.key();
+        stats.bump = ctx.bumps.stats;
+        Ok(())
+    }
 
-#[derive(Accounts)]
-pub struct PurchaseTokens<'info> {
-    #[account(mut)]
-    pub buyer: Signer<'info>,
-    /// CHECK: This is the admin recipient (must match ADMIN)
-    #[account(mut, address = pubkey!("3c1gFBMmZFrDTgUz2HH8yhhbfqibdwfK14QtHRiQLYE1"))]
-    pub admin: UncheckedAccount<'info>,
-    #[account(mut)]
-    pub vault: Account<'info, Vault>,
-    pub token_mint: InterfaceAccount<'info, Mint>,
-    #[account(mut)]
-    pub vault_token_account: InterfaceAccount<'info, TokenAccount>,
-    #[account(mut)]
-    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
-    /// CHECK: PDA signer for vault
-    #[account(
-        seeds = [b"vault", token_mint.key().as_ref(), vault.index.to_le_bytes().as_ref()],
-        bump = vault.bump
-    )]
-    pub vault_signer: UncheckedAccount<'info>,
-    pub token_program: Interface<'info, TokenInterface>,
-    pub system_program: Program<'info, System>,
 
+    /// Read-only view of the vault's sale statistics, returned through return data.
+    pub fn get_sale_stats(ctx: Context<GetSaleStats>) -> Result<SaleStats> {
+        Ok((*ctx.accounts.stats).clone())
+    }
 
-    // Add this line:
-    // Add this line:
-    /// CHECK: Pyth price account
-    #[account()]
-    pub sol_usd_price: Account<'info, PriceUpdateV2>,
-}
 
+    /// Commits the share of the raised SOL that `finalize_sale` will lock as liquidity.
+    /// The share can only be raised, so buyers can rely on the committed amount.
+    pub fn set_liquidity_bps(ctx: Context<ConfigureVault>, liquidity_bps: u16) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ADMIN,
+            CustomError::InvalidAuth
+        );
+        let vault = &mut ctx.accounts.vault;
+        require!(
+            liquidity_bps > 0
+                && liquidity_bps as u64 <= BPS_DENOMINATOR
+                && liquidity_bps >= vault.liquidity_bps,
+            CustomError::InvalidPercentage
+        );
+        vault.liquidity_bps = liquidity_bps;
+        Ok(())
+    }
 
-#[derive(Accounts)]
-pub struct CloseVault<'info> {
-    #[account(mut, close = owner)]
-    pub vault: Account<'info, Vault>,
-    #[account(mut)]
-    pub owner: Signer<'info>,
-    #[account(mut)]
-    pub vault_token_account: InterfaceAccount<'info, TokenAccount>,
-    #[account(mut)]
-    pub admin_token_account: InterfaceAccount<'info, TokenAccount>,
-    pub token_mint: InterfaceAccount<'info, Mint>,
-    /// CHECK: PDA signer for vault
-    #[account(
-        seeds = [b"vault", token_mint.key().as_ref(), vault.index.to_le_bytes().as_ref()],
-        bump = vault.bump
-    )]
-    pub vault_signer: UncheckedAccount<'info>,
-    pub token_program: Interface<'info, TokenInterface>,
-}
 
-#[derive(Accounts)]
-pub struct TransferFromVault<'info> {
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    #[account(mut)]
-    pub vault: Account<'info, Vault>,
-    #[account(mut)]
-    pub vault_token_account: InterfaceAccount<'info, TokenAccount>,
-    #[account(mut)]
-    pub destination_token_account: InterfaceAccount<'info, TokenAccount>,
-    pub token_mint: InterfaceAccount<'info, Mint>,
-    /// CHECK: PDA signer for vault
-    #[account(
-        seeds = [b"vault", token_mint.key().as_ref(), vault.index.to_le_bytes().as_ref()],
-        bump = vault.bump
-    )]
-    pub vault_signer: UncheckedAccount<'info>,
-    pub token_program: Interface<'info, TokenInterface>,
-}
+    /// Locks the vault's committed `liquidity_bps` of the SOL recorded in its sale
+    /// statistics, plus the tokens it buys at the current sale price, in a liquidity
+    /// PDA until `unlock_time`.
+    pub fn finalize_sale(ctx: Context<FinalizeSale>, unlock_time: i64) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ADMIN,
+            CustomError::InvalidAuth
+        );
+        let liquidity_bps = ctx.accounts.vault.liquidity_bps;
+        require!(liquidity_bps > 0, CustomError::InvalidPercentage);
+        let clock = Clock::get()?;
+        // The reserve is released by `withdraw_liquidity`, so it can't unlock sooner
+        // than a queued withdrawal would.
+        let earliest_unlock = clock
+            .unix_timestamp
+            .checked_add(ctx.accounts.vault.withdrawal_delay)
+            .ok_or(CustomError::Overflow)?;
+        require!(
+            unlock_time > clock.unix_timestamp && unlock_time >= earliest_unlock,
+            CustomError::InvalidUnlockTime
+        );
 
+        let lamports = (ctx.accounts.stats.lamports_raised as u128)
+            .checked_mul(liquidity_bps as u128)
+            .ok_or(CustomError::Overflow)?
+            / BPS_DENOMINATOR as u128;
+        let lamports = u64::try_from(lamports).map_err(|_| CustomError::Overflow)?;
 
-#[account]
-#[derive(InitSpace)]
-pub struct Vault {
-    pub index: u64,
-    pub token_mint: Pubkey,
-    pub vault_token_account: Pubkey,
-    pub price_per_token: u64,
-    pub total_tokens: u64,
-    pub bump: u8,
-    pub owner: Pubkey,
-}
+        ctx.accounts
+            .vault
+            .check_price_account(&ctx.accounts.sol_usd_price.key())?;
+        let price_data = ctx.accounts.sol_usd_price.get_price_no_older_than(
+            &clock,
+            MAXIMUM_AGE,
+            &get_feed_id_from_hex(FEED_ID)?,
+        )?;
+        let token_amount = lamports_to_tokens(
+            lamports,
+            ctx.accounts.vault.price_per_token,
+            price_data.price,
+            price_data.exponent,
+            ctx.accounts.token_mint.decimals,
+        )?;
+        require!(
+            token_amount <= ctx.accounts.vault.total_tokens,
+            CustomError::InsufficientTokens
+        );
 
+        let ix = system_instruction::transfer(
+            ctx.accounts.authority.key,
+            &ctx.accounts.liquidity_reserve.key(),
+            lamports,
+        );
+        anchor_lang::solana_program::program::invoke(
+            &ix,
+            &[
+                ctx.accounts.authority.to_account_info(),
+                ctx.accounts.liquidity_reserve.to_account_info(),
+            ],
+        )?;
 
-#[error_code]
-pub enum CustomError {
-    #[msg("Unauthorized User. Access Denied.")]
-    InvalidAuth,
+        let cpi_accounts = TransferChecked {
+            mint: ctx.accounts.token_mint.to_account_info(),
+            from: ctx.accounts.vault_token_account.to_account_info(),
+            to: ctx.accounts.liquidity_token_account.to_account_info(),
+            authority: ctx.accounts.vault_signer.to_account_info(),
+        };
+        let mint = ctx.accounts.token_mint.key();
+        let seeds = &[
+            b"vault",
+            mint.as_ref(),
+            &ctx.accounts.vault.index.to_le_bytes(),
+            &[ctx.accounts.vault.bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+        transfer_checked(cpi_ctx, token_amount, ctx.accounts.token_mint.decimals)?;
+        ctx.accounts.vault.total_tokens = ctx
+            .accounts
+            .vault
+            .total_tokens
+            .checked_sub(token_amount)
+            .ok_or(CustomError::InsufficientTokens)?;
+
+        let reserve = &mut ctx.accounts.liquidity_reserve;
+        reserve.vault = ctx.accounts.vault.key();
+        reserve.token_mint = mint;
+        reserve.lamports = lamports;
+        reserve.token_amount = token_amount;
+        reserve.unlock_time = unlock_time;
+        reserve.bump = ctx.bumps.liquidity_reserve;
+
+        emit!(LiquidityReserved {
+            vault: reserve.vault,
+            lamports,
+            token_amount,
+            unlock_time,
+        });
+        Ok(())
+    }
+
+
+    /// Releases a liquidity reserve to the admin once its unlock time has passed.
+    pub fn withdraw_liquidity(ctx: Context<WithdrawLiquidity>) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ADMIN,
+            CustomError::InvalidAuth
+        );
+        let reserve = &ctx.accounts.liquidity_reserve;
+        require!(
+            Clock::get()?.unix_timestamp >= reserve.unlock_time,
+            CustomError::LiquidityLocked
+        );
+
+        let vault_key = reserve.vault;
+        let seeds = &[b"liquidity", vault_key.as_ref(), &[reserve.bump]];
+        let signer_seeds = &[&seeds[..]];
+
+        let token_amount = ctx.accounts.liquidity_token_account.amount;
+        if token_amount > 0 {
+            let cpi_accounts = TransferChecked {
+                mint: ctx.accounts.token_mint.to_account_info(),
+                from: ctx.accounts.liquidity_token_account.to_account_info(),
+                to: ctx.accounts.admin_token_account.to_account_info(),
+                authority: ctx.accounts.liquidity_reserve.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+            transfer_checked(cpi_ctx, token_amount, ctx.accounts.token_mint.decimals)?;
+        }
+
+        let cpi_accounts = anchor_spl::token_interface::CloseAccount {
+            account: ctx.accounts.liquidity_token_account.to_account_info(),
+            destination: ctx.accounts.authority.to_account_info(),
+            authority: ctx.accounts.liquidity_reserve.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+        anchor_spl::token_interface::close_account(cpi_ctx)?;
+
+        // The reserved SOL is released when the reserve account is closed to the admin.
+        emit!(LiquidityReleased {
+            vault: vault_key,
+            lamports: reserve.lamports,
+            token_amount,
+        });
+        Ok(())
+    }
+
+
+    /// Opens a descending-price auction over the vault inventory, pausing
+    /// fixed-price purchases until it is settled. With `uniform_clearing`
+    /// payments are escrowed in the auction PDA so every buyer can be refunded
+    /// down to the final clearing price.
+    pub fn start_auction(ctx: Context<StartAuction>, params: AuctionParams) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ADMIN,
+            CustomError::InvalidAuth
+        );
+        let AuctionParams {
+            start_price,
+            floor_price,
+            price_decay,
+            decay_interval,
+            start_time,
+            end_time,
+            uniform_clearing,
+        } = params;
+        require!(
+            floor_price > 0 && floor_price <= start_price,
+            CustomError::InvalidAuctionConfig
+        );
+        require!(
+            decay_interval > 0 && end_time > start_time,
+            CustomError::InvalidAuctionConfig
+        );
+        // Auction prices take effect no sooner than a price change would.
+        let earliest_start = Clock::get()?
+            .unix_timestamp
+            .checked_add(ctx.accounts.vault.withdrawal_delay)
+            .ok_or(CustomError::Overflow)?;
+        require!(
+            ctx.accounts.vault.withdrawal_delay == 0 || start_time >= earliest_start,
+            CustomError::InvalidAuctionConfig
+        );
+
+        let auction = &mut ctx.accounts.auction;
+        auction.vault = ctx.accounts.vault.key();
+        auction.start_price = start_price;
+        auction.floor_price = floor_price;
+        auction.price_decay = price_decay;
+        auction.decay_interval = decay_interval;
+        auction.start_time = start_time;
+        auction.end_time = end_time;
+        auction.uniform_clearing = uniform_clearing;
+        auction.settled = false;
+        auction.clearing_price = 0;
+        auction.last_price = start_price;
+        auction.tokens_sold = 0;
+        auction.lamports_escrowed = 0;
+        auction.weighted_lamports = 0;
+        auction.proceeds_withdrawn = false;
+        auction.bump = ctx.bumps.auction;
+        ctx.accounts.vault.auction_active = true;
+        Ok(())
+    }
+
+
+    pub fn purchase_auction_tokens(ctx: Context<PurchaseAuctionTokens>, amount: u64) -> Result<()> {
+        let decimals = ctx.accounts.token_mint.decimals;
+        let tkn_amount = amount
+            / 10u64
+                .checked_pow(decimals as u32)
+                .ok_or(CustomError::Overflow)?;
+        require!(
+            tkn_amount <= 1000000,
+            CustomError::TokenLimit
+        );
+        require!(
+            amount <= ctx.accounts.vault.total_tokens,
+            CustomError::InsufficientTokens
+        );
+        require!(
+            ctx.accounts.vault.total_tokens <= ctx.accounts.vault_token_account.amount,
+            CustomError::VaultOutOfSync
+        );
+
+        let clock = Clock::get()?;
+        let auction = &ctx.accounts.auction;
+        require!(
+            !auction.settled
+                && clock.unix_timestamp >= auction.start_time
+                && clock.unix_timestamp < auction.end_time,
+            CustomError::AuctionNotActive
+        );
+        let price_per_token = auction.current_price(clock.unix_timestamp);
+        // Auction tokens always go to the buyer's own account, so the buyer is the recipient.
+        if ctx.accounts.vault.withdrawal_delay > 0 {
+            require!(
+                ctx.accounts.buyer.key() != ADMIN,
+                CustomError::AdminPurchase
+            );
+        }
+        if ctx.accounts.vault.gate_enabled {
+            let gate = ctx.accounts.gate.as_ref().ok_or(CustomError::GateRequired)?;
+            gate.verify(
+                &ctx.accounts.vault.key(),
+                &ctx.accounts.buyer.key(),
+                ctx.accounts.attestation.as_deref(),
+                ctx.accounts.instructions_sysvar.as_deref(),
+            )?;
+        }
+
+        ctx.accounts
+            .vault
+            .check_price_account(&ctx.accounts.sol_usd_price.key())?;
+        let price_data = ctx.accounts.sol_usd_price.get_price_no_older_than(
+            &clock,
+            MAXIMUM_AGE,
+            &get_feed_id_from_hex(FEED_ID)?,
+        )?;
+        let total_price = (amount as u128)
+            .checked_mul(price_per_token as u128)
+            .ok_or(CustomError::Overflow)?
+            / 10u128
+                .checked_pow(decimals as u32)
+                .ok_or(CustomError::Overflow)?;
+        let amount_to_pay = tokens_to_lamports(
+            amount,
+            price_per_token,
+            price_data.price,
+            price_data.exponent,
+            decimals,
+            true,
+        )?;
+
+        // Uniform-clearing payments stay in escrow until the auction is settled.
+        let recipient = if auction.uniform_clearing {
+            ctx.accounts.auction.to_account_info()
+        } else {
+            ctx.accounts.admin.to_account_info()
+        };
+        let ix = system_instruction::transfer(ctx.accounts.buyer.key, recipient.key, amount_to_pay);
+        anchor_lang::solana_program::program::invoke(
+            &ix,
+            &[ctx.accounts.buyer.to_account_info(), recipient],
+        )?;
+
+        let cpi_accounts = TransferChecked {
+            mint: ctx.accounts.token_mint.to_account_info(),
+            from: ctx.accounts.vault_token_account.to_account_info(),
+            to: ctx.accounts.user_token_account.to_account_info(),
+            authority: ctx.accounts.vault_signer.to_account_info(),
+        };
+        let mint = ctx.accounts.token_mint.key();
+        let seeds = &[
+            b"vault",
+            mint.as_ref(),
+            &ctx.accounts.vault.index.to_le_bytes(),
+            &[ctx.accounts.vault.bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+        transfer_checked(cpi_ctx, amount, decimals)?;
+        ctx.accounts.vault.total_tokens = ctx
+            .accounts
+            .vault
+            .total_tokens
+            .checked_sub(amount)
+            .ok_or(CustomError::InsufficientTokens)?;
+
+        // lamports / price, rounded up so refunds can never exceed the escrow
+        let weighted = (amount_to_pay as u128)
+            .checked_mul(AUCTION_WEIGHT_SCALE)
+            .ok_or(CustomError::Overflow)?
+            .div_ceil(price_per_token as u128);
+
+        let auction = &mut ctx.accounts.auction;
+        auction.last_price = price_per_token;
+        auction.tokens_sold = auction
+            .tokens_sold
+            .checked_add(amount)
+            .ok_or(CustomError::Overflow)?;
+        if auction.uniform_clearing {
+            auction.lamports_escrowed = auction
+                .lamports_escrowed
+                .checked_add(amount_to_pay)
+                .ok_or(CustomError::Overflow)?;
+            auction.weighted_lamports = auction
+                .weighted_lamports
+                .checked_add(weighted)
+                .ok_or(CustomError::Overflow)?;
+        }
+
+        let bid = &mut ctx.accounts.bid;
+        if bid.auction == Pubkey::default() {
+            bid.auction = auction.key();
+            bid.buyer = ctx.accounts.buyer.key();
+            bid.bump = ctx.bumps.bid;
+        }
+        bid.tokens = bid.tokens.checked_add(amount).ok_or(CustomError::Overflow)?;
+        bid.lamports_paid = bid
+            .lamports_paid
+            .checked_add(amount_to_pay)
+            .ok_or(CustomError::Overflow)?;
+        bid.weighted_lamports = bid
+            .weighted_lamports
+            .checked_add(weighted)
+            .ok_or(CustomError::Overflow)?;
+
+        let buyer_record = &mut ctx.accounts.buyer_record;
+        if buyer_record.vault == Pubkey::default() {
+            buyer_record.vault = ctx.accounts.vault.key();
+            buyer_record.buyer = ctx.accounts.buyer.key();
+            buyer_record.bump = ctx.bumps.buyer_record;
+        }
+        let usd_value = u64::try_from(total_price).map_err(|_| CustomError::Overflow)?;
+        let stats = &mut ctx.accounts.stats;
+        stats.enforce_limits(&ctx.accounts.vault, buyer_record, amount, &clock)?;
+        stats.record_purchase(
+            buyer_record,
+            amount,
+            amount_to_pay,
+            usd_value,
+            clock.unix_timestamp,
+        )?;
+        Ok(())
+    }
+
+
+    /// Fixes the clearing price once the auction has ended or the vault is sold out.
+    pub fn settle_auction(ctx: Context<SettleAuction>) -> Result<()> {
+        let auction = &mut ctx.accounts.auction;
+        require!(!auction.settled, CustomError::AuctionSettled);
+        require!(
+            Clock::get()?.unix_timestamp >= auction.end_time
+                || ctx.accounts.vault.total_tokens == 0,
+            CustomError::AuctionNotEnded
+        );
+        // Prices only ever decrease, so the last sale price is the lowest one.
+        auction.clearing_price = auction.last_price;
+        auction.settled = true;
+        ctx.accounts.vault.auction_active = false;
+
+        // Escrowed payments above the clearing price go back to bidders, so only
+        // the proceeds count towards the SOL raised.
+        if auction.uniform_clearing {
+            let proceeds = auction
+                .clearing_cost(auction.weighted_lamports)?
+                .min(auction.lamports_escrowed);
+            let refunds = auction.lamports_escrowed - proceeds;
+            let stats = &mut ctx.accounts.stats;
+            stats.lamports_raised = stats
+                .lamports_raised
+                .checked_sub(refunds)
+                .ok_or(CustomError::Overflow)?;
+        }
+
+        emit!(AuctionSettled {
+            vault: auction.vault,
+            clearing_price: auction.clearing_price,
+            tokens_sold: auction.tokens_sold,
+        });
+        Ok(())
+    }
+
+
+    /// Closes a bid once the auction is settled, refunding a uniform-clearing
+    /// bid down to the clearing price. Other bids only get their rent back.
+    pub fn claim_auction_refund(ctx: Context<ClaimAuctionRefund>) -> Result<()> {
+        let auction = &ctx.accounts.auction;
+        require!(auction.settled, CustomError::AuctionNotSettled);
+        if !auction.uniform_clearing {
+            return Ok(());
+        }
+        let bid = &ctx.accounts.bid;
+        let owed = auction.clearing_cost(bid.weighted_lamports)?;
+        let refund = bid.lamports_paid.saturating_sub(owed);
+
+        if refund > 0 {
+            ctx.accounts.auction.sub_lamports(refund)?;
+            ctx.accounts.buyer.add_lamports(refund)?;
+        }
+        Ok(())
+    }
+
+
+    pub fn withdraw_auction_proceeds(ctx: Context<WithdrawAuctionProceeds>) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ADMIN,
+            CustomError::InvalidAuth
+        );
+        let auction = &mut ctx.accounts.auction;
+        require!(
+            auction.settled && auction.uniform_clearing,
+            CustomError::AuctionNotSettled
+        );
+        require!(!auction.proceeds_withdrawn, CustomError::AuctionSettled);
+
+        let proceeds = auction
+            .clearing_cost(auction.weighted_lamports)?
+            .min(auction.lamports_escrowed);
+        auction.proceeds_withdrawn = true;
+
+        ctx.accounts.auction.sub_lamports(proceeds)?;
+        ctx.accounts.authority.add_lamports(proceeds)?;
+        Ok(())
+    }
+
+
+    /// Configures buyback mode. Holders are paid `buyback_price` per token, or
+    /// the sale price less `discount_bps` when `buyback_price` is zero.
+    pub fn configure_buyback(
+        ctx: Context<ConfigureBuyback>,
+        buyback_price: u64,
+        discount_bps: u16,
+        daily_cap: u64,
+        enabled: bool,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ADMIN,
+            CustomError::InvalidAuth
+        );
+        require!(
+            discount_bps as u64 <= BPS_DENOMINATOR,
+            CustomError::InvalidPercentage
+        );
+        let buyback = &mut ctx.accounts.buyback;
+        if buyback.vault == Pubkey::default() {
+            buyback.vault = ctx.accounts.vault.key();
+            buyback.bump = ctx.bumps.buyback;
+        }
+        buyback.buyback_price = buyback_price;
+        buyback.discount_bps = discount_bps;
+        buyback.daily_cap = daily_cap;
+        if buyback.enabled && !enabled {
+            buyback.disabled_at = Clock::get()?.unix_timestamp;
+        }
+        buyback.enabled = enabled;
+        Ok(())
+    }
+
+
+    pub fn fund_buyback(ctx: Context<BuybackReserve>, lamports: u64) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ADMIN,
+            CustomError::InvalidAuth
+        );
+        let ix = system_instruction::transfer(
+            ctx.accounts.authority.key,
+            &ctx.accounts.buyback.key(),
+            lamports,
+        );
+        anchor_lang::solana_program::program::invoke(
+            &ix,
+            &[
+                ctx.accounts.authority.to_account_info(),
+                ctx.accounts.buyback.to_account_info(),
+            ],
+        )?;
+        let buyback = &mut ctx.accounts.buyback;
+        buyback.reserve_lamports = buyback
+            .reserve_lamports
+            .checked_add(lamports)
+            .ok_or(CustomError::Overflow)?;
+        Ok(())
+    }
+
+
+    /// Returns reserve SOL to the admin. Under a withdrawal timelock the buyback must
+    /// have been disabled for the full delay, so sellers get the same notice as buyers.
+    pub fn withdraw_buyback(ctx: Context<BuybackReserve>, lamports: u64) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ADMIN,
+            CustomError::InvalidAuth
+        );
+        let delay = ctx.accounts.vault.withdrawal_delay;
+        let buyback = &mut ctx.accounts.buyback;
+        if delay > 0 {
+            require!(!buyback.enabled, CustomError::BuybackActive);
+            require!(
+                Clock::get()?.unix_timestamp >= buyback.disabled_at.saturating_add(delay),
+                CustomError::TimelockNotExpired
+            );
+        }
+        buyback.reserve_lamports = buyback
+            .reserve_lamports
+            .checked_sub(lamports)
+            .ok_or(CustomError::InsufficientReserve)?;
+        ctx.accounts.buyback.sub_lamports(lamports)?;
+        ctx.accounts.authority.add_lamports(lamports)?;
+        Ok(())
+    }
+
+
+    /// Sells tokens back into the vault for SOL from the buyback reserve.
+    pub fn sell_tokens(ctx: Context<SellTokens>, amount: u64) -> Result<()> {
+        let buyback = &ctx.accounts.buyback;
+        require!(buyback.enabled, CustomError::BuybackDisabled);
+
+        let clock = Clock::get()?;
+        let price_per_token = buyback.sell_price(ctx.accounts.vault.price_per_token)?;
+        let decimals = ctx.accounts.token_mint.decimals;
+        ctx.accounts
+            .vault
+            .check_price_account(&ctx.accounts.sol_usd_price.key())?;
+        let price_data = ctx.accounts.sol_usd_price.get_price_no_older_than(
+            &clock,
+            MAXIMUM_AGE,
+            &get_feed_id_from_hex(FEED_ID)?,
+        )?;
+        let lamports = tokens_to_lamports(
+            amount,
+            price_per_token,
+            price_data.price,
+            price_data.exponent,
+            decimals,
+            false,
+        )?;
+
+        let day = clock.unix_timestamp / SECONDS_PER_DAY;
+        let buyback = &mut ctx.accounts.buyback;
+        if buyback.current_day != day {
+            buyback.current_day = day;
+            buyback.day_volume = 0;
+        }
+        buyback.day_volume = buyback
+            .day_volume
+            .checked_add(amount)
+            .ok_or(CustomError::Overflow)?;
+        require!(
+            buyback.day_volume <= buyback.daily_cap,
+            CustomError::DailyCapExceeded
+        );
+        buyback.reserve_lamports = buyback
+            .reserve_lamports
+            .checked_sub(lamports)
+            .ok_or(CustomError::InsufficientReserve)?;
+
+        let cpi_accounts = TransferChecked {
+            mint: ctx.accounts.token_mint.to_account_info(),
+            from: ctx.accounts.seller_token_account.to_account_info(),
+            to: ctx.accounts.vault_token_account.to_account_info(),
+            authority: ctx.accounts.seller.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        transfer_checked(cpi_ctx, amount, decimals)?;
+        let vault = &mut ctx.accounts.vault;
+        vault.total_tokens = vault
+            .total_tokens
+            .checked_add(amount)
+            .ok_or(CustomError::Overflow)?;
+
+        ctx.accounts.buyback.sub_lamports(lamports)?;
+        ctx.accounts.seller.add_lamports(lamports)?;
+
+        emit!(TokensSold {
+            vault: vault.key(),
+            seller: ctx.accounts.seller.key(),
+            amount,
+            lamports,
+            price_per_token,
+        });
+        Ok(())
+    }
+
+
+    /// Enables or lengthens the withdrawal timelock. The delay can't be shortened,
+    /// otherwise the admin could drop it right before withdrawing.
+    pub fn set_withdrawal_delay(ctx: Context<ConfigureVault>, delay: i64) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ADMIN,
+            CustomError::InvalidAuth
+        );
+        let vault = &mut ctx.accounts.vault;
+        require!(
+            delay >= vault.withdrawal_delay,
+            CustomError::TimelockDecrease
+        );
+        vault.withdrawal_delay = delay;
+        Ok(())
+    }
+
+
+    pub fn queue_withdrawal(
+        ctx: Context<QueueWithdrawal>,
+        amount: u64,
+        destination: Pubkey,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ADMIN,
+            CustomError::InvalidAuth
+        );
+        let vault = &mut ctx.accounts.vault;
+        let eta = Clock::get()?
+            .unix_timestamp
+            .checked_add(vault.withdrawal_delay)
+            .ok_or(CustomError::Overflow)?;
+
+        let pending = &mut ctx.accounts.pending_withdrawal;
+        pending.vault = vault.key();
+        pending.nonce = vault.withdrawal_nonce;
+        pending.amount = amount;
+        pending.destination = destination;
+        pending.eta = eta;
+        pending.bump = ctx.bumps.pending_withdrawal;
+        vault.withdrawal_nonce = vault
+            .withdrawal_nonce
+            .checked_add(1)
+            .ok_or(CustomError::Overflow)?;
+
+        emit!(WithdrawalQueued {
+            vault: pending.vault,
+            nonce: pending.nonce,
+            amount,
+            destination,
+            eta,
+        });
+        Ok(())
+    }
+
+
+    pub fn execute_withdrawal(ctx: Context<ExecuteWithdrawal>) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ADMIN,
+            CustomError::InvalidAuth
+        );
+        let pending = &ctx.accounts.pending_withdrawal;
+        require!(
+            Clock::get()?.unix_timestamp >= pending.eta,
+            CustomError::TimelockNotExpired
+        );
+
+        let cpi_accounts = TransferChecked {
+            mint: ctx.accounts.token_mint.to_account_info(),
+            from: ctx.accounts.vault_token_account.to_account_info(),
+            to: ctx.accounts.destination_token_account.to_account_info(),
+            authority: ctx.accounts.vault_signer.to_account_info(),
+        };
+        let mint = ctx.accounts.token_mint.key();
+        let seeds = &[
+            b"vault",
+            mint.as_ref(),
+            &ctx.accounts.vault.index.to_le_bytes(),
+            &[ctx.accounts.vault.bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+        transfer_checked(cpi_ctx, pending.amount, ctx.accounts.token_mint.decimals)?;
+        ctx.accounts.vault.total_tokens = ctx
+            .accounts
+            .vault
+            .total_tokens
+            .checked_sub(pending.amount)
+            .ok_or(CustomError::InsufficientTokens)?;
+
+        emit!(WithdrawalExecuted {
+            vault: pending.vault,
+            nonce: pending.nonce,
+            amount: pending.amount,
+            destination: pending.destination,
+        });
+        Ok(())
+    }
+
+
+    pub fn cancel_withdrawal(ctx: Context<CancelWithdrawal>) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ADMIN,
+            CustomError::InvalidAuth
+        );
+        let pending = &ctx.accounts.pending_withdrawal;
+        emit!(WithdrawalCancelled {
+            vault: pending.vault,
+            nonce: pending.nonce,
+            amount: pending.amount,
+        });
+        Ok(())
+    }
+
+
+    /// Restricts purchases to verified recipients, either through an attestation
+    /// account issued by `issuer` or an ed25519 voucher signed by `issuer`.
+    pub fn configure_gate(
+        ctx: Context<ConfigureGate>,
+        kind: GateKind,
+        issuer: Pubkey,
+        issuer_program: Pubkey,
+        seeds: Vec<GateSeed>,
+        expiry_offset: Option<u16>,
+        enabled: bool,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ADMIN,
+            CustomError::InvalidAuth
+        );
+        require!(
+            seeds.len() <= MAX_GATE_SEEDS
+                && seeds.iter().all(|seed| match seed {
+                    GateSeed::Literal { bytes } => bytes.len() <= MAX_GATE_SEED_LEN,
+                    _ => true,
+                }),
+            CustomError::InvalidGateConfig
+        );
+        // The attestation address is all that ties it to the issuer and the
+        // recipient, so both must be part of its derivation.
+        require!(
+            kind != GateKind::Attestation
+                || (seeds.contains(&GateSeed::Issuer) && seeds.contains(&GateSeed::Recipient)),
+            CustomError::InvalidGateConfig
+        );
+        let gate = &mut ctx.accounts.gate;
+        if gate.vault == Pubkey::default() {
+            gate.vault = ctx.accounts.vault.key();
+            gate.bump = ctx.bumps.gate;
+        }
+        gate.kind = kind;
+        gate.issuer = issuer;
+        gate.issuer_program = issuer_program;
+        gate.seeds = seeds;
+        gate.expiry_offset = expiry_offset;
+        ctx.accounts.vault.gate_enabled = enabled;
+        Ok(())
+    }
+
+
+    /// Sets the anti-bot limits; pass zero to disable any of them.
+    pub fn configure_anti_bot(
+        ctx: Context<ConfigureVault>,
+        purchase_cooldown: i64,
+        max_tokens_per_slot: u64,
+        max_tokens_per_minute: u64,
+        launch_window_end: i64,
+        launch_max_per_purchase: u64,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ADMIN,
+            CustomError::InvalidAuth
+        );
+        require!(purchase_cooldown >= 0, CustomError::InvalidRateLimit);
+        let vault = &mut ctx.accounts.vault;
+        vault.purchase_cooldown = purchase_cooldown;
+        vault.max_tokens_per_slot = max_tokens_per_slot;
+        vault.max_tokens_per_minute = max_tokens_per_minute;
+        vault.launch_window_end = launch_window_end;
+        vault.launch_max_per_purchase = launch_max_per_purchase;
+        Ok(())
+    }
+
+
+    /// Pins the vault's price source to the pyth-push-oracle feed for `shard_id`,
+    /// or accepts any `PriceUpdateV2` for the feed when `None`.
+    pub fn set_price_feed_shard(ctx: Context<ConfigureVault>, shard_id: Option<u16>) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ADMIN,
+            CustomError::InvalidAuth
+        );
+        let vault = &mut ctx.accounts.vault;
+        vault.require_push_feed = shard_id.is_some();
+        vault.push_feed_shard = shard_id.unwrap_or_default();
+        Ok(())
+    }
+
+
+    /// Caps the tokens any one recipient can buy across all purchases, whoever pays;
+    /// zero disables the cap.
+    pub fn set_wallet_cap(ctx: Context<ConfigureVault>, max_tokens_per_wallet: u64) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ADMIN,
+            CustomError::InvalidAuth
+        );
+        ctx.accounts.vault.max_tokens_per_wallet = max_tokens_per_wallet;
+        Ok(())
+    }
+}
+
+
+#[derive(Accounts)]
+#[instruction(index: u64)]
+pub struct Initialize<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        init,
+        seeds = [b"vault", token_mint.key().as_ref(), index.to_le_bytes().as_ref()],
+        bump,
+        payer = authority,
+        space = 8 + Vault::INIT_SPACE
+    )]
+    pub vault: Account<'info, Vault>,
+    pub token_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        init,
+        payer = authority,
+        associated_token::mint = token_mint,
+        associated_token::authority = vault,
+        associated_token::token_program = token_program
+    )]
+    pub vault_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init,
+        seeds = [b"stats", vault.key().as_ref()],
+        bump,
+        payer = authority,
+        space = 8 + SaleStats::INIT_SPACE
+    )]
+    pub stats: Account<'info, SaleStats>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+
+#[derive(Accounts)]
+pub struct UpdatePrice<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub vault: Account<'info, Vault>,
+}
+
+
+#[derive(Accounts)]
+pub struct DepositTokens<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub vault: Account<'info, Vault>,
+    pub token_mint: InterfaceAccount<'info, Mint>,
+    #[account(mut)]
+    pub admin_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, address = vault.vault_token_account)]
+    pub vault_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+
+#[derive(Accounts)]
+pub struct WithdrawTokens<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub vault: Account<'info, Vault>,
+    pub token_mint: InterfaceAccount<'info, Mint>,
+    #[account(mut)]
+    pub admin_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, address = vault.vault_token_account)]
+    pub vault_token_account: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: PDA signer for vault
+    #[account(
+        seeds = [b"vault", token_mint.key().as_ref(), vault.index.to_le_bytes().as_ref()],
+        bump = vault.bump
+    )]
+    pub vault_signer: UncheckedAccount<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+
+
+
+#[derive(Accounts)]
+pub struct PurchaseTokens<'info> {
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+    /// CHECK: This is the admin recipient (must match ADMIN)
+    #[account(mut, address = ADMIN)]
+    pub admin: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub vault: Account<'info, Vault>,
+    #[account(
+        mut,
+        seeds = [b"stats", vault.key().as_ref()],
+        bump = stats.bump,
+    )]
+    pub stats: Account<'info, SaleStats>,
+    /// CHECK: Wallet receiving the tokens; may be the buyer itself
+    pub recipient: UncheckedAccount<'info>,
+    #[account(
+        init_if_needed,
+        seeds = [b"buyer", vault.key().as_ref(), recipient.key().as_ref()],
+        bump,
+        payer = buyer,
+        space = 8 + BuyerRecord::INIT_SPACE
+    )]
+    pub buyer_record: Account<'info, BuyerRecord>,
+    pub token_mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, address = vault.vault_token_account)]
+    pub vault_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        associated_token::mint = token_mint,
+        associated_token::authority = recipient,
+        associated_token::token_program = token_program
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: PDA signer for vault
+    #[account(
+        seeds = [b"vault", token_mint.key().as_ref(), vault.index.to_le_bytes().as_ref()],
+        bump = vault.bump
+    )]
+    pub vault_signer: UncheckedAccount<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+
+
+    // Add this line:
+    // Add this line:
+    /// CHECK: Pyth price account
+    #[account()]
+    pub sol_usd_price: Account<'info, PriceUpdateV2>,
+    #[account(
+        seeds = [b"gate", vault.key().as_ref()],
+        bump = gate.bump,
+    )]
+    pub gate: Option<Account<'info, GateConfig>>,
+    /// CHECK: Recipient's attestation, validated against the gate config
+    pub attestation: Option<UncheckedAccount<'info>>,
+    /// CHECK: Instructions sysvar, used to find the ed25519 voucher
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: Option<UncheckedAccount<'info>>,
+}
+
+
+#[derive(Accounts)]
+pub struct ConfigureGate<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub vault: Account<'info, Vault>,
+    #[account(
+        init_if_needed,
+        seeds = [b"gate", vault.key().as_ref()],
+        bump,
+        payer = authority,
+        space = 8 + GateConfig::INIT_SPACE
+    )]
+    pub gate: Account<'info, GateConfig>,
+    pub system_program: Program<'info, System>,
+}
+
+
+#[derive(Accounts)]
+pub struct CloseVault<'info> {
+    #[account(mut, close = owner)]
+    pub vault: Account<'info, Vault>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(mut, address = vault.vault_token_account)]
+    pub vault_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = token_mint,
+        token::authority = owner,
+        token::token_program = token_program
+    )]
+    pub admin_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = vault.token_mint)]
+    pub token_mint: InterfaceAccount<'info, Mint>,
+    /// CHECK: PDA signer for vault
+    #[account(
+        seeds = [b"vault", token_mint.key().as_ref(), vault.index.to_le_bytes().as_ref()],
+        bump = vault.bump
+    )]
+    pub vault_signer: UncheckedAccount<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct TransferFromVault<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub vault: Account<'info, Vault>,
+    #[account(mut, address = vault.vault_token_account)]
+    pub vault_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub destination_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub token_mint: InterfaceAccount<'info, Mint>,
+    /// CHECK: PDA signer for vault
+    #[account(
+        seeds = [b"vault", token_mint.key().as_ref(), vault.index.to_le_bytes().as_ref()],
+        bump = vault.bump
+    )]
+    pub vault_signer: UncheckedAccount<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+
+#[derive(Accounts)]
+pub struct DistributeFromVault<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub vault: Account<'info, Vault>,
+    #[account(mut, address = vault.vault_token_account)]
+    pub vault_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub token_mint: InterfaceAccount<'info, Mint>,
+    /// CHECK: PDA signer for vault
+    #[account(
+        seeds = [b"vault", token_mint.key().as_ref(), vault.index.to_le_bytes().as_ref()],
+        bump = vault.bump
+    )]
+    pub vault_signer: UncheckedAccount<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct SyncVault<'info> {
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub vault: Account<'info, Vault>,
+    #[account(address = vault.vault_token_account)]
+    pub vault_token_account: InterfaceAccount<'info, TokenAccount>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateVault<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: Legacy vaults can't be deserialized as `Vault`; the discriminator is checked in the handler
+    #[account(mut, owner = crate::ID)]
+    pub vault: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeStats<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub vault: Account<'info, Vault>,
+    #[account(
+        init,
+        seeds = [b"stats", vault.key().as_ref()],
+        bump,
+        payer = authority,
+        space = 8 + SaleStats::INIT_SPACE
+    )]
+    pub stats: Account<'info, SaleStats>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct GetSaleStats<'info> {
+    pub vault: Account<'info, Vault>,
+    #[account(
+        seeds = [b"stats", vault.key().as_ref()],
+        bump = stats.bump,
+    )]
+    pub stats: Account<'info, SaleStats>,
+}
+
+#[derive(Accounts)]
+pub struct FinalizeSale<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub vault: Account<'info, Vault>,
+    #[account(
+        seeds = [b"stats", vault.key().as_ref()],
+        bump = stats.bump,
+    )]
+    pub stats: Account<'info, SaleStats>,
+    #[account(address = vault.token_mint)]
+    pub token_mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, address = vault.vault_token_account)]
+    pub vault_token_account: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: PDA signer for vault
+    #[account(
+        seeds = [b"vault", token_mint.key().as_ref(), vault.index.to_le_bytes().as_ref()],
+        bump = vault.bump
+    )]
+    pub vault_signer: UncheckedAccount<'info>,
+    #[account(
+        init,
+        seeds = [b"liquidity", vault.key().as_ref()],
+        bump,
+        payer = authority,
+        space = 8 + LiquidityReserve::INIT_SPACE
+    )]
+    pub liquidity_reserve: Account<'info, LiquidityReserve>,
+    #[account(
+        init,
+        payer = authority,
+        associated_token::mint = token_mint,
+        associated_token::authority = liquidity_reserve,
+        associated_token::token_program = token_program
+    )]
+    pub liquidity_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub sol_usd_price: Account<'info, PriceUpdateV2>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawLiquidity<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"liquidity", liquidity_reserve.vault.as_ref()],
+        bump = liquidity_reserve.bump,
+        has_one = token_mint,
+    )]
+    pub liquidity_reserve: Account<'info, LiquidityReserve>,
+    pub token_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = token_mint,
+        associated_token::authority = liquidity_reserve,
+        associated_token::token_program = token_program
+    )]
+    pub liquidity_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub admin_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+
+#[derive(Accounts)]
+pub struct StartAuction<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub vault: Account<'info, Vault>,
+    #[account(
+        init,
+        seeds = [b"auction", vault.key().as_ref()],
+        bump,
+        payer = authority,
+        space = 8 + Auction::INIT_SPACE
+    )]
+    pub auction: Account<'info, Auction>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct PurchaseAuctionTokens<'info> {
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+    /// CHECK: This is the admin recipient (must match ADMIN)
+    #[account(mut, address = ADMIN)]
+    pub admin: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub vault: Account<'info, Vault>,
+    #[account(
+        mut,
+        seeds = [b"auction", vault.key().as_ref()],
+        bump = auction.bump,
+    )]
+    pub auction: Account<'info, Auction>,
+    #[account(
+        init_if_needed,
+        seeds = [b"bid", auction.key().as_ref(), buyer.key().as_ref()],
+        bump,
+        payer = buyer,
+        space = 8 + AuctionBid::INIT_SPACE
+    )]
+    pub bid: Account<'info, AuctionBid>,
+    #[account(
+        mut,
+        seeds = [b"stats", vault.key().as_ref()],
+        bump = stats.bump,
+    )]
+    pub stats: Account<'info, SaleStats>,
+    /// Keyed on the buyer as the recipient of the auction tokens, like `PurchaseTokens`.
+    #[account(
+        init_if_needed,
+        seeds = [b"buyer", vault.key().as_ref(), buyer.key().as_ref()],
+        bump,
+        payer = buyer,
+        space = 8 + BuyerRecord::INIT_SPACE
+    )]
+    pub buyer_record: Account<'info, BuyerRecord>,
+    #[account(address = vault.token_mint)]
+    pub token_mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, address = vault.vault_token_account)]
+    pub vault_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = token_mint,
+        token::authority = buyer,
+        token::token_program = token_program
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: PDA signer for vault
+    #[account(
+        seeds = [b"vault", token_mint.key().as_ref(), vault.index.to_le_bytes().as_ref()],
+        bump = vault.bump
+    )]
+    pub vault_signer: UncheckedAccount<'info>,
+    pub sol_usd_price: Account<'info, PriceUpdateV2>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+    #[account(
+        seeds = [b"gate", vault.key().as_ref()],
+        bump = gate.bump,
+    )]
+    pub gate: Option<Account<'info, GateConfig>>,
+    /// CHECK: Buyer's attestation, validated against the gate config
+    pub attestation: Option<UncheckedAccount<'info>>,
+    /// CHECK: Instructions sysvar, used to find the ed25519 voucher
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct SettleAuction<'info> {
+    #[account(mut)]
+    pub vault: Account<'info, Vault>,
+    #[account(
+        mut,
+        seeds = [b"auction", vault.key().as_ref()],
+        bump = auction.bump,
+    )]
+    pub auction: Account<'info, Auction>,
+    #[account(
+        mut,
+        seeds = [b"stats", vault.key().as_ref()],
+        bump = stats.bump,
+    )]
+    pub stats: Account<'info, SaleStats>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimAuctionRefund<'info> {
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+    #[account(mut)]
+    pub auction: Account<'info, Auction>,
+    #[account(
+        mut,
+        close = buyer,
+        seeds = [b"bid", auction.key().as_ref(), buyer.key().as_ref()],
+        bump = bid.bump,
+        has_one = auction,
+        has_one = buyer,
+    )]
+    pub bid: Account<'info, AuctionBid>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawAuctionProceeds<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub auction: Account<'info, Auction>,
+}
+
+
+#[derive(Accounts)]
+pub struct ConfigureBuyback<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub vault: Account<'info, Vault>,
+    #[account(
+        init_if_needed,
+        seeds = [b"buyback", vault.key().as_ref()],
+        bump,
+        payer = authority,
+        space = 8 + BuybackConfig::INIT_SPACE
+    )]
+    pub buyback: Account<'info, BuybackConfig>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct BuybackReserve<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(address = buyback.vault)]
+    pub vault: Account<'info, Vault>,
+    #[account(
+        mut,
+        seeds = [b"buyback", buyback.vault.as_ref()],
+        bump = buyback.bump,
+    )]
+    pub buyback: Account<'info, BuybackConfig>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SellTokens<'info> {
+    #[account(mut)]
+    pub seller: Signer<'info>,
+    #[account(mut)]
+    pub vault: Account<'info, Vault>,
+    #[account(
+        mut,
+        seeds = [b"buyback", vault.key().as_ref()],
+        bump = buyback.bump,
+    )]
+    pub buyback: Account<'info, BuybackConfig>,
+    #[account(address = vault.token_mint)]
+    pub token_mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, address = vault.vault_token_account)]
+    pub vault_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = token_mint,
+        token::authority = seller,
+        token::token_program = token_program
+    )]
+    pub seller_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub sol_usd_price: Account<'info, PriceUpdateV2>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+
+#[derive(Accounts)]
+pub struct ConfigureVault<'info> {
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub vault: Account<'info, Vault>,
+}
+
+#[derive(Accounts)]
+pub struct QueueWithdrawal<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub vault: Account<'info, Vault>,
+    #[account(
+        init,
+        seeds = [b"withdrawal", vault.key().as_ref(), vault.withdrawal_nonce.to_le_bytes().as_ref()],
+        bump,
+        payer = authority,
+        space = 8 + PendingWithdrawal::INIT_SPACE
+    )]
+    pub pending_withdrawal: Account<'info, PendingWithdrawal>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteWithdrawal<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub vault: Account<'info, Vault>,
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"withdrawal", vault.key().as_ref(), pending_withdrawal.nonce.to_le_bytes().as_ref()],
+        bump = pending_withdrawal.bump,
+        has_one = vault,
+    )]
+    pub pending_withdrawal: Account<'info, PendingWithdrawal>,
+    #[account(address = vault.token_mint)]
+    pub token_mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, address = vault.vault_token_account)]
+    pub vault_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, address = pending_withdrawal.destination)]
+    pub destination_token_account: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: PDA signer for vault
+    #[account(
+        seeds = [b"vault", token_mint.key().as_ref(), vault.index.to_le_bytes().as_ref()],
+        bump = vault.bump
+    )]
+    pub vault_signer: UncheckedAccount<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct CancelWithdrawal<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"withdrawal", pending_withdrawal.vault.as_ref(), pending_withdrawal.nonce.to_le_bytes().as_ref()],
+        bump = pending_withdrawal.bump,
+    )]
+    pub pending_withdrawal: Account<'info, PendingWithdrawal>,
+}
+
+
+#[account]
+#[derive(InitSpace)]
+pub struct Vault {
+    pub index: u64,
+    pub token_mint: Pubkey,
+    pub vault_token_account: Pubkey,
+    pub price_per_token: u64,
+    pub total_tokens: u64,
+    pub bump: u8,
+    pub owner: Pubkey,
+    pub version: u8,
+    /// Minimum delay between queueing and executing an admin withdrawal; zero disables the timelock.
+    pub withdrawal_delay: i64,
+    pub withdrawal_nonce: u64,
+    /// Purchases must pass the vault's `GateConfig` check.
+    pub gate_enabled: bool,
+    /// Anti-bot limits, each disabled when zero.
+    pub purchase_cooldown: i64,
+    pub max_tokens_per_slot: u64,
+    pub max_tokens_per_minute: u64,
+    pub launch_window_end: i64,
+    pub launch_max_per_purchase: u64,
+    /// Only accept the canonical pyth-push-oracle feed account for `push_feed_shard`.
+    pub require_push_feed: bool,
+    pub push_feed_shard: u16,
+    /// Share of the raised SOL, in basis points, that `finalize_sale` locks as liquidity.
+    pub liquidity_bps: u16,
+    /// Set while the vault's auction runs; fixed-price purchases are rejected meanwhile.
+    pub auction_active: bool,
+    /// Cumulative purchase cap per `BuyerRecord`; zero disables it.
+    pub max_tokens_per_wallet: u64,
+    /// Price queued by `update_price` under a withdrawal timelock; zero when none is queued.
+    pub pending_price: u64,
+    /// When `pending_price` can be applied.
+    pub price_update_eta: i64,
+    /// Zeroed space for future settings, so new fields don't change the account size.
+    pub reserved: [u8; 41],
+}
+
+impl Vault {
+    /// Rejects price accounts other than the sponsored push-oracle feed when the vault requires it,
+    /// so buyers can't supply their own hand-picked price update.
+    pub fn check_price_account(&self, price_account: &Pubkey) -> Result<()> {
+        if self.require_push_feed {
+            let feed_id = get_feed_id_from_hex(FEED_ID)?;
+            let (expected, _) = Pubkey::find_program_address(
+                &[&self.push_feed_shard.to_le_bytes(), &feed_id],
+                &PYTH_PUSH_ORACLE_ID,
+            );
+            require_keys_eq!(*price_account, expected, CustomError::InvalidPriceAccount);
+        }
+        Ok(())
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct SaleStats {
+    pub vault: Pubkey,
+    /// SOL raised, the only payment asset accepted by the vault.
+    pub lamports_raised: u64,
+    /// USD value (6 decimals) of all purchases at purchase-time prices.
+    pub usd_raised: u64,
+    pub tokens_sold: u64,
+    pub unique_buyers: u64,
+    pub largest_purchase: u64,
+    pub first_purchase_at: i64,
+    pub last_purchase_at: i64,
+    pub bump: u8,
+    /// Rolling purchase volume for the vault-wide rate limits.
+    pub current_slot: u64,
+    pub slot_volume: u64,
+    pub current_minute: i64,
+    pub minute_volume: u64,
+    pub reserved: [u8; 32],
+}
+
+impl SaleStats {
+    /// Applies the vault's wallet cap and anti-bot limits to a purchase of `tokens` for `record`'s wallet.
+    pub fn enforce_limits(
+        &mut self,
+        vault: &Vault,
+        record: &BuyerRecord,
+        tokens: u64,
+        clock: &Clock,
+    ) -> Result<()> {
+        let now = clock.unix_timestamp;
+        if vault.max_tokens_per_wallet > 0 {
+            let purchased = record
+                .tokens_purchased
+                .checked_add(tokens)
+                .ok_or(CustomError::Overflow)?;
+            require!(
+                purchased <= vault.max_tokens_per_wallet,
+                CustomError::WalletCapExceeded
+            );
+        }
+        if vault.purchase_cooldown > 0 && record.purchase_count > 0 {
+            require!(
+                now >= record.last_purchase_at.saturating_add(vault.purchase_cooldown),
+                CustomError::CooldownActive
+            );
+        }
+        if vault.launch_max_per_purchase > 0 && now < vault.launch_window_end {
+            require!(
+                tokens <= vault.launch_max_per_purchase,
+                CustomError::RateLimited
+            );
+        }
+
+        if self.current_slot != clock.slot {
+            self.current_slot = clock.slot;
+            self.slot_volume = 0;
+        }
+        self.slot_volume = self
+            .slot_volume
+            .checked_add(tokens)
+            .ok_or(CustomError::Overflow)?;
+        if vault.max_tokens_per_slot > 0 {
+            require!(
+                self.slot_volume <= vault.max_tokens_per_slot,
+                CustomError::RateLimited
+            );
+        }
+
+        let minute = now / 60;
+        if self.current_minute != minute {
+            self.current_minute = minute;
+            self.minute_volume = 0;
+        }
+        self.minute_volume = self
+            .minute_volume
+            .checked_add(tokens)
+            .ok_or(CustomError::Overflow)?;
+        if vault.max_tokens_per_minute > 0 {
+            require!(
+                self.minute_volume <= vault.max_tokens_per_minute,
+                CustomError::RateLimited
+            );
+        }
+        Ok(())
+    }
+
+    pub fn record_purchase(
+        &mut self,
+        record: &mut BuyerRecord,
+        tokens: u64,
+        lamports: u64,
+        usd_value: u64,
+        now: i64,
+    ) -> Result<()> {
+        if record.purchase_count == 0 {
+            self.unique_buyers = self
+                .unique_buyers
+                .checked_add(1)
+                .ok_or(CustomError::Overflow)?;
+        }
+        if self.first_purchase_at == 0 {
+            self.first_purchase_at = now;
+        }
+        self.last_purchase_at = now;
+        self.largest_purchase = self.largest_purchase.max(tokens);
+        self.tokens_sold = self
+            .tokens_sold
+            .checked_add(tokens)
+            .ok_or(CustomError::Overflow)?;
+        self.lamports_raised = self
+            .lamports_raised
+            .checked_add(lamports)
+            .ok_or(CustomError::Overflow)?;
+        self.usd_raised = self
+            .usd_raised
+            .checked_add(usd_value)
+            .ok_or(CustomError::Overflow)?;
+
+        record.tokens_purchased = record
+            .tokens_purchased
+            .checked_add(tokens)
+            .ok_or(CustomError::Overflow)?;
+        record.lamports_paid = record
+            .lamports_paid
+            .checked_add(lamports)
+            .ok_or(CustomError::Overflow)?;
+        record.purchase_count = record
+            .purchase_count
+            .checked_add(1)
+            .ok_or(CustomError::Overflow)?;
+        record.last_purchase_at = now;
+        Ok(())
+    }
+}
+
+/// Purchase history of the wallet that receives the tokens: the `recipient` of
+/// `purchase_tokens`, and the buyer of `purchase_auction_tokens`, which can only
+/// deliver to the buyer's own account. Wallet caps and cooldowns are keyed on it,
+/// so paying from several wallets doesn't get around them.
+#[account]
+#[derive(InitSpace)]
+pub struct BuyerRecord {
+    pub vault: Pubkey,
+    /// The receiving wallet, not necessarily the one that paid.
+    pub buyer: Pubkey,
+    pub tokens_purchased: u64,
+    pub lamports_paid: u64,
+    pub purchase_count: u64,
+    pub last_purchase_at: i64,
+    pub bump: u8,
+    pub reserved: [u8; 32],
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct BuybackConfig {
+    pub vault: Pubkey,
+    pub enabled: bool,
+    /// Fixed buyback price in USD (6 decimals) per token; zero means `discount_bps` off the sale price.
+    pub buyback_price: u64,
+    pub discount_bps: u16,
+    pub daily_cap: u64,
+    pub current_day: i64,
+    pub day_volume: u64,
+    /// SOL held by this PDA for buybacks, on top of its rent.
+    pub reserve_lamports: u64,
+    pub bump: u8,
+    /// When the buyback was last disabled; starts the withdrawal delay for the reserve.
+    pub disabled_at: i64,
+}
+
+impl BuybackConfig {
+    pub fn sell_price(&self, sale_price: u64) -> Result<u64> {
+        if self.buyback_price > 0 {
+            return Ok(self.buyback_price);
+        }
+        let price = (sale_price as u128)
+            .checked_mul((BPS_DENOMINATOR - self.discount_bps as u64) as u128)
+            .ok_or(CustomError::Overflow)?
+            / BPS_DENOMINATOR as u128;
+        Ok(price as u64)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum GateKind {
+    /// A non-expired account owned by `issuer_program` at the address derived from `seeds`,
+    /// which must include the issuer and the recipient.
+    Attestation,
+    /// An ed25519 signature by `issuer` over (vault, recipient, expiry) in the previous instruction.
+    Voucher,
+}
+
+/// One seed of the attestation PDA, so different issuers' layouts can be described.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum GateSeed {
+    Literal {
+        #[max_len(32)]
+        bytes: Vec<u8>,
+    },
+    Recipient,
+    Issuer,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct GateConfig {
+    pub vault: Pubkey,
+    pub kind: GateKind,
+    pub issuer: Pubkey,
+    pub issuer_program: Pubkey,
+    #[max_len(4)]
+    pub seeds: Vec<GateSeed>,
+    /// Offset of the little-endian i64 expiry timestamp in the attestation data, if any.
+    pub expiry_offset: Option<u16>,
+    pub bump: u8,
+}
+
+impl GateConfig {
+    pub fn verify(
+        &self,
+        vault: &Pubkey,
+        recipient: &Pubkey,
+        attestation: Option<&AccountInfo>,
+        instructions_sysvar: Option<&AccountInfo>,
+    ) -> Result<()> {
+        match self.kind {
+            GateKind::Attestation => {
+                let attestation = attestation.ok_or(CustomError::GateRequired)?;
+                self.verify_attestation(recipient, attestation)
+            }
+            GateKind::Voucher => {
+                let sysvar = instructions_sysvar.ok_or(CustomError::GateRequired)?;
+                self.verify_voucher(vault, recipient, sysvar)
+            }
+        }
+    }
+
+    fn verify_attestation(&self, recipient: &Pubkey, attestation: &AccountInfo) -> Result<()> {
+        let seeds: Vec<&[u8]> = self
+            .seeds
+            .iter()
+            .map(|seed| match seed {
+                GateSeed::Literal { bytes } => bytes.as_slice(),
+                GateSeed::Recipient => recipient.as_ref(),
+                GateSeed::Issuer => self.issuer.as_ref(),
+            })
+            .collect();
+        let (expected, _) = Pubkey::find_program_address(&seeds, &self.issuer_program);
+        require_keys_eq!(attestation.key(), expected, CustomError::InvalidAttestation);
+        require_keys_eq!(
+            *attestation.owner,
+            self.issuer_program,
+            CustomError::InvalidAttestation
+        );
+
+        if let Some(offset) = self.expiry_offset {
+            let data = attestation.try_borrow_data()?;
+            let start = offset as usize;
+            let bytes = data
+                .get(start..start + 8)
+                .ok_or(CustomError::InvalidAttestation)?;
+            let expires_at = i64::from_le_bytes(bytes.try_into().unwrap());
+            require!(
+                expires_at > Clock::get()?.unix_timestamp,
+                CustomError::AttestationExpired
+            );
+        }
+        Ok(())
+    }
+
+    fn verify_voucher(&self, vault: &Pubkey, recipient: &Pubkey, sysvar: &AccountInfo) -> Result<()> {
+        let current = load_current_index_checked(sysvar)?;
+        require!(current > 0, CustomError::InvalidVoucher);
+        let ix = load_instruction_at_checked(current as usize - 1, sysvar)?;
+        require_keys_eq!(ix.program_id, ED25519_PROGRAM_ID, CustomError::InvalidVoucher);
+
+        // Single signature, with its offsets pointing into the ed25519 instruction itself.
+        let data = &ix.data;
+        require!(data.len() >= 16 && data[0] == 1, CustomError::InvalidVoucher);
+        let read_u16 = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]);
+        require!(
+            read_u16(4) == u16::MAX && read_u16(8) == u16::MAX && read_u16(14) == u16::MAX,
+            CustomError::InvalidVoucher
+        );
+        let pubkey_offset = read_u16(6) as usize;
+        let message_offset = read_u16(10) as usize;
+        let message_len = read_u16(12) as usize;
+
+        let pubkey = data
+            .get(pubkey_offset..pubkey_offset + 32)
+            .ok_or(CustomError::InvalidVoucher)?;
+        require!(pubkey == self.issuer.as_ref(), CustomError::InvalidVoucher);
+
+        // message: vault (32) || recipient (32) || expires_at (i64 LE)
+        require!(message_len == 72, CustomError::InvalidVoucher);
+        let message = data
+            .get(message_offset..message_offset + message_len)
+            .ok_or(CustomError::InvalidVoucher)?;
+        require!(
+            &message[..32] == vault.as_ref() && &message[32..64] == recipient.as_ref(),
+            CustomError::InvalidVoucher
+        );
+        let expires_at = i64::from_le_bytes(message[64..72].try_into().unwrap());
+        require!(
+            expires_at > Clock::get()?.unix_timestamp,
+            CustomError::AttestationExpired
+        );
+        Ok(())
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct PendingWithdrawal {
+    pub vault: Pubkey,
+    pub nonce: u64,
+    pub amount: u64,
+    /// Token account the withdrawal will be sent to.
+    pub destination: Pubkey,
+    pub eta: i64,
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct LiquidityReserve {
+    pub vault: Pubkey,
+    pub token_mint: Pubkey,
+    pub lamports: u64,
+    pub token_amount: u64,
+    pub unlock_time: i64,
+    pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AuctionParams {
+    pub start_price: u64,
+    pub floor_price: u64,
+    pub price_decay: u64,
+    pub decay_interval: i64,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub uniform_clearing: bool,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Auction {
+    pub vault: Pubkey,
+    pub start_price: u64,
+    pub floor_price: u64,
+    pub price_decay: u64,
+    pub decay_interval: i64,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub uniform_clearing: bool,
+    pub settled: bool,
+    pub clearing_price: u64,
+    pub last_price: u64,
+    pub tokens_sold: u64,
+    pub lamports_escrowed: u64,
+    pub weighted_lamports: u128,
+    pub proceeds_withdrawn: bool,
+    pub bump: u8,
+}
+
+impl Auction {
+    /// Price per token at `now`: `start_price` minus one `price_decay` per
+    /// elapsed `decay_interval`, never below `floor_price`.
+    pub fn current_price(&self, now: i64) -> u64 {
+        let steps = (now.saturating_sub(self.start_time) / self.decay_interval).max(0) as u64;
+        self.start_price
+            .saturating_sub(steps.saturating_mul(self.price_decay))
+            .max(self.floor_price)
+    }
+
+    /// Lamports owed at the clearing price for purchases with the given weight.
+    pub fn clearing_cost(&self, weighted_lamports: u128) -> Result<u64> {
+        let cost = weighted_lamports
+            .checked_mul(self.clearing_price as u128)
+            .ok_or(CustomError::Overflow)?
+            .div_ceil(AUCTION_WEIGHT_SCALE);
+        u64::try_from(cost).map_err(|_| CustomError::Overflow.into())
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct AuctionBid {
+    pub auction: Pubkey,
+    pub buyer: Pubkey,
+    pub tokens: u64,
+    pub lamports_paid: u64,
+    pub weighted_lamports: u128,
+    pub bump: u8,
+}
+
+
+#[error_code]
+pub enum CustomError {
+    #[msg("Unauthorized User. Access Denied.")]
+    InvalidAuth,
     #[msg("Presale Phase Completed. Please Wait For The Next Phase.")]
     InsufficientTokens,
     #[msg("Arithmetic Overflow")]
@@ -431,4 +2447,250 @@ pub enum CustomError {
     VaultNotExpired,
     #[msg("You can only purchase SAURAI equivalent upto 1000 USD per transaction")]
     TokenLimit,
+    #[msg("Invalid Percentage. Must Be Between 1 And 10000 Basis Points.")]
+    InvalidPercentage,
+    #[msg("Unlock Time Must Be In The Future And After The Withdrawal Delay.")]
+    InvalidUnlockTime,
+    #[msg("Liquidity Reserve Still Locked. Please Wait For The Unlock Time.")]
+    LiquidityLocked,
+    #[msg("Invalid Auction Configuration.")]
+    InvalidAuctionConfig,
+    #[msg("Auction Is Not Active.")]
+    AuctionNotActive,
+    #[msg("Auction Has Not Ended Yet.")]
+    AuctionNotEnded,
+    #[msg("Auction Has Not Been Settled Yet.")]
+    AuctionNotSettled,
+    #[msg("Auction Already Settled.")]
+    AuctionSettled,
+    #[msg("Vault Inventory Out Of Sync. Please Sync The Vault.")]
+    VaultOutOfSync,
+    #[msg("Invalid Vault Account.")]
+    InvalidVaultAccount,
+    #[msg("Vault Already Migrated.")]
+    AlreadyMigrated,
+    #[msg("Buyback Is Not Enabled For This Vault.")]
+    BuybackDisabled,
+    #[msg("Daily Buyback Limit Reached. Please Try Again Tomorrow.")]
+    DailyCapExceeded,
+    #[msg("Insufficient Buyback Reserve.")]
+    InsufficientReserve,
+    #[msg("Withdrawal Timelock Active. Please Queue The Withdrawal.")]
+    TimelockActive,
+    #[msg("Withdrawal Timelock Not Expired Yet.")]
+    TimelockNotExpired,
+    #[msg("Withdrawal Delay Cannot Be Shortened.")]
+    TimelockDecrease,
+    #[msg("Wallet Not Verified. Verification Required For This Sale.")]
+    GateRequired,
+    #[msg("Invalid Gate Configuration.")]
+    InvalidGateConfig,
+    #[msg("Invalid Verification Attestation.")]
+    InvalidAttestation,
+    #[msg("Verification Expired. Please Verify Again.")]
+    AttestationExpired,
+    #[msg("Invalid Verification Voucher.")]
+    InvalidVoucher,
+    #[msg("Purchase Cooldown Active. Please Wait Before Purchasing Again.")]
+    CooldownActive,
+    #[msg("Purchase Volume Limit Reached. Please Try Again Shortly.")]
+    RateLimited,
+    #[msg("Invalid Rate Limit Configuration.")]
+    InvalidRateLimit,
+    #[msg("Invalid Price Account. Use The Sponsored Price Feed.")]
+    InvalidPriceAccount,
+    #[msg("Invalid Distribution. Provide One Non-Zero Amount Per Recipient.")]
+    InvalidDistribution,
+    #[msg("Invalid Recipient Token Account.")]
+    InvalidRecipient,
+    #[msg("Fixed-Price Sale Paused While An Auction Is Running.")]
+    AuctionActive,
+    #[msg("Buyback Still Enabled. Disable It Before Withdrawing The Reserve.")]
+    BuybackActive,
+    #[msg("Purchase Limit Per Wallet Reached.")]
+    WalletCapExceeded,
+    #[msg("Invalid Price. Must Be Greater Than Zero.")]
+    InvalidPrice,
+    #[msg("No Price Update Queued.")]
+    NoPriceUpdateQueued,
+    #[msg("The Admin Can't Buy From A Timelocked Vault.")]
+    AdminPurchase,
+}
+
+
+#[event]
+pub struct VaultSynced {
+    pub vault: Pubkey,
+    pub previous_total: u64,
+    pub total_tokens: u64,
+}
+
+#[event]
+pub struct VaultMigrated {
+    pub vault: Pubkey,
+    pub previous_version: u8,
+    pub version: u8,
+}
+
+#[event]
+pub struct LiquidityReserved {
+    pub vault: Pubkey,
+    pub lamports: u64,
+    pub token_amount: u64,
+    pub unlock_time: i64,
+}
+
+#[event]
+pub struct LiquidityReleased {
+    pub vault: Pubkey,
+    pub lamports: u64,
+    pub token_amount: u64,
+}
+
+#[event]
+pub struct TokensPurchased {
+    pub vault: Pubkey,
+    pub buyer: Pubkey,
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub lamports: u64,
+}
+
+#[event]
+pub struct TokensDistributed {
+    pub vault: Pubkey,
+    pub recipient: Pubkey,
+    pub token_account: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct PriceUpdateQueued {
+    pub vault: Pubkey,
+    pub price: u64,
+    pub eta: i64,
+}
+
+#[event]
+pub struct WithdrawalQueued {
+    pub vault: Pubkey,
+    pub nonce: u64,
+    pub amount: u64,
+    pub destination: Pubkey,
+    pub eta: i64,
+}
+
+#[event]
+pub struct WithdrawalExecuted {
+    pub vault: Pubkey,
+    pub nonce: u64,
+    pub amount: u64,
+    pub destination: Pubkey,
+}
+
+#[event]
+pub struct WithdrawalCancelled {
+    pub vault: Pubkey,
+    pub nonce: u64,
+    pub amount: u64,
+}
+
+#[event]
+pub struct TokensSold {
+    pub vault: Pubkey,
+    pub seller: Pubkey,
+    pub amount: u64,
+    pub lamports: u64,
+    pub price_per_token: u64,
+}
+
+#[event]
+pub struct AuctionSettled {
+    pub vault: Pubkey,
+    pub clearing_price: u64,
+    pub tokens_sold: u64,
+}
+
+
+/// Converts `amount` token base units at `price_per_token` (USD with 6 decimals per
+/// whole token) into lamports at the given Pyth SOL/USD price. Purchases round up so
+/// buyers never underpay; buybacks round down so sellers are never overpaid.
+fn tokens_to_lamports(
+    amount: u64,
+    price_per_token: u64,
+    price: i64,
+    expo: i32,
+    decimals: u8,
+    round_up: bool,
+) -> Result<u64> {
+    let usdt_decimals = 6u32;
+    let sol_decimals = 9u32;
+
+    // numerator: amount * price_per_token * 10^sol_decimals * 10^(-expo)
+    let mut numerator = (amount as u128)
+        .checked_mul(price_per_token as u128)
+        .and_then(|n| n.checked_mul(10u128.pow(sol_decimals)))
+        .ok_or(CustomError::Overflow)?;
+    // denominator: price * 10^usdt_decimals * 10^decimals * 10^expo
+    let mut denominator = (price.unsigned_abs() as u128)
+        .checked_mul(10u128.pow(usdt_decimals))
+        .and_then(|d| d.checked_mul(10u128.pow(decimals as u32)))
+        .ok_or(CustomError::Overflow)?;
+
+    if expo < 0 {
+        numerator = numerator
+            .checked_mul(10u128.pow((-expo) as u32))
+            .ok_or(CustomError::Overflow)?;
+    } else {
+        denominator = denominator
+            .checked_mul(10u128.pow(expo as u32))
+            .ok_or(CustomError::Overflow)?;
+    }
+    require!(denominator > 0, CustomError::Overflow);
+
+    let lamports = if round_up {
+        numerator.div_ceil(denominator)
+    } else {
+        numerator / denominator
+    };
+    u64::try_from(lamports).map_err(|_| CustomError::Overflow.into())
+}
+
+/// Converts a lamport amount into token base units at `price_per_token`
+/// (USD with 6 decimals per whole token) and the given Pyth SOL/USD price.
+fn lamports_to_tokens(
+    lamports: u64,
+    price_per_token: u64,
+    price: i64,
+    expo: i32,
+    decimals: u8,
+) -> Result<u64> {
+    let usdt_decimals = 6u32;
+    let sol_decimals = 9u32;
+
+    // numerator: lamports * price * 10^usdt_decimals * 10^decimals * 10^expo
+    let mut numerator = (lamports as u128)
+        .checked_mul(price.unsigned_abs() as u128)
+        .and_then(|n| n.checked_mul(10u128.pow(usdt_decimals)))
+        .and_then(|n| n.checked_mul(10u128.pow(decimals as u32)))
+        .ok_or(CustomError::Overflow)?;
+    // denominator: price_per_token * 10^sol_decimals * 10^(-expo)
+    let mut denominator = (price_per_token as u128)
+        .checked_mul(10u128.pow(sol_decimals))
+        .ok_or(CustomError::Overflow)?;
+
+    if expo < 0 {
+        denominator = denominator
+            .checked_mul(10u128.pow((-expo) as u32))
+            .ok_or(CustomError::Overflow)?;
+    } else {
+        numerator = numerator
+            .checked_mul(10u128.pow(expo as u32))
+            .ok_or(CustomError::Overflow)?;
+    }
+
+    let tokens = numerator
+        .checked_div(denominator)
+        .ok_or(CustomError::Overflow)?;
+    u64::try_from(tokens).map_err(|_| CustomError::Overflow.into())
 }
\ No newline at end of file
@@ -1,11 +1,23 @@
 use anchor_lang::prelude::*;
 use anchor_spl::{
-    associated_token::AssociatedToken,
+    associated_token::{self, AssociatedToken},
+    token_2022::{
+        self,
+        spl_token_2022::{self, extension::ExtensionType, instruction::AuthorityType},
+        Token2022,
+    },
+    token_2022_extensions::{non_transferable_mint_initialize, NonTransferableMintInitialize},
     token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
 };
 use chrono::prelude::*;
+use pythnet_sdk::{
+    accumulators::merkle::{MerklePath, MerkleRoot},
+    hashers::keccak256_160::Keccak160,
+};
 use solana_security_txt::security_txt;
 
+pub mod sdk;
+
 security_txt! {
     name: "SAURAI Vesting Program",
     project_url: "https://saurs.ai",
@@ -18,18 +30,82 @@ security_txt! {
 }
 
 declare_id!("SAURGZTwnVRzp85TcuKiENjZxF4x7VnSvPGCzW8MsWv");
+/// Deployer key allowed to create the `Config` account; afterwards only `Config::admin` is trusted.
 const ADMIN: Pubkey = pubkey!("3c1gFBMmZFrDTgUz2HH8yhhbfqibdwfK14QtHRiQLYE1");
+pub const VESTING_ACCOUNT_VERSION: u8 = 1;
+/// Version 2 stores amounts as `u64`; version 1 reserves held them as `i64`.
+pub const RESERVE_VERSION: u8 = 2;
+/// Size of a `ReserveAccount` created before the layout was versioned.
+pub const LEGACY_RESERVE_LEN: usize = 8 + 32 + 8 * 6 + 32 + 1;
+pub const SECONDS_PER_DAY: i64 = 86_400;
+pub const SECONDS_PER_WEEK: i64 = 7 * SECONDS_PER_DAY;
+pub const MAX_TRANCHES: usize = 64;
+/// Unlocks listed by one `get_reserve_status` call, keeping its return data under 1 KiB.
+pub const MAX_PROJECTED_UNLOCKS: usize = 48;
+/// Largest tip `configure_tip_vault` accepts, 0.001 SOL.
+pub const MAX_CRANK_TIP: u64 = 1_000_000;
+/// A reserve pays a crank tip at most this often, so streaming reserves can't be cranked for tips.
+pub const MIN_CRANK_TIP_INTERVAL: i64 = SECONDS_PER_DAY;
 
 #[program]
 pub mod vesting {
     use super::*;
 
+    /// Creates the program config with its first admin. `admin` may be a PDA, such as a
+    /// Squads vault or a remote-executor account, that signs through CPI.
+    pub fn initialize_config(ctx: Context<InitializeConfig>, admin: Pubkey) -> Result<()> {
+        require!(
+            ctx.accounts.signer.key() == ADMIN,
+            ErrorCode::AccessDenied
+        );
+        let config = &mut ctx.accounts.config;
+        config.admin = admin;
+        config.pending_admin = None;
+        config.bump = ctx.bumps.config;
+        emit!(AdminChanged {
+            previous_admin: Pubkey::default(),
+            admin,
+        });
+        Ok(())
+    }
+
+    /// Starts an admin handover that `new_admin` must accept; `None` cancels a pending one.
+    pub fn propose_admin(ctx: Context<ProposeAdmin>, new_admin: Option<Pubkey>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        require!(
+            ctx.accounts.admin.key() == config.admin,
+            ErrorCode::AccessDenied
+        );
+        config.pending_admin = new_admin;
+        emit!(AdminProposed {
+            admin: config.admin,
+            pending_admin: new_admin,
+        });
+        Ok(())
+    }
+
+    pub fn accept_admin(ctx: Context<AcceptAdmin>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        require!(
+            config.pending_admin == Some(ctx.accounts.pending_admin.key()),
+            ErrorCode::AccessDenied
+        );
+        let previous_admin = config.admin;
+        config.admin = ctx.accounts.pending_admin.key();
+        config.pending_admin = None;
+        emit!(AdminChanged {
+            previous_admin,
+            admin: config.admin,
+        });
+        Ok(())
+    }
+
     pub fn create_vesting_account(
         ctx: Context<CreateVestingAccount>,
         reserve_type: String,
     ) -> Result<()> {
         require!(
-            ctx.accounts.signer.key() == ADMIN,
+            ctx.accounts.signer.key() == ctx.accounts.config.admin,
             ErrorCode::AccessDenied
         );
         let vesting_account = &mut ctx.accounts.vesting_account;
@@ -38,21 +114,99 @@ pub mod vesting {
         vesting_account.treasury_token_account = ctx.accounts.treasury_token_account.key();
         vesting_account.reserve_type = reserve_type;
         vesting_account.bump = ctx.bumps.vesting_account;
+        vesting_account.version = VESTING_ACCOUNT_VERSION;
+        Ok(())
+    }
+
+    /// Adds the aggregate counters to a vesting account created before a pool could hold
+    /// several reserves, seeding them from its single legacy reserve if there is one.
+    pub fn migrate_vesting_account(ctx: Context<MigrateVestingAccount>) -> Result<()> {
+        require!(
+            ctx.accounts.admin.key() == ctx.accounts.config.admin,
+            ErrorCode::AccessDenied
+        );
+        let vesting_info = ctx.accounts.vesting_account.to_account_info();
+        let old_len = vesting_info.data_len();
+        require!(
+            old_len > 8 && vesting_info.try_borrow_data()?[..8] == *VestingAccount::DISCRIMINATOR,
+            ErrorCode::InvalidVestingAccount
+        );
+
+        let new_len = 8 + VestingAccount::INIT_SPACE;
+        if old_len < new_len {
+            let rent_due = Rent::get()?
+                .minimum_balance(new_len)
+                .saturating_sub(vesting_info.lamports());
+            if rent_due > 0 {
+                anchor_lang::system_program::transfer(
+                    CpiContext::new(
+                        ctx.accounts.system_program.to_account_info(),
+                        anchor_lang::system_program::Transfer {
+                            from: ctx.accounts.admin.to_account_info(),
+                            to: vesting_info.clone(),
+                        },
+                    ),
+                    rent_due,
+                )?;
+            }
+            vesting_info.resize(new_len)?;
+        }
+
+        let mut data = vesting_info.try_borrow_mut_data()?;
+        let mut vesting_account = VestingAccount::try_deserialize(&mut &data[..])?;
+        require!(
+            vesting_account.version < VESTING_ACCOUNT_VERSION,
+            ErrorCode::AlreadyMigrated
+        );
+        if let Some(reserve) = &ctx.accounts.legacy_reserve {
+            require_keys_eq!(
+                reserve.vesting_account,
+                vesting_info.key(),
+                ErrorCode::InvalidVestingAccount
+            );
+            vesting_account.total_allocated = reserve.total_amount;
+            vesting_account.total_withdrawn = reserve.amount_withdrawn;
+            vesting_account.reserve_count = 1;
+        }
+        vesting_account.version = VESTING_ACCOUNT_VERSION;
+        vesting_account.try_serialize(&mut &mut data[..])?;
         Ok(())
     }
 
     pub fn create_reserve(
         ctx: Context<CreateReserveAccount>,
-        start_time: i64,
-        end_time: i64,
-        total_amount: i64,
-        cliff_time: i64,
-        monthly_claim: i64,
+        _index: u64,
+        params: ReserveParams,
     ) -> Result<()> {
+        let ReserveParams {
+            start_time,
+            end_time,
+            total_amount,
+            cliff_time,
+            monthly_claim,
+            revocable,
+            schedule,
+            tge_unlock_amount,
+            cliff_release_amount,
+        } = params;
         require!(
-            ctx.accounts.owner.key() == ADMIN,
+            ctx.accounts.owner.key() == ctx.accounts.config.admin,
             ErrorCode::AccessDenied
         );
+        let vesting_account = &mut ctx.accounts.vesting_account;
+        require!(
+            vesting_account.version == VESTING_ACCOUNT_VERSION,
+            ErrorCode::NotMigrated
+        );
+        vesting_account.total_allocated = vesting_account
+            .total_allocated
+            .checked_add(total_amount)
+            .ok_or(ErrorCode::Overflow)?;
+        vesting_account.reserve_count = vesting_account
+            .reserve_count
+            .checked_add(1)
+            .ok_or(ErrorCode::Overflow)?;
+
         let reserve_account = &mut ctx.accounts.reserve_account;
         reserve_account.beneficiary = ctx.accounts.beneficiary.key();
         reserve_account.start_time = start_time;
@@ -63,6 +217,12 @@ pub mod vesting {
         reserve_account.monthly_claim = monthly_claim;
         reserve_account.vesting_account = ctx.accounts.vesting_account.key();
         reserve_account.bump = ctx.bumps.reserve_account;
+        reserve_account.version = RESERVE_VERSION;
+        reserve_account.revocable = revocable;
+        reserve_account.schedule = schedule;
+        reserve_account.tge_unlock_amount = tge_unlock_amount;
+        reserve_account.cliff_release_amount = cliff_release_amount;
+        reserve_account.validate()?;
 
         let cpi_accounts = TransferChecked {
             mint: ctx.accounts.mint.to_account_info(),
@@ -74,99 +234,788 @@ pub mod vesting {
         let decimals = ctx.accounts.mint.decimals;
         let cpi_program = ctx.accounts.token_program.to_account_info();
         let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-        transfer_checked(cpi_ctx, total_amount.try_into().unwrap(), decimals)?;
+        transfer_checked(cpi_ctx, total_amount, decimals)?;
 
         emit!(TokensLocked {
-            amount: total_amount as u64,
+            amount: total_amount,
             locked_until: start_time + cliff_time,
-            unlock_amount_per_period: monthly_claim as u64,
+            unlock_amount_per_period: monthly_claim,
             vesting_end_time: end_time,
             decimals: decimals,
+            tge_unlock_amount,
+            cliff_release_amount,
         });
 
         Ok(())
     }
 
-    pub fn claim_tokens(ctx: Context<ClaimTokens>, _reserve_type: String) -> Result<()> {
-        let reserve_account = &mut ctx.accounts.reserve_account;
-        let current_time = Clock::get()?.unix_timestamp;
+    /// Funds the treasury for a batch of grants committed to by `root`, a
+    /// `MerkleTree<Keccak160>` root over `DistributorGrant::leaf` of each grant. Reserves are
+    /// created as beneficiaries claim; whatever is unclaimed at `expiry_time` can be clawed back.
+    pub fn create_distributor(
+        ctx: Context<CreateDistributor>,
+        _index: u64,
+        root: [u8; 20],
+        total_amount: u64,
+        expiry_time: i64,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.owner.key() == ctx.accounts.config.admin,
+            ErrorCode::AccessDenied
+        );
+        require!(
+            ctx.accounts.vesting_account.version == VESTING_ACCOUNT_VERSION,
+            ErrorCode::NotMigrated
+        );
+        require!(total_amount > 0, ErrorCode::InvalidAmount);
+        require!(
+            expiry_time > Clock::get()?.unix_timestamp,
+            ErrorCode::InvalidExpiryTime
+        );
 
-        if current_time < reserve_account.start_time + reserve_account.cliff_time {
-            return Err(ErrorCode::CliffPeriodNotEnded.into());
-        }
-        let v_start_time = reserve_account.start_time + reserve_account.cliff_time;
-
-        let start_dt = DateTime::from_timestamp(v_start_time, 0).ok_or(ErrorCode::InvalidTime)?;
-        let current_dt = DateTime::from_timestamp(current_time, 0).ok_or(ErrorCode::InvalidTime)?;
-        
-        let periods = months_elapsed(start_dt, current_dt) + 1;
-
-        msg!("Debug Claim: CurrentTime: {}, VStartTime: {}", current_time, v_start_time);
-        msg!("Debug Claim: MonthsElapsed: {}, Periods: {}", periods - 1, periods);
-        
-        let max_claimable = (periods as i64) * reserve_account.monthly_claim;
-        let already_claimed = reserve_account.amount_withdrawn;
-        let claimable = max_claimable
-            .saturating_sub(already_claimed)
-            .min(reserve_account.total_amount.saturating_sub(already_claimed));
-
-        if claimable <= 0 {
-            return Err(ErrorCode::ClaimNotAvailableYet.into());
-        }
-        reserve_account.amount_withdrawn = reserve_account.amount_withdrawn.saturating_add(claimable);
+        let distributor = &mut ctx.accounts.distributor;
+        distributor.vesting_account = ctx.accounts.vesting_account.key();
+        distributor.root = root;
+        distributor.total_amount = total_amount;
+        distributor.claimed_amount = 0;
+        distributor.claimed_count = 0;
+        distributor.expiry_time = expiry_time;
+        distributor.clawed_back = false;
+        distributor.bump = ctx.bumps.distributor;
 
         let cpi_accounts = TransferChecked {
             mint: ctx.accounts.mint.to_account_info(),
-            from: ctx.accounts.treasury_token_account.to_account_info(),
-            to: ctx.accounts.beneficiary_token_account.to_account_info(),
-            authority: ctx.accounts.vesting_account.to_account_info(),
+            from: ctx.accounts.owner_token_account.to_account_info(),
+            to: ctx.accounts.treasury_token_account.to_account_info(),
+            authority: ctx.accounts.owner.to_account_info(),
         };
+        let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
+        transfer_checked(cpi_ctx, total_amount, ctx.accounts.mint.decimals)?;
+
+        emit!(DistributorCreated {
+            distributor: distributor.key(),
+            vesting_account: distributor.vesting_account,
+            root,
+            total_amount,
+            expiry_time,
+        });
+        Ok(())
+    }
+
+    /// Creates the beneficiary's reserve from their grant and its Merkle `proof`, then claims
+    /// whatever has already vested. Later claims go through `claim_tokens` as usual.
+    pub fn claim_from_distributor(
+        ctx: Context<ClaimFromDistributor>,
+        _reserve_type: String,
+        grant: DistributorGrant,
+        proof: Vec<[u8; 20]>,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let distributor = &mut ctx.accounts.distributor;
+        require!(!distributor.clawed_back, ErrorCode::DistributorClawedBack);
+        require!(now < distributor.expiry_time, ErrorCode::DistributorExpired);
+        require_keys_eq!(
+            grant.beneficiary,
+            ctx.accounts.beneficiary.key(),
+            ErrorCode::InvalidProof
+        );
+        require!(
+            MerkleRoot::<Keccak160>::new(distributor.root)
+                .check(MerklePath::new(proof), &grant.leaf()?),
+            ErrorCode::InvalidProof
+        );
+        // Tranches are set per reserve, so they can't be committed to by the root.
+        require!(
+            grant.schedule != ScheduleKind::Custom,
+            ErrorCode::InvalidSchedule
+        );
+        distributor.claimed_amount = distributor
+            .claimed_amount
+            .checked_add(grant.total_amount)
+            .filter(|claimed| *claimed <= distributor.total_amount)
+            .ok_or(ErrorCode::DistributorExhausted)?;
+        distributor.claimed_count = distributor
+            .claimed_count
+            .checked_add(1)
+            .ok_or(ErrorCode::Overflow)?;
+
+        let claim_receipt = &mut ctx.accounts.claim_receipt;
+        claim_receipt.distributor = distributor.key();
+        claim_receipt.beneficiary = grant.beneficiary;
+        claim_receipt.bump = ctx.bumps.claim_receipt;
+
+        let vesting_account = &mut ctx.accounts.vesting_account;
+        vesting_account.total_allocated = vesting_account
+            .total_allocated
+            .checked_add(grant.total_amount)
+            .ok_or(ErrorCode::Overflow)?;
+        vesting_account.reserve_count = vesting_account
+            .reserve_count
+            .checked_add(1)
+            .ok_or(ErrorCode::Overflow)?;
+
+        let reserve_account = &mut ctx.accounts.reserve_account;
+        reserve_account.beneficiary = grant.beneficiary;
+        reserve_account.start_time = grant.start_time;
+        reserve_account.end_time = grant.end_time;
+        reserve_account.total_amount = grant.total_amount;
+        reserve_account.amount_withdrawn = 0;
+        reserve_account.cliff_time = grant.cliff_time;
+        reserve_account.monthly_claim = grant.monthly_claim;
+        reserve_account.vesting_account = vesting_account.key();
+        reserve_account.bump = ctx.bumps.reserve_account;
+        reserve_account.version = RESERVE_VERSION;
+        reserve_account.revocable = grant.revocable;
+        reserve_account.schedule = grant.schedule;
+        reserve_account.tge_unlock_amount = grant.tge_unlock_amount;
+        reserve_account.cliff_release_amount = grant.cliff_release_amount;
+        reserve_account.validate()?;
+
+        emit!(DistributorGrantClaimed {
+            distributor: distributor.key(),
+            reserve: reserve_account.key(),
+            beneficiary: grant.beneficiary,
+            total_amount: grant.total_amount,
+        });
+
+        // Before the cliff there may be nothing to claim yet; the reserve is created regardless.
+        if sdk::claimable_amount(reserve_account, None, now)? > 0 {
+            release_vested(
+                &mut ctx.accounts.reserve_account,
+                &mut ctx.accounts.vesting_account,
+                None,
+                &ctx.accounts.mint,
+                &ctx.accounts.treasury_token_account,
+                &ctx.accounts.beneficiary_token_account,
+                &ctx.accounts.token_program,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Returns the distributor's unclaimed funds to the admin once it has expired.
+    pub fn clawback_distributor(ctx: Context<ClawbackDistributor>) -> Result<()> {
+        require!(
+            ctx.accounts.owner.key() == ctx.accounts.config.admin,
+            ErrorCode::AccessDenied
+        );
+        let distributor = &mut ctx.accounts.distributor;
+        require!(!distributor.clawed_back, ErrorCode::DistributorClawedBack);
+        require!(
+            Clock::get()?.unix_timestamp >= distributor.expiry_time,
+            ErrorCode::DistributorNotExpired
+        );
+        let amount = distributor
+            .total_amount
+            .saturating_sub(distributor.claimed_amount);
+        distributor.clawed_back = true;
+
+        if amount > 0 {
+            let vesting_account = &ctx.accounts.vesting_account;
+            let seeds = &[
+                vesting_account.reserve_type.as_bytes(),
+                &[vesting_account.bump],
+            ];
+            let signer_seeds = &[&seeds[..]];
+            let cpi_accounts = TransferChecked {
+                mint: ctx.accounts.mint.to_account_info(),
+                from: ctx.accounts.treasury_token_account.to_account_info(),
+                to: ctx.accounts.owner_token_account.to_account_info(),
+                authority: vesting_account.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                cpi_accounts,
+                signer_seeds,
+            );
+            transfer_checked(cpi_ctx, amount, ctx.accounts.mint.decimals)?;
+        }
+
+        emit!(DistributorClawedBack {
+            distributor: distributor.key(),
+            amount,
+        });
+        Ok(())
+    }
+
+    pub fn claim_tokens(ctx: Context<ClaimTokens>, _reserve_type: String) -> Result<()> {
+        require!(
+            !ctx.accounts.reserve_account.tokenized,
+            ErrorCode::ReserveTokenized
+        );
+        release_vested(
+            &mut ctx.accounts.reserve_account,
+            &mut ctx.accounts.vesting_account,
+            ctx.accounts.tranche_schedule.as_deref(),
+            &ctx.accounts.mint,
+            &ctx.accounts.treasury_token_account,
+            &ctx.accounts.beneficiary_token_account,
+            &ctx.accounts.token_program,
+        )?;
+        Ok(())
+    }
+
+    /// Delivers a reserve's vested tokens to the beneficiary's associated token account without
+    /// their signature. Anyone may call it; if the pool has a funded `TipVault`, the caller earns
+    /// its tip, at most once a day per reserve.
+    pub fn crank_claim(ctx: Context<CrankClaim>, _reserve_type: String) -> Result<()> {
+        require!(
+            !ctx.accounts.reserve_account.tokenized,
+            ErrorCode::ReserveTokenized
+        );
+        let amount = release_vested(
+            &mut ctx.accounts.reserve_account,
+            &mut ctx.accounts.vesting_account,
+            ctx.accounts.tranche_schedule.as_deref(),
+            &ctx.accounts.mint,
+            &ctx.accounts.treasury_token_account,
+            &ctx.accounts.beneficiary_token_account,
+            &ctx.accounts.token_program,
+        )?;
+
+        let now = Clock::get()?.unix_timestamp;
+        let mut tip_lamports = 0;
+        if let Some(tip_vault) = ctx.accounts.tip_vault.as_mut() {
+            let reserve_account = &mut ctx.accounts.reserve_account;
+            let tip_due = now.saturating_sub(reserve_account.last_crank_tip_time)
+                >= MIN_CRANK_TIP_INTERVAL;
+            // An empty vault just means the crank goes unpaid; it never blocks the claim.
+            if tip_due && tip_vault.tip_lamports > 0 && tip_vault.balance >= tip_vault.tip_lamports {
+                tip_lamports = tip_vault.tip_lamports;
+                tip_vault.balance -= tip_lamports;
+                tip_vault.sub_lamports(tip_lamports)?;
+                ctx.accounts.cranker.add_lamports(tip_lamports)?;
+                reserve_account.last_crank_tip_time = now;
+            }
+        }
+
+        emit!(ClaimCranked {
+            reserve: ctx.accounts.reserve_account.key(),
+            beneficiary: ctx.accounts.beneficiary.key(),
+            cranker: ctx.accounts.cranker.key(),
+            amount,
+            tip_lamports,
+        });
+        Ok(())
+    }
+
+    /// Creates the pool's tip vault if needed and sets the tip paid per cranked claim.
+    pub fn configure_tip_vault(ctx: Context<ConfigureTipVault>, tip_lamports: u64) -> Result<()> {
+        require!(
+            ctx.accounts.admin.key() == ctx.accounts.config.admin,
+            ErrorCode::AccessDenied
+        );
+        require!(tip_lamports <= MAX_CRANK_TIP, ErrorCode::InvalidTip);
+        let tip_vault = &mut ctx.accounts.tip_vault;
+        if tip_vault.vesting_account == Pubkey::default() {
+            tip_vault.vesting_account = ctx.accounts.vesting_account.key();
+            tip_vault.bump = ctx.bumps.tip_vault;
+        }
+        tip_vault.tip_lamports = tip_lamports;
+        Ok(())
+    }
+
+    /// Adds lamports for crank tips. Anyone may top the vault up.
+    pub fn fund_tip_vault(ctx: Context<FundTipVault>, lamports: u64) -> Result<()> {
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.funder.to_account_info(),
+                    to: ctx.accounts.tip_vault.to_account_info(),
+                },
+            ),
+            lamports,
+        )?;
+        let tip_vault = &mut ctx.accounts.tip_vault;
+        tip_vault.balance = tip_vault
+            .balance
+            .checked_add(lamports)
+            .ok_or(ErrorCode::Overflow)?;
+        Ok(())
+    }
+
+    pub fn withdraw_tips(ctx: Context<WithdrawTips>, lamports: u64) -> Result<()> {
+        require!(
+            ctx.accounts.admin.key() == ctx.accounts.config.admin,
+            ErrorCode::AccessDenied
+        );
+        let tip_vault = &mut ctx.accounts.tip_vault;
+        tip_vault.balance = tip_vault
+            .balance
+            .checked_sub(lamports)
+            .ok_or(ErrorCode::InsufficientTipBalance)?;
+        ctx.accounts.tip_vault.sub_lamports(lamports)?;
+        ctx.accounts.admin.add_lamports(lamports)?;
+        Ok(())
+    }
+
+    /// Proposes moving a reserve to `new_beneficiary`, who takes over once they accept; `None`
+    /// cancels a pending transfer. The admin may propose too, for beneficiaries who lost their key,
+    /// but only on revocable reserves: a non-revocable grant never moves without its beneficiary.
+    pub fn transfer_beneficiary(
+        ctx: Context<TransferBeneficiary>,
+        new_beneficiary: Option<Pubkey>,
+    ) -> Result<()> {
+        let reserve_account = &mut ctx.accounts.reserve_account;
+        let authority = ctx.accounts.authority.key();
+        if authority != reserve_account.beneficiary {
+            require!(
+                authority == ctx.accounts.config.admin,
+                ErrorCode::AccessDenied
+            );
+            require!(reserve_account.revocable, ErrorCode::NotRevocable);
+        }
+        require!(!reserve_account.terminated, ErrorCode::ReserveTerminated);
+        require!(!reserve_account.tokenized, ErrorCode::ReserveTokenized);
+        reserve_account.pending_beneficiary = new_beneficiary;
+        emit!(BeneficiaryProposed {
+            reserve: reserve_account.key(),
+            beneficiary: reserve_account.beneficiary,
+            pending_beneficiary: new_beneficiary,
+            proposed_by: authority,
+        });
+        Ok(())
+    }
+
+    pub fn accept_beneficiary(ctx: Context<AcceptBeneficiary>) -> Result<()> {
+        let reserve_account = &mut ctx.accounts.reserve_account;
+        require!(
+            reserve_account.pending_beneficiary == Some(ctx.accounts.pending_beneficiary.key()),
+            ErrorCode::AccessDenied
+        );
+        require!(!reserve_account.terminated, ErrorCode::ReserveTerminated);
+        require!(!reserve_account.tokenized, ErrorCode::ReserveTokenized);
+        let previous_beneficiary = reserve_account.beneficiary;
+        reserve_account.beneficiary = ctx.accounts.pending_beneficiary.key();
+        reserve_account.pending_beneficiary = None;
+        emit!(BeneficiaryChanged {
+            reserve: reserve_account.key(),
+            previous_beneficiary,
+            beneficiary: reserve_account.beneficiary,
+        });
+        Ok(())
+    }
+
+    /// Lets `delegate` claim the reserve into the beneficiary's own token account. The
+    /// delegation lapses if the reserve moves to another beneficiary.
+    pub fn set_claim_delegate(ctx: Context<SetClaimDelegate>, delegate: Pubkey) -> Result<()> {
+        let claim_delegate = &mut ctx.accounts.claim_delegate;
+        claim_delegate.reserve = ctx.accounts.reserve_account.key();
+        claim_delegate.beneficiary = ctx.accounts.beneficiary.key();
+        claim_delegate.delegate = delegate;
+        claim_delegate.bump = ctx.bumps.claim_delegate;
+        emit!(ClaimDelegateSet {
+            reserve: claim_delegate.reserve,
+            delegate: Some(delegate),
+        });
+        Ok(())
+    }
+
+    pub fn remove_claim_delegate(ctx: Context<RemoveClaimDelegate>) -> Result<()> {
+        emit!(ClaimDelegateSet {
+            reserve: ctx.accounts.reserve_account.key(),
+            delegate: None,
+        });
+        Ok(())
+    }
+
+    pub fn claim_as_delegate(ctx: Context<ClaimAsDelegate>, _reserve_type: String) -> Result<()> {
+        require!(
+            !ctx.accounts.reserve_account.tokenized,
+            ErrorCode::ReserveTokenized
+        );
+        release_vested(
+            &mut ctx.accounts.reserve_account,
+            &mut ctx.accounts.vesting_account,
+            ctx.accounts.tranche_schedule.as_deref(),
+            &ctx.accounts.mint,
+            &ctx.accounts.treasury_token_account,
+            &ctx.accounts.beneficiary_token_account,
+            &ctx.accounts.token_program,
+        )?;
+        Ok(())
+    }
+
+    /// Mints a Token-2022 NFT (supply 1, decimals 0) for the reserve to its beneficiary. From then
+    /// on the reserve is claimed with `claim_position` by whoever holds the NFT; a non-transferable
+    /// position stays with the beneficiary but still shows up in their wallet.
+    pub fn tokenize_reserve(ctx: Context<TokenizeReserve>, non_transferable: bool) -> Result<()> {
+        let authority = ctx.accounts.authority.key();
+        let reserve_account = &mut ctx.accounts.reserve_account;
+        require!(
+            authority == reserve_account.beneficiary || authority == ctx.accounts.config.admin,
+            ErrorCode::AccessDenied
+        );
+        require!(!reserve_account.terminated, ErrorCode::ReserveTerminated);
+        require!(!reserve_account.tokenized, ErrorCode::ReserveTokenized);
+        require!(
+            reserve_account.version == RESERVE_VERSION,
+            ErrorCode::NotMigrated
+        );
+        reserve_account.tokenized = true;
+        reserve_account.pending_beneficiary = None;
 
+        let reserve_key = reserve_account.key();
         let seeds = &[
-            ctx.accounts.vesting_account.reserve_type.as_bytes(),
-            &[ctx.accounts.vesting_account.bump],
+            b"position".as_ref(),
+            reserve_key.as_ref(),
+            &[ctx.bumps.position_mint],
         ];
         let signer_seeds = &[&seeds[..]];
-        let cpi_program = ctx.accounts.token_program.to_account_info();
-        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
-        transfer_checked(cpi_ctx, claimable as u64, ctx.accounts.mint.decimals)?;
-        
-        // Calculate correctly next claim time using calendar months
-        let next_claim_time = if periods < 0 {
-             v_start_time
+        let position_mint = ctx.accounts.position_mint.to_account_info();
+        let token_program = ctx.accounts.position_token_program.to_account_info();
+
+        let extensions: &[ExtensionType] = if non_transferable {
+            &[ExtensionType::NonTransferable]
+        } else {
+            &[]
+        };
+        let space = ExtensionType::try_calculate_account_len::<spl_token_2022::state::Mint>(
+            extensions,
+        )?;
+        let system_program = ctx.accounts.system_program.to_account_info();
+        let rent = Rent::get()?.minimum_balance(space);
+        if position_mint.lamports() == 0 {
+            anchor_lang::system_program::create_account(
+                CpiContext::new_with_signer(
+                    system_program,
+                    anchor_lang::system_program::CreateAccount {
+                        from: ctx.accounts.authority.to_account_info(),
+                        to: position_mint.clone(),
+                    },
+                    signer_seeds,
+                ),
+                rent,
+                space as u64,
+                &token_2022::ID,
+            )?;
+        } else {
+            // The mint address is public, so anyone can send it lamports first, which would
+            // make `create_account` fail; top it up to rent and claim it piecewise instead.
+            let rent_due = rent.saturating_sub(position_mint.lamports());
+            if rent_due > 0 {
+                anchor_lang::system_program::transfer(
+                    CpiContext::new(
+                        system_program.clone(),
+                        anchor_lang::system_program::Transfer {
+                            from: ctx.accounts.authority.to_account_info(),
+                            to: position_mint.clone(),
+                        },
+                    ),
+                    rent_due,
+                )?;
+            }
+            anchor_lang::system_program::allocate(
+                CpiContext::new_with_signer(
+                    system_program.clone(),
+                    anchor_lang::system_program::Allocate {
+                        account_to_allocate: position_mint.clone(),
+                    },
+                    signer_seeds,
+                ),
+                space as u64,
+            )?;
+            anchor_lang::system_program::assign(
+                CpiContext::new_with_signer(
+                    system_program,
+                    anchor_lang::system_program::Assign {
+                        account_to_assign: position_mint.clone(),
+                    },
+                    signer_seeds,
+                ),
+                &token_2022::ID,
+            )?;
+        }
+        if non_transferable {
+            non_transferable_mint_initialize(CpiContext::new(
+                token_program.clone(),
+                NonTransferableMintInitialize {
+                    token_program_id: token_program.clone(),
+                    mint: position_mint.clone(),
+                },
+            ))?;
+        }
+        token_2022::initialize_mint2(
+            CpiContext::new(
+                token_program.clone(),
+                token_2022::InitializeMint2 {
+                    mint: position_mint.clone(),
+                },
+            ),
+            0,
+            &position_mint.key(),
+            None,
+        )?;
+        associated_token::create(CpiContext::new(
+            ctx.accounts.associated_token_program.to_account_info(),
+            associated_token::Create {
+                payer: ctx.accounts.authority.to_account_info(),
+                associated_token: ctx.accounts.position_token_account.to_account_info(),
+                authority: ctx.accounts.beneficiary.to_account_info(),
+                mint: position_mint.clone(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+                token_program: token_program.clone(),
+            },
+        ))?;
+        token_2022::mint_to(
+            CpiContext::new_with_signer(
+                token_program.clone(),
+                token_2022::MintTo {
+                    mint: position_mint.clone(),
+                    to: ctx.accounts.position_token_account.to_account_info(),
+                    authority: position_mint.clone(),
+                },
+                signer_seeds,
+            ),
+            1,
+        )?;
+        // Dropping the mint authority fixes the supply at one.
+        token_2022::set_authority(
+            CpiContext::new_with_signer(
+                token_program,
+                token_2022::SetAuthority {
+                    current_authority: position_mint.clone(),
+                    account_or_mint: position_mint.clone(),
+                },
+                signer_seeds,
+            ),
+            AuthorityType::MintTokens,
+            None,
+        )?;
+
+        emit!(ReserveTokenized {
+            reserve: reserve_key,
+            position_mint: position_mint.key(),
+            beneficiary: ctx.accounts.beneficiary.key(),
+            non_transferable,
+        });
+        Ok(())
+    }
+
+    /// Claims a tokenized reserve for the holder of its position NFT.
+    pub fn claim_position(ctx: Context<ClaimPosition>, _reserve_type: String) -> Result<()> {
+        require!(
+            ctx.accounts.reserve_account.tokenized,
+            ErrorCode::ReserveNotTokenized
+        );
+        release_vested(
+            &mut ctx.accounts.reserve_account,
+            &mut ctx.accounts.vesting_account,
+            ctx.accounts.tranche_schedule.as_deref(),
+            &ctx.accounts.mint,
+            &ctx.accounts.treasury_token_account,
+            &ctx.accounts.holder_token_account,
+            &ctx.accounts.token_program,
+        )?;
+        Ok(())
+    }
+
+    /// Reports what the beneficiary can claim now and the unlocks still to come. Meant to be
+    /// simulated; pass `after` to page through schedules longer than `MAX_PROJECTED_UNLOCKS`.
+    pub fn get_reserve_status(
+        ctx: Context<GetReserveStatus>,
+        after: Option<i64>,
+    ) -> Result<ReserveStatus> {
+        sdk::reserve_status(
+            &ctx.accounts.reserve_account,
+            ctx.accounts.tranche_schedule.as_deref(),
+            Clock::get()?.unix_timestamp,
+            after,
+            MAX_PROJECTED_UNLOCKS,
+        )
+    }
+
+    /// Terminates a revocable reserve: whatever has vested but not been claimed goes to the
+    /// beneficiary and the unvested remainder goes back to the owner.
+    pub fn revoke_reserve(ctx: Context<RevokeReserve>) -> Result<()> {
+        require!(
+            ctx.accounts.owner.key() == ctx.accounts.config.admin,
+            ErrorCode::AccessDenied
+        );
+        let reserve_account = &mut ctx.accounts.reserve_account;
+        require!(reserve_account.revocable, ErrorCode::NotRevocable);
+        require!(!reserve_account.terminated, ErrorCode::ReserveTerminated);
+        require!(
+            reserve_account.version == RESERVE_VERSION,
+            ErrorCode::NotMigrated
+        );
+
+        // Tokenized reserves settle with whoever holds the position NFT.
+        let recipient = if reserve_account.tokenized {
+            let position = ctx
+                .accounts
+                .position_token_account
+                .as_ref()
+                .ok_or(ErrorCode::InvalidPositionAccount)?;
+            require!(
+                position.mint == position_mint_address(&reserve_account.key())
+                    && position.amount == 1,
+                ErrorCode::InvalidPositionAccount
+            );
+            position.owner
         } else {
-             // Use checked_add_months if available, or fallback to simple logic?
-             // Chrono 0.4.23+ supports checked_add_months
-             match start_dt.checked_add_months(chrono::Months::new(periods as u32)) {
-                 Some(dt) => dt.timestamp(),
-                 None => v_start_time + (periods * 30 * 24 * 60 * 60), // Fallback (should not happen)
-             }
+            reserve_account.beneficiary
         };
+        require_keys_eq!(
+            ctx.accounts.beneficiary_token_account.owner,
+            recipient,
+            anchor_lang::error::ErrorCode::ConstraintTokenOwner
+        );
+
+        let vested = reserve_account.vested_amount(
+            Clock::get()?.unix_timestamp,
+            ctx.accounts.tranche_schedule.as_deref(),
+        )?;
+        let settled = vested.saturating_sub(reserve_account.amount_withdrawn);
+        let clawback = reserve_account.total_amount.saturating_sub(vested);
+        reserve_account.amount_withdrawn = vested;
+        reserve_account.terminated = true;
+
+        let vesting_account = &mut ctx.accounts.vesting_account;
+        vesting_account.total_withdrawn = vesting_account.total_withdrawn.saturating_add(settled);
+        vesting_account.total_allocated = vesting_account.total_allocated.saturating_sub(clawback);
+
+        let seeds = &[
+            vesting_account.reserve_type.as_bytes(),
+            &[vesting_account.bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+        let decimals = ctx.accounts.mint.decimals;
+        for (destination, amount) in [
+            (&ctx.accounts.beneficiary_token_account, settled),
+            (&ctx.accounts.owner_token_account, clawback),
+        ] {
+            if amount > 0 {
+                let cpi_accounts = TransferChecked {
+                    mint: ctx.accounts.mint.to_account_info(),
+                    from: ctx.accounts.treasury_token_account.to_account_info(),
+                    to: destination.to_account_info(),
+                    authority: vesting_account.to_account_info(),
+                };
+                let cpi_program = ctx.accounts.token_program.to_account_info();
+                let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+                transfer_checked(cpi_ctx, amount, decimals)?;
+            }
+        }
 
-        emit!(TokensClaimed {
-            claimed_amount: claimable as u64,
-            next_claim_timestamp: next_claim_time,
-            decimals: ctx.accounts.mint.decimals,
+        emit!(ReserveRevoked {
+            reserve: reserve_account.key(),
+            beneficiary: reserve_account.beneficiary,
+            settled_amount: settled,
+            clawback_amount: clawback,
+            decimals,
         });
+        Ok(())
+    }
+
+    /// Sets the unlock tranches of a `Custom` reserve. Tranches can be replaced until the first claim.
+    pub fn initialize_tranches(ctx: Context<InitializeTranches>, tranches: Vec<Tranche>) -> Result<()> {
+        require!(
+            ctx.accounts.owner.key() == ctx.accounts.config.admin,
+            ErrorCode::AccessDenied
+        );
+        let reserve_account = &ctx.accounts.reserve_account;
+        TrancheSchedule::validate(&tranches, reserve_account.total_amount)?;
+        let tranche_schedule = &mut ctx.accounts.tranche_schedule;
+        tranche_schedule.reserve = reserve_account.key();
+        tranche_schedule.bump = ctx.bumps.tranche_schedule;
+        tranche_schedule.tranches = tranches;
+        Ok(())
+    }
+
+    pub fn update_tranches(ctx: Context<UpdateTranches>, tranches: Vec<Tranche>) -> Result<()> {
+        require!(
+            ctx.accounts.owner.key() == ctx.accounts.config.admin,
+            ErrorCode::AccessDenied
+        );
+        let reserve_account = &ctx.accounts.reserve_account;
+        require!(
+            reserve_account.amount_withdrawn == 0 && !reserve_account.terminated,
+            ErrorCode::TranchesLocked
+        );
+        TrancheSchedule::validate(&tranches, reserve_account.total_amount)?;
+        ctx.accounts.tranche_schedule.tranches = tranches;
+        Ok(())
+    }
+
+    /// Brings a reserve up to `RESERVE_VERSION`, growing it if it predates versioning and checking
+    /// that its former `i64` amounts are non-negative. Anyone may pay for it, so beneficiaries
+    /// aren't locked out of claiming while waiting for the admin.
+    pub fn migrate_reserve(ctx: Context<MigrateReserve>) -> Result<()> {
+        let reserve_info = ctx.accounts.reserve_account.to_account_info();
+        let old_len = reserve_info.data_len();
+        require!(
+            old_len >= LEGACY_RESERVE_LEN
+                && reserve_info.try_borrow_data()?[..8] == *ReserveAccount::DISCRIMINATOR,
+            ErrorCode::InvalidReserveAccount
+        );
+
+        let new_len = 8 + ReserveAccount::INIT_SPACE;
+        if old_len < new_len {
+            let rent_due = Rent::get()?
+                .minimum_balance(new_len)
+                .saturating_sub(reserve_info.lamports());
+            if rent_due > 0 {
+                anchor_lang::system_program::transfer(
+                    CpiContext::new(
+                        ctx.accounts.system_program.to_account_info(),
+                        anchor_lang::system_program::Transfer {
+                            from: ctx.accounts.payer.to_account_info(),
+                            to: reserve_info.clone(),
+                        },
+                    ),
+                    rent_due,
+                )?;
+            }
+            reserve_info.resize(new_len)?;
+        }
 
+        let mut data = reserve_info.try_borrow_mut_data()?;
+        let mut reserve_account = ReserveAccount::try_deserialize(&mut &data[..])?;
+        require!(
+            reserve_account.version < RESERVE_VERSION,
+            ErrorCode::AlreadyMigrated
+        );
+        // Amounts were `i64` before version 2; a negative one now reads above `i64::MAX`.
+        require!(
+            [
+                reserve_account.total_amount,
+                reserve_account.amount_withdrawn,
+                reserve_account.monthly_claim,
+                reserve_account.tge_unlock_amount,
+                reserve_account.cliff_release_amount,
+            ]
+            .iter()
+            .all(|&amount| amount <= i64::MAX as u64),
+            ErrorCode::InvalidReserveAccount
+        );
+        reserve_account.version = RESERVE_VERSION;
+        reserve_account.try_serialize(&mut &mut data[..])?;
         Ok(())
     }
+
     pub fn close_reserve_account(ctx: Context<CloseReserveAccount>) -> Result<()> {
         let reserve_account = &ctx.accounts.reserve_account;
         let current_time = Clock::get()?.unix_timestamp;
     
         require!(
-            current_time >= reserve_account.end_time,
+            reserve_account.terminated || current_time >= reserve_account.end_time,
             ErrorCode::VestingNotOver
         );
         require!(
-            reserve_account.amount_withdrawn >= reserve_account.total_amount,
+            reserve_account.terminated
+                || reserve_account.amount_withdrawn >= reserve_account.total_amount,
             ErrorCode::FundsRemaining
         );
 
         Ok(())
     }
      pub fn close_vesting_account(ctx: Context<CloseVestingAccount>) -> Result<()> {
+    require!(
+        ctx.accounts.owner.key() == ctx.accounts.config.admin,
+        ErrorCode::AccessDenied
+    );
     let vesting_account = &ctx.accounts.vesting_account;
     let treasury_balance = ctx.accounts.treasury_token_account.amount;
    
@@ -198,20 +1047,116 @@ pub mod vesting {
 
 }
 
+/// Pays out everything vested in `reserve_account` but not yet withdrawn to `destination`.
+fn release_vested<'info>(
+    reserve_account: &mut Account<'info, ReserveAccount>,
+    vesting_account: &mut Account<'info, VestingAccount>,
+    tranches: Option<&TrancheSchedule>,
+    mint: &InterfaceAccount<'info, Mint>,
+    treasury_token_account: &InterfaceAccount<'info, TokenAccount>,
+    destination: &InterfaceAccount<'info, TokenAccount>,
+    token_program: &Interface<'info, TokenInterface>,
+) -> Result<u64> {
+    let current_time = Clock::get()?.unix_timestamp;
+
+    require!(!reserve_account.terminated, ErrorCode::ReserveTerminated);
+    require!(
+        reserve_account.version == RESERVE_VERSION,
+        ErrorCode::NotMigrated
+    );
+    let v_start_time = reserve_account.start_time + reserve_account.cliff_time;
+    let claimable = reserve_account
+        .vested_amount(current_time, tranches)?
+        .saturating_sub(reserve_account.amount_withdrawn);
+
+    // Only the TGE unlock can be claimed before the cliff ends.
+    if current_time < v_start_time && claimable == 0 {
+        return Err(ErrorCode::CliffPeriodNotEnded.into());
+    }
+
+    if claimable == 0 {
+        return Err(ErrorCode::ClaimNotAvailableYet.into());
+    }
+    reserve_account.amount_withdrawn = reserve_account.amount_withdrawn.saturating_add(claimable);
+    vesting_account.total_withdrawn = vesting_account.total_withdrawn.saturating_add(claimable);
+
+    let cpi_accounts = TransferChecked {
+        mint: mint.to_account_info(),
+        from: treasury_token_account.to_account_info(),
+        to: destination.to_account_info(),
+        authority: vesting_account.to_account_info(),
+    };
+
+    let seeds = &[
+        vesting_account.reserve_type.as_bytes(),
+        &[vesting_account.bump],
+    ];
+    let signer_seeds = &[&seeds[..]];
+    let cpi_program = token_program.to_account_info();
+    let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+    transfer_checked(cpi_ctx, claimable, mint.decimals)?;
+
+    let next_claim_time = match tranches {
+        Some(tranches) if reserve_account.schedule == ScheduleKind::Custom => {
+            tranches.next_unlock_time(current_time)
+        }
+        _ => next_unlock_time(reserve_account.schedule, v_start_time, current_time)?,
+    };
+
+    emit!(TokensClaimed {
+        claimed_amount: claimable,
+        next_claim_timestamp: next_claim_time,
+        decimals: mint.decimals,
+    });
+
+    Ok(claimable)
+}
+
 #[derive(Accounts)]
-#[instruction(reserve_type: String)]
-pub struct CreateVestingAccount<'info> {
+pub struct InitializeConfig<'info> {
     #[account(mut)]
     pub signer: Signer<'info>,
     #[account(
         init,
-        space = 8 + VestingAccount::INIT_SPACE,
+        space = 8 + Config::INIT_SPACE,
         payer = signer,
-        seeds = [reserve_type.as_bytes()],
+        seeds = [b"config"],
         bump
     )]
-    pub vesting_account: Account<'info, VestingAccount>,
-    pub mint: InterfaceAccount<'info, Mint>,
+    pub config: Account<'info, Config>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeAdmin<'info> {
+    pub admin: Signer<'info>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAdmin<'info> {
+    pub pending_admin: Signer<'info>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+}
+
+#[derive(Accounts)]
+#[instruction(reserve_type: String)]
+pub struct CreateVestingAccount<'info> {
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        init,
+        space = 8 + VestingAccount::INIT_SPACE,
+        payer = signer,
+        seeds = [reserve_type.as_bytes()],
+        bump
+    )]
+    pub vesting_account: Account<'info, VestingAccount>,
+    pub mint: InterfaceAccount<'info, Mint>,
     #[account(
         init,
         payer = signer,
@@ -226,6 +1171,7 @@ pub struct CreateVestingAccount<'info> {
 }
 
 #[derive(Accounts)]
+#[instruction(index: u64)]
 pub struct CreateReserveAccount<'info> {
     #[account(mut)]
     pub owner: Signer<'info>,
@@ -233,13 +1179,20 @@ pub struct CreateReserveAccount<'info> {
     pub beneficiary: SystemAccount<'info>,
     #[account(mut)]
     pub owner_token_account: InterfaceAccount<'info, TokenAccount>,
-    #[account(has_one = owner)]
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut)]
     pub vesting_account: Account<'info, VestingAccount>,
     #[account(
         init,
         space = 8 + ReserveAccount::INIT_SPACE,
         payer = owner,
-        seeds = [b"reserve", vesting_account.key().as_ref()],
+        seeds = [
+            b"reserve",
+            vesting_account.key().as_ref(),
+            beneficiary.key().as_ref(),
+            index.to_le_bytes().as_ref()
+        ],
         bump
     )]
     pub reserve_account: Account<'info, ReserveAccount>,
@@ -250,15 +1203,112 @@ pub struct CreateReserveAccount<'info> {
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+#[instruction(index: u64)]
+pub struct CreateDistributor<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(mut, token::mint = mint, token::authority = owner)]
+    pub owner_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    #[account(has_one = treasury_token_account, has_one = mint)]
+    pub vesting_account: Account<'info, VestingAccount>,
+    #[account(
+        init,
+        space = 8 + Distributor::INIT_SPACE,
+        payer = owner,
+        seeds = [
+            b"distributor",
+            vesting_account.key().as_ref(),
+            index.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub distributor: Account<'info, Distributor>,
+    #[account(mut)]
+    pub treasury_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(reserve_type: String)]
+pub struct ClaimFromDistributor<'info> {
+    #[account(mut)]
+    pub beneficiary: Signer<'info>,
+    #[account(mut, has_one = vesting_account)]
+    pub distributor: Account<'info, Distributor>,
+    /// Records the claim for good, so each grant is claimed once even after its reserve is
+    /// closed.
+    #[account(
+        init,
+        space = 8 + DistributorClaim::INIT_SPACE,
+        payer = beneficiary,
+        seeds = [
+            b"distributor_claim",
+            distributor.key().as_ref(),
+            beneficiary.key().as_ref()
+        ],
+        bump
+    )]
+    pub claim_receipt: Account<'info, DistributorClaim>,
+    #[account(
+        init,
+        space = 8 + ReserveAccount::INIT_SPACE,
+        payer = beneficiary,
+        seeds = [
+            b"reserve",
+            distributor.key().as_ref(),
+            beneficiary.key().as_ref()
+        ],
+        bump
+    )]
+    pub reserve_account: Account<'info, ReserveAccount>,
+    #[account(
+        mut,
+        seeds = [reserve_type.as_ref()],
+        bump = vesting_account.bump,
+        has_one = treasury_token_account,
+        has_one = mint
+    )]
+    pub vesting_account: Account<'info, VestingAccount>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(mut)]
+    pub treasury_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, token::mint = mint, token::authority = beneficiary)]
+    pub beneficiary_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClawbackDistributor<'info> {
+    pub owner: Signer<'info>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut, has_one = vesting_account)]
+    pub distributor: Account<'info, Distributor>,
+    #[account(has_one = treasury_token_account, has_one = mint)]
+    pub vesting_account: Account<'info, VestingAccount>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(mut)]
+    pub treasury_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, token::mint = mint, token::authority = owner)]
+    pub owner_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
 #[derive(Accounts)]
 #[instruction(reserve_type: String)]
 pub struct ClaimTokens<'info> {
     #[account(mut)]
     pub beneficiary: Signer<'info>,
+    /// Reserves created before per-beneficiary seeds still live at `[b"reserve", vesting_account]`,
+    /// so the reserve is tied to its pool and beneficiary by `has_one` instead of by seeds.
     #[account(
         mut,
-        seeds = [b"reserve", vesting_account.key().as_ref()],
-        bump = reserve_account.bump,
         has_one = beneficiary,
         has_one = vesting_account,
     )]
@@ -279,15 +1329,366 @@ pub struct ClaimTokens<'info> {
     pub token_program: Interface<'info, TokenInterface>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
+    /// Required for `Custom` reserves.
+    #[account(
+        seeds = [b"tranches", reserve_account.key().as_ref()],
+        bump = tranche_schedule.bump,
+    )]
+    pub tranche_schedule: Option<Account<'info, TrancheSchedule>>,
+}
+
+
+#[derive(Accounts)]
+#[instruction(reserve_type: String)]
+pub struct CrankClaim<'info> {
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+    /// CHECK: Only receives tokens; tied to the reserve by `has_one`
+    pub beneficiary: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        has_one = beneficiary,
+        has_one = vesting_account,
+    )]
+    pub reserve_account: Account<'info, ReserveAccount>,
+    #[account(
+        mut,
+        seeds = [reserve_type.as_ref()],
+        bump = vesting_account.bump,
+        has_one = treasury_token_account,
+        has_one = mint
+    )]
+    pub vesting_account: Account<'info, VestingAccount>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(mut)]
+    pub treasury_token_account: InterfaceAccount<'info, TokenAccount>,
+    /// Cranked claims only ever go to the beneficiary's associated token account.
+    #[account(
+        init_if_needed,
+        payer = cranker,
+        associated_token::mint = mint,
+        associated_token::authority = beneficiary,
+        associated_token::token_program = token_program
+    )]
+    pub beneficiary_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    /// Required for `Custom` reserves.
+    #[account(
+        seeds = [b"tranches", reserve_account.key().as_ref()],
+        bump = tranche_schedule.bump,
+    )]
+    pub tranche_schedule: Option<Account<'info, TrancheSchedule>>,
+    #[account(
+        mut,
+        seeds = [b"tip_vault", vesting_account.key().as_ref()],
+        bump = tip_vault.bump,
+    )]
+    pub tip_vault: Option<Account<'info, TipVault>>,
+}
+
+#[derive(Accounts)]
+pub struct ConfigureTipVault<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    pub vesting_account: Account<'info, VestingAccount>,
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + TipVault::INIT_SPACE,
+        seeds = [b"tip_vault", vesting_account.key().as_ref()],
+        bump
+    )]
+    pub tip_vault: Account<'info, TipVault>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FundTipVault<'info> {
+    #[account(mut)]
+    pub funder: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"tip_vault", tip_vault.vesting_account.as_ref()],
+        bump = tip_vault.bump,
+    )]
+    pub tip_vault: Account<'info, TipVault>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawTips<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [b"tip_vault", tip_vault.vesting_account.as_ref()],
+        bump = tip_vault.bump,
+    )]
+    pub tip_vault: Account<'info, TipVault>,
+}
+
+#[derive(Accounts)]
+pub struct TransferBeneficiary<'info> {
+    /// The current beneficiary or the admin.
+    pub authority: Signer<'info>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut)]
+    pub reserve_account: Account<'info, ReserveAccount>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptBeneficiary<'info> {
+    pub pending_beneficiary: Signer<'info>,
+    #[account(mut)]
+    pub reserve_account: Account<'info, ReserveAccount>,
+}
+
+#[derive(Accounts)]
+pub struct SetClaimDelegate<'info> {
+    #[account(mut)]
+    pub beneficiary: Signer<'info>,
+    #[account(has_one = beneficiary)]
+    pub reserve_account: Account<'info, ReserveAccount>,
+    #[account(
+        init_if_needed,
+        payer = beneficiary,
+        space = 8 + ClaimDelegate::INIT_SPACE,
+        seeds = [b"claim_delegate", reserve_account.key().as_ref()],
+        bump
+    )]
+    pub claim_delegate: Account<'info, ClaimDelegate>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveClaimDelegate<'info> {
+    #[account(mut)]
+    pub beneficiary: Signer<'info>,
+    #[account(has_one = beneficiary)]
+    pub reserve_account: Account<'info, ReserveAccount>,
+    #[account(
+        mut,
+        close = beneficiary,
+        seeds = [b"claim_delegate", reserve_account.key().as_ref()],
+        bump = claim_delegate.bump,
+    )]
+    pub claim_delegate: Account<'info, ClaimDelegate>,
+}
+
+#[derive(Accounts)]
+#[instruction(reserve_type: String)]
+pub struct ClaimAsDelegate<'info> {
+    pub delegate: Signer<'info>,
+    /// CHECK: Only receives tokens; tied to the reserve by `has_one`
+    pub beneficiary: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        has_one = beneficiary,
+        has_one = vesting_account,
+    )]
+    pub reserve_account: Account<'info, ReserveAccount>,
+    /// Appointed by the current beneficiary; delegations from a previous beneficiary fail `has_one`.
+    #[account(
+        seeds = [b"claim_delegate", reserve_account.key().as_ref()],
+        bump = claim_delegate.bump,
+        has_one = delegate,
+        has_one = beneficiary,
+    )]
+    pub claim_delegate: Account<'info, ClaimDelegate>,
+    #[account(
+        mut,
+        seeds = [reserve_type.as_ref()],
+        bump = vesting_account.bump,
+        has_one = treasury_token_account,
+        has_one = mint
+    )]
+    pub vesting_account: Account<'info, VestingAccount>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(mut)]
+    pub treasury_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = beneficiary,
+        token::token_program = token_program,
+    )]
+    pub beneficiary_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+    /// Required for `Custom` reserves.
+    #[account(
+        seeds = [b"tranches", reserve_account.key().as_ref()],
+        bump = tranche_schedule.bump,
+    )]
+    pub tranche_schedule: Option<Account<'info, TrancheSchedule>>,
 }
 
+#[derive(Accounts)]
+pub struct TokenizeReserve<'info> {
+    /// The beneficiary or the admin; pays for the position mint and account.
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    /// CHECK: Receives the position NFT; tied to the reserve by `has_one`
+    pub beneficiary: UncheckedAccount<'info>,
+    #[account(mut, has_one = beneficiary)]
+    pub reserve_account: Account<'info, ReserveAccount>,
+    /// CHECK: Created and initialized as a Token-2022 mint in the handler
+    #[account(
+        mut,
+        seeds = [b"position", reserve_account.key().as_ref()],
+        bump
+    )]
+    pub position_mint: UncheckedAccount<'info>,
+    /// CHECK: The beneficiary's associated token account for the position, created in the handler
+    #[account(mut)]
+    pub position_token_account: UncheckedAccount<'info>,
+    pub position_token_program: Program<'info, Token2022>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(reserve_type: String)]
+pub struct ClaimPosition<'info> {
+    pub holder: Signer<'info>,
+    #[account(mut, has_one = vesting_account)]
+    pub reserve_account: Account<'info, ReserveAccount>,
+    #[account(
+        token::mint = position_mint_address(&reserve_account.key()),
+        token::authority = holder,
+        constraint = position_token_account.amount == 1 @ ErrorCode::InvalidPositionAccount,
+    )]
+    pub position_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [reserve_type.as_ref()],
+        bump = vesting_account.bump,
+        has_one = treasury_token_account,
+        has_one = mint
+    )]
+    pub vesting_account: Account<'info, VestingAccount>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(mut)]
+    pub treasury_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, token::mint = mint, token::authority = holder)]
+    pub holder_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+    /// Required for `Custom` reserves.
+    #[account(
+        seeds = [b"tranches", reserve_account.key().as_ref()],
+        bump = tranche_schedule.bump,
+    )]
+    pub tranche_schedule: Option<Account<'info, TrancheSchedule>>,
+}
+
+#[derive(Accounts)]
+pub struct GetReserveStatus<'info> {
+    pub reserve_account: Account<'info, ReserveAccount>,
+    /// Required for `Custom` reserves.
+    #[account(
+        seeds = [b"tranches", reserve_account.key().as_ref()],
+        bump = tranche_schedule.bump,
+    )]
+    pub tranche_schedule: Option<Account<'info, TrancheSchedule>>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeReserve<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut, has_one = vesting_account)]
+    pub reserve_account: Account<'info, ReserveAccount>,
+    #[account(
+        mut,
+        seeds = [vesting_account.reserve_type.as_bytes()],
+        bump = vesting_account.bump,
+        has_one = treasury_token_account,
+        has_one = mint,
+    )]
+    pub vesting_account: Account<'info, VestingAccount>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(mut)]
+    pub treasury_token_account: InterfaceAccount<'info, TokenAccount>,
+    /// Owned by the beneficiary, or by the position holder if the reserve is tokenized.
+    #[account(mut, token::mint = mint)]
+    pub beneficiary_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, token::mint = mint, token::authority = owner)]
+    pub owner_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+    /// Required for `Custom` reserves.
+    #[account(
+        seeds = [b"tranches", reserve_account.key().as_ref()],
+        bump = tranche_schedule.bump,
+    )]
+    pub tranche_schedule: Option<Account<'info, TrancheSchedule>>,
+    /// Required for tokenized reserves: the holder's position NFT account.
+    pub position_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+}
+
+#[derive(Accounts)]
+#[instruction(tranches: Vec<Tranche>)]
+pub struct InitializeTranches<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    #[account(constraint = reserve_account.schedule == ScheduleKind::Custom @ ErrorCode::InvalidSchedule)]
+    pub reserve_account: Account<'info, ReserveAccount>,
+    #[account(
+        init,
+        space = TrancheSchedule::space(tranches.len()),
+        payer = owner,
+        seeds = [b"tranches", reserve_account.key().as_ref()],
+        bump
+    )]
+    pub tranche_schedule: Account<'info, TrancheSchedule>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(tranches: Vec<Tranche>)]
+pub struct UpdateTranches<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    pub reserve_account: Account<'info, ReserveAccount>,
+    #[account(
+        mut,
+        seeds = [b"tranches", reserve_account.key().as_ref()],
+        bump = tranche_schedule.bump,
+        realloc = TrancheSchedule::space(tranches.len()),
+        realloc::payer = owner,
+        realloc::zero = false,
+    )]
+    pub tranche_schedule: Account<'info, TrancheSchedule>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateReserve<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: Legacy reserves can't be deserialized as `ReserveAccount`; the discriminator is checked in the handler
+    #[account(mut, owner = crate::ID)]
+    pub reserve_account: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
 
 #[derive(Accounts)]
 pub struct CloseReserveAccount<'info> {
     #[account(
         mut,
-        seeds = [b"reserve", vesting_account.key().as_ref()],
-        bump = reserve_account.bump,
         close = beneficiary,
         has_one = beneficiary,
         has_one = vesting_account,
@@ -302,14 +1703,32 @@ pub struct CloseReserveAccount<'info> {
     pub beneficiary: Signer<'info>,
     pub system_program: Program<'info, System>,
 }
+#[derive(Accounts)]
+pub struct MigrateVestingAccount<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    /// CHECK: Legacy vesting accounts may not deserialize as `VestingAccount`; the discriminator is checked in the handler
+    #[account(mut, owner = crate::ID)]
+    pub vesting_account: UncheckedAccount<'info>,
+    #[account(
+        seeds = [b"reserve", vesting_account.key().as_ref()],
+        bump = legacy_reserve.bump,
+    )]
+    pub legacy_reserve: Option<Account<'info, ReserveAccount>>,
+    pub system_program: Program<'info, System>,
+}
+
 #[derive(Accounts)]
 pub struct CloseVestingAccount<'info> {
     #[account(mut)]
     pub owner: Signer<'info>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
     #[account(
         mut,
         close = owner,
-        has_one = owner,
         has_one = treasury_token_account,
         has_one = mint,
     )]
@@ -322,6 +1741,14 @@ pub struct CloseVestingAccount<'info> {
     pub token_program: Interface<'info, TokenInterface>,
 }
 
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct Config {
+    pub admin: Pubkey,
+    pub pending_admin: Option<Pubkey>,
+    pub bump: u8,
+}
+
 #[account]
 #[derive(InitSpace, Debug)]
 pub struct VestingAccount {
@@ -331,6 +1758,11 @@ pub struct VestingAccount {
     #[max_len(50)]
     pub reserve_type: String,
     pub bump: u8,
+    pub version: u8,
+    /// Sum of `total_amount` over every reserve created in this pool.
+    pub total_allocated: u64,
+    pub total_withdrawn: u64,
+    pub reserve_count: u64,
 }
 
 #[account]
@@ -339,12 +1771,285 @@ pub struct ReserveAccount {
     pub beneficiary: Pubkey,
     pub start_time: i64,
     pub end_time: i64,
-    pub total_amount: i64,
-    pub amount_withdrawn: i64,
+    pub total_amount: u64,
+    pub amount_withdrawn: u64,
     pub cliff_time: i64,
-    pub monthly_claim: i64,
+    pub monthly_claim: u64,
     pub vesting_account: Pubkey,
     pub bump: u8,
+    pub version: u8,
+    /// The admin may terminate the grant with `revoke_reserve`.
+    pub revocable: bool,
+    /// Revoked; nothing further vests or can be claimed.
+    pub terminated: bool,
+    /// How `monthly_claim` is released; for periodic kinds it is the amount per period.
+    pub schedule: ScheduleKind,
+    /// Claimable from `start_time`, before the cliff.
+    pub tge_unlock_amount: u64,
+    /// Released in full when the cliff ends.
+    pub cliff_release_amount: u64,
+    /// Last time `crank_claim` paid a tip for this reserve.
+    pub last_crank_tip_time: i64,
+    /// Proposed by `transfer_beneficiary`, pending their acceptance.
+    pub pending_beneficiary: Option<Pubkey>,
+    /// Represented by a position NFT at `position_mint_address`; claims follow its holder.
+    pub tokenized: bool,
+    /// Left zeroed wherever a reserve is created or migrated; new reserve fields are carved
+    /// from here so reserves already migrated to this size never need another resize.
+    pub reserved: [u8; 5],
+}
+
+impl ReserveAccount {
+    /// Rejects grants that could never be paid out in full, so a bad parameter fails at creation
+    /// instead of locking tokens in the treasury.
+    pub fn validate(&self) -> Result<()> {
+        require!(self.total_amount > 0, ErrorCode::InvalidAmount);
+        require!(self.end_time > self.start_time, ErrorCode::InvalidEndTime);
+        let vesting_start = self
+            .start_time
+            .checked_add(self.cliff_time)
+            .ok_or(ErrorCode::InvalidCliff)?;
+        require!(
+            self.cliff_time >= 0 && vesting_start <= self.end_time,
+            ErrorCode::InvalidCliff
+        );
+        let upfront = self
+            .tge_unlock_amount
+            .checked_add(self.cliff_release_amount)
+            .ok_or(ErrorCode::InvalidUpfrontAmount)?;
+        require!(upfront <= self.total_amount, ErrorCode::InvalidUpfrontAmount);
+
+        let scheduled = self.total_amount - upfront;
+        match self.schedule {
+            // Tranches already say exactly when each amount unlocks.
+            ScheduleKind::Custom => require!(upfront == 0, ErrorCode::InvalidSchedule),
+            ScheduleKind::Linear => require!(self.end_time > vesting_start, ErrorCode::InvalidSchedule),
+            _ => require!(
+                scheduled == 0 || self.monthly_claim > 0,
+                ErrorCode::InvalidPeriodAmount
+            ),
+        }
+        if self.schedule != ScheduleKind::Custom {
+            let vested = vested_amount(
+                self.schedule,
+                vesting_start,
+                self.end_time,
+                scheduled,
+                self.monthly_claim,
+                self.end_time,
+            )?;
+            require!(vested == scheduled, ErrorCode::ScheduleIncomplete);
+        }
+        Ok(())
+    }
+
+    /// Amount vested by `now`: the TGE unlock from `start_time`, the cliff release once the cliff
+    /// ends, then the rest under the reserve's schedule. `Custom` reserves need their `TrancheSchedule`.
+    pub fn vested_amount(&self, now: i64, tranches: Option<&TrancheSchedule>) -> Result<u64> {
+        if self.schedule == ScheduleKind::Custom {
+            let tranches = tranches.ok_or(ErrorCode::MissingTranches)?;
+            return Ok(tranches.unlocked_amount(now));
+        }
+        if now < self.start_time {
+            return Ok(0);
+        }
+        let vesting_start = self.start_time + self.cliff_time;
+        if now < vesting_start {
+            return Ok(self.tge_unlock_amount);
+        }
+        let upfront = self.tge_unlock_amount + self.cliff_release_amount;
+        let scheduled = vested_amount(
+            self.schedule,
+            vesting_start,
+            self.end_time,
+            self.total_amount - upfront,
+            self.monthly_claim,
+            now,
+        )?;
+        Ok(upfront + scheduled)
+    }
+}
+
+/// Release schedule of a reserve. Periodic kinds release one period amount at the cliff end
+/// and at the start of each later period.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace, Debug)]
+pub enum ScheduleKind {
+    /// Calendar months; reserves created before schedule kinds existed read as `Monthly`.
+    Monthly,
+    /// Continuous per-second release between the cliff end and `end_time`.
+    Linear,
+    Daily,
+    Weekly,
+    /// Every three calendar months.
+    Quarterly,
+    /// Explicit unlock tranches kept in the reserve's `TrancheSchedule`.
+    Custom,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace, Debug)]
+pub struct Tranche {
+    pub unlock_time: i64,
+    /// Total released once this tranche unlocks, including earlier tranches.
+    pub cumulative_amount: u64,
+}
+
+#[account]
+#[derive(Debug)]
+pub struct TrancheSchedule {
+    pub reserve: Pubkey,
+    pub bump: u8,
+    pub tranches: Vec<Tranche>,
+}
+
+impl TrancheSchedule {
+    pub fn space(len: usize) -> usize {
+        8 + 32 + 1 + 4 + len * Tranche::INIT_SPACE
+    }
+
+    /// Tranches must strictly increase in time and amount and end at `total_amount`.
+    pub fn validate(tranches: &[Tranche], total_amount: u64) -> Result<()> {
+        require!(
+            !tranches.is_empty() && tranches.len() <= MAX_TRANCHES,
+            ErrorCode::InvalidTranches
+        );
+        let mut previous: Option<&Tranche> = None;
+        for tranche in tranches {
+            let increasing = match previous {
+                Some(previous) => {
+                    tranche.unlock_time > previous.unlock_time
+                        && tranche.cumulative_amount > previous.cumulative_amount
+                }
+                None => tranche.cumulative_amount > 0,
+            };
+            require!(increasing, ErrorCode::InvalidTranches);
+            previous = Some(tranche);
+        }
+        require!(
+            previous.map(|tranche| tranche.cumulative_amount) == Some(total_amount),
+            ErrorCode::InvalidTranches
+        );
+        Ok(())
+    }
+
+    /// Cumulative amount of the latest tranche unlocked by `now`.
+    pub fn unlocked_amount(&self, now: i64) -> u64 {
+        self.tranches
+            .iter()
+            .take_while(|tranche| tranche.unlock_time <= now)
+            .last()
+            .map_or(0, |tranche| tranche.cumulative_amount)
+    }
+
+    /// Unlock time of the first tranche after `now`, or of the last tranche once all have unlocked.
+    pub fn next_unlock_time(&self, now: i64) -> i64 {
+        self.tranches
+            .iter()
+            .find(|tranche| tranche.unlock_time > now)
+            .or(self.tranches.last())
+            .map_or(now, |tranche| tranche.unlock_time)
+    }
+}
+
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct ClaimDelegate {
+    pub reserve: Pubkey,
+    /// Beneficiary who appointed the delegate.
+    pub beneficiary: Pubkey,
+    pub delegate: Pubkey,
+    pub bump: u8,
+}
+
+/// Grants committed to by a Merkle root, turned into reserves as beneficiaries claim them.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct Distributor {
+    pub vesting_account: Pubkey,
+    /// `MerkleTree<Keccak160>` root over the `DistributorGrant::leaf` of every grant.
+    pub root: [u8; 20],
+    /// Funded into the treasury by `create_distributor`.
+    pub total_amount: u64,
+    /// Granted to reserves claimed so far.
+    pub claimed_amount: u64,
+    pub claimed_count: u64,
+    /// Claims close at this time, after which the admin may claw back the rest.
+    pub expiry_time: i64,
+    pub clawed_back: bool,
+    pub bump: u8,
+}
+
+/// Proof that a beneficiary claimed their distributor grant. Unlike the reserve it created, it
+/// can never be closed.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct DistributorClaim {
+    pub distributor: Pubkey,
+    pub beneficiary: Pubkey,
+    pub bump: u8,
+}
+
+/// Terms of a reserve created by `create_reserve`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ReserveParams {
+    pub start_time: i64,
+    pub end_time: i64,
+    pub total_amount: u64,
+    pub cliff_time: i64,
+    pub monthly_claim: u64,
+    pub revocable: bool,
+    pub schedule: ScheduleKind,
+    pub tge_unlock_amount: u64,
+    pub cliff_release_amount: u64,
+}
+
+/// A grant committed to by a distributor's Merkle root: the beneficiary followed by the
+/// `ReserveParams` fields.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct DistributorGrant {
+    pub beneficiary: Pubkey,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub total_amount: u64,
+    pub cliff_time: i64,
+    pub monthly_claim: u64,
+    pub revocable: bool,
+    pub schedule: ScheduleKind,
+    pub tge_unlock_amount: u64,
+    pub cliff_release_amount: u64,
+}
+
+impl DistributorGrant {
+    /// The Merkle leaf for this grant: its borsh encoding.
+    pub fn leaf(&self) -> Result<Vec<u8>> {
+        let mut leaf = Vec::new();
+        self.serialize(&mut leaf)?;
+        Ok(leaf)
+    }
+}
+
+/// Lamports paid out to whoever cranks claims for a pool's reserves.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct TipVault {
+    pub vesting_account: Pubkey,
+    pub tip_lamports: u64,
+    /// Lamports deposited through `fund_tip_vault` and not yet paid out; rent is kept aside.
+    pub balance: u64,
+    pub bump: u8,
+}
+
+/// Returned by `get_reserve_status`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub struct ReserveStatus {
+    pub now: i64,
+    pub vested_amount: u64,
+    pub claimable_amount: u64,
+    /// `None` once the reserve is fully vested or revoked.
+    pub next_unlock_time: Option<i64>,
+    /// Upcoming unlocks with the cumulative amount vested at each.
+    pub remaining_schedule: Vec<Tranche>,
+    /// More unlocks follow the last one listed.
+    pub truncated: bool,
 }
 
 #[error_code]
@@ -363,6 +2068,104 @@ pub enum ErrorCode {
     InvalidTime,
     #[msg("Unauthorized User. Access Denied.")]
     AccessDenied,
+    #[msg("Arithmetic Overflow")]
+    Overflow,
+    #[msg("Invalid Vesting Account.")]
+    InvalidVestingAccount,
+    #[msg("Account Already Migrated.")]
+    AlreadyMigrated,
+    #[msg("Account Not Migrated. Please Migrate It First.")]
+    NotMigrated,
+    #[msg("Reserve Is Not Revocable.")]
+    NotRevocable,
+    #[msg("Reserve Terminated. Grant Was Revoked.")]
+    ReserveTerminated,
+    #[msg("Invalid Reserve Account.")]
+    InvalidReserveAccount,
+    #[msg("Invalid Vesting Schedule.")]
+    InvalidSchedule,
+    #[msg("Invalid Tranches. Unlocks Must Increase And End At The Total Amount.")]
+    InvalidTranches,
+    #[msg("Tranche Schedule Required For This Reserve.")]
+    MissingTranches,
+    #[msg("Tranches Locked. Claims Have Already Started.")]
+    TranchesLocked,
+    #[msg("Invalid Amount. Total Amount Must Be Greater Than Zero.")]
+    InvalidAmount,
+    #[msg("Invalid End Time. Must Be After Start Time.")]
+    InvalidEndTime,
+    #[msg("Invalid Cliff. Must Be Non-Negative And End By End Time.")]
+    InvalidCliff,
+    #[msg("Invalid Period Amount. Must Be Greater Than Zero.")]
+    InvalidPeriodAmount,
+    #[msg("Invalid Upfront Amount. TGE And Cliff Release Exceed Total Amount.")]
+    InvalidUpfrontAmount,
+    #[msg("Schedule Incomplete. Total Amount Not Vested By End Time.")]
+    ScheduleIncomplete,
+    #[msg("Invalid Tip. Exceeds The Maximum Crank Tip.")]
+    InvalidTip,
+    #[msg("Insufficient Tip Balance.")]
+    InsufficientTipBalance,
+    #[msg("Reserve Tokenized. Claim With The Position NFT.")]
+    ReserveTokenized,
+    #[msg("Reserve Not Tokenized.")]
+    ReserveNotTokenized,
+    #[msg("Invalid Position Account. Must Hold The Reserve's Position NFT.")]
+    InvalidPositionAccount,
+    #[msg("Invalid Expiry Time. Must Be In The Future.")]
+    InvalidExpiryTime,
+    #[msg("Invalid Merkle Proof.")]
+    InvalidProof,
+    #[msg("Distributor Expired. Claims Are Closed.")]
+    DistributorExpired,
+    #[msg("Distributor Not Expired Yet.")]
+    DistributorNotExpired,
+    #[msg("Distributor Already Clawed Back.")]
+    DistributorClawedBack,
+    #[msg("Distributor Exhausted. Grants Exceed Its Funding.")]
+    DistributorExhausted,
+}
+
+#[event]
+pub struct AdminProposed {
+    pub admin: Pubkey,
+    pub pending_admin: Option<Pubkey>,
+}
+
+#[event]
+pub struct AdminChanged {
+    pub previous_admin: Pubkey,
+    pub admin: Pubkey,
+}
+
+#[event]
+pub struct BeneficiaryProposed {
+    pub reserve: Pubkey,
+    pub beneficiary: Pubkey,
+    pub pending_beneficiary: Option<Pubkey>,
+    pub proposed_by: Pubkey,
+}
+
+#[event]
+pub struct BeneficiaryChanged {
+    pub reserve: Pubkey,
+    pub previous_beneficiary: Pubkey,
+    pub beneficiary: Pubkey,
+}
+
+#[event]
+pub struct ClaimDelegateSet {
+    pub reserve: Pubkey,
+    pub delegate: Option<Pubkey>,
+}
+
+#[event]
+pub struct ReserveRevoked {
+    pub reserve: Pubkey,
+    pub beneficiary: Pubkey,
+    pub settled_amount: u64,
+    pub clawback_amount: u64,
+    pub decimals: u8,
 }
 
 #[event]
@@ -372,6 +2175,48 @@ pub struct TokensLocked {
     pub unlock_amount_per_period: u64,
     pub vesting_end_time: i64,
     pub decimals: u8,
+    pub tge_unlock_amount: u64,
+    pub cliff_release_amount: u64,
+}
+
+#[event]
+pub struct DistributorCreated {
+    pub distributor: Pubkey,
+    pub vesting_account: Pubkey,
+    pub root: [u8; 20],
+    pub total_amount: u64,
+    pub expiry_time: i64,
+}
+
+#[event]
+pub struct DistributorGrantClaimed {
+    pub distributor: Pubkey,
+    pub reserve: Pubkey,
+    pub beneficiary: Pubkey,
+    pub total_amount: u64,
+}
+
+#[event]
+pub struct DistributorClawedBack {
+    pub distributor: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct ReserveTokenized {
+    pub reserve: Pubkey,
+    pub position_mint: Pubkey,
+    pub beneficiary: Pubkey,
+    pub non_transferable: bool,
+}
+
+#[event]
+pub struct ClaimCranked {
+    pub reserve: Pubkey,
+    pub beneficiary: Pubkey,
+    pub cranker: Pubkey,
+    pub amount: u64,
+    pub tip_lamports: u64,
 }
 
 #[event]
@@ -381,6 +2226,87 @@ pub struct TokensClaimed {
     pub decimals: u8,
 }
 
+/// Mint of the position NFT created by `tokenize_reserve`.
+pub fn position_mint_address(reserve: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"position", reserve.as_ref()], &crate::ID).0
+}
+
+/// Amount vested by `now` for a schedule starting at `vesting_start` (the cliff end).
+pub fn vested_amount(
+    schedule: ScheduleKind,
+    vesting_start: i64,
+    end_time: i64,
+    total_amount: u64,
+    period_amount: u64,
+    now: i64,
+) -> Result<u64> {
+    if now < vesting_start {
+        return Ok(0);
+    }
+    let elapsed = now - vesting_start;
+    let periods = match schedule {
+        ScheduleKind::Linear if now >= end_time => return Ok(total_amount),
+        ScheduleKind::Linear => {
+            let vested = (total_amount as u128) * (elapsed as u128)
+                / ((end_time - vesting_start) as u128);
+            return Ok(vested as u64);
+        }
+        ScheduleKind::Daily => elapsed / SECONDS_PER_DAY + 1,
+        ScheduleKind::Weekly => elapsed / SECONDS_PER_WEEK + 1,
+        ScheduleKind::Monthly => calendar_months(vesting_start, now)? + 1,
+        ScheduleKind::Quarterly => calendar_months(vesting_start, now)? / 3 + 1,
+        ScheduleKind::Custom => return err!(ErrorCode::MissingTranches),
+    };
+    Ok((periods as u64)
+        .saturating_mul(period_amount)
+        .min(total_amount))
+}
+
+/// Time at which the period following `now` starts releasing tokens.
+pub fn next_unlock_time(schedule: ScheduleKind, vesting_start: i64, now: i64) -> Result<i64> {
+    if now < vesting_start {
+        return Ok(vesting_start);
+    }
+    let elapsed = now - vesting_start;
+    match schedule {
+        ScheduleKind::Linear => Ok(now + 1),
+        ScheduleKind::Daily => Ok(vesting_start + (elapsed / SECONDS_PER_DAY + 1) * SECONDS_PER_DAY),
+        ScheduleKind::Weekly => {
+            Ok(vesting_start + (elapsed / SECONDS_PER_WEEK + 1) * SECONDS_PER_WEEK)
+        }
+        ScheduleKind::Monthly => {
+            add_months(vesting_start, calendar_months(vesting_start, now)? + 1)
+        }
+        ScheduleKind::Quarterly => {
+            add_months(vesting_start, (calendar_months(vesting_start, now)? / 3 + 1) * 3)
+        }
+        ScheduleKind::Custom => err!(ErrorCode::MissingTranches),
+    }
+}
+
+fn calendar_months(start: i64, now: i64) -> Result<i64> {
+    let start_dt = DateTime::from_timestamp(start, 0).ok_or(ErrorCode::InvalidTime)?;
+    let current_dt = DateTime::from_timestamp(now, 0).ok_or(ErrorCode::InvalidTime)?;
+    Ok(months_elapsed(start_dt, current_dt))
+}
+
+/// First time `months` calendar months have elapsed since `start`, as `months_elapsed` counts them.
+fn add_months(start: i64, months: i64) -> Result<i64> {
+    let start_dt = DateTime::from_timestamp(start, 0).ok_or(ErrorCode::InvalidTime)?;
+    let target = start_dt
+        .checked_add_months(chrono::Months::new(months as u32))
+        .ok_or(ErrorCode::InvalidTime)?;
+    if target.day() == start_dt.day() {
+        return Ok(target.timestamp());
+    }
+    // The month is too short for the start's day, so it only completes at the next month's start.
+    NaiveDate::from_ymd_opt(target.year(), target.month(), 1)
+        .and_then(|date| date.checked_add_months(chrono::Months::new(1)))
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|dt| dt.and_utc().timestamp())
+        .ok_or(ErrorCode::InvalidTime.into())
+}
+
 fn months_elapsed(start: DateTime<Utc>, end: DateTime<Utc>) -> i64 {
     if end < start {
         return 0;
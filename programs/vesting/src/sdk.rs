@@ -0,0 +1,107 @@
+//! Pure functions behind `get_reserve_status`, for clients that read reserves off-chain.
+//!
+//! Calendar months are counted by day of month and time of day, so an unlock on the 31st
+//! waits for the next month that has one. Reusing these functions keeps frontends in step
+//! with what `claim_tokens` will actually pay out.
+
+use anchor_lang::prelude::*;
+
+use crate::{ReserveAccount, ReserveStatus, ScheduleKind, Tranche, TrancheSchedule};
+
+/// Amount the beneficiary could claim at `now`.
+pub fn claimable_amount(
+    reserve: &ReserveAccount,
+    tranches: Option<&TrancheSchedule>,
+    now: i64,
+) -> Result<u64> {
+    if reserve.terminated {
+        return Ok(0);
+    }
+    Ok(reserve
+        .vested_amount(now, tranches)?
+        .saturating_sub(reserve.amount_withdrawn))
+}
+
+/// Time of the first unlock after `now`, or `None` once nothing is left to vest.
+pub fn next_unlock_time(
+    reserve: &ReserveAccount,
+    tranches: Option<&TrancheSchedule>,
+    now: i64,
+) -> Result<Option<i64>> {
+    if reserve.terminated || reserve.vested_amount(now, tranches)? >= reserve.total_amount {
+        return Ok(None);
+    }
+    if reserve.schedule == ScheduleKind::Custom {
+        let tranches = tranches.ok_or(crate::ErrorCode::MissingTranches)?;
+        return Ok(Some(tranches.next_unlock_time(now)));
+    }
+    let vesting_start = reserve.start_time + reserve.cliff_time;
+    if now < reserve.start_time && reserve.tge_unlock_amount > 0 {
+        return Ok(Some(reserve.start_time));
+    }
+    if now < vesting_start {
+        return Ok(Some(vesting_start));
+    }
+    crate::next_unlock_time(reserve.schedule, vesting_start, now).map(Some)
+}
+
+/// Up to `limit` unlocks after `after`, each with the total vested once it is reached.
+/// `Linear` reserves stream continuously after the cliff, so only their end time is listed.
+pub fn remaining_schedule(
+    reserve: &ReserveAccount,
+    tranches: Option<&TrancheSchedule>,
+    after: i64,
+    limit: usize,
+) -> Result<Vec<Tranche>> {
+    let vesting_start = reserve.start_time + reserve.cliff_time;
+    let mut schedule = Vec::new();
+    let mut time = after;
+    while schedule.len() < limit {
+        let unlock_time = match next_unlock_time(reserve, tranches, time)? {
+            None => break,
+            Some(_) if reserve.schedule == ScheduleKind::Linear && time >= vesting_start => {
+                reserve.end_time
+            }
+            Some(unlock_time) => unlock_time,
+        };
+        if unlock_time <= time {
+            break;
+        }
+        schedule.push(Tranche {
+            unlock_time,
+            cumulative_amount: reserve.vested_amount(unlock_time, tranches)?,
+        });
+        time = unlock_time;
+    }
+    Ok(schedule)
+}
+
+/// Everything `get_reserve_status` reports at `now`, listing at most `max_unlocks` unlocks
+/// after `after` (or after `now` if that is later).
+pub fn reserve_status(
+    reserve: &ReserveAccount,
+    tranches: Option<&TrancheSchedule>,
+    now: i64,
+    after: Option<i64>,
+    max_unlocks: usize,
+) -> Result<ReserveStatus> {
+    let vested_amount = if reserve.terminated {
+        reserve.amount_withdrawn
+    } else {
+        reserve.vested_amount(now, tranches)?
+    };
+    let after = after.map_or(now, |after| after.max(now));
+    let mut remaining_schedule =
+        remaining_schedule(reserve, tranches, after, max_unlocks.saturating_add(1))?;
+    let truncated = remaining_schedule.len() > max_unlocks;
+    remaining_schedule.truncate(max_unlocks);
+
+    Ok(ReserveStatus {
+        now,
+        vested_amount,
+        claimable_amount: claimable_amount(reserve, tranches, now)?,
+        next_unlock_time: next_unlock_time(reserve, tranches, now)?,
+        remaining_schedule,
+        truncated,
+    })
+}
@@ -0,0 +1,218 @@
+//! Shared harness for the in-process program tests under `tests/`.
+//!
+//! Programs are loaded from `target/deploy`, so run `anchor build` before
+//! `cargo test`. Signature verification is disabled, which lets transactions
+//! list the hardcoded program admin as a signer without its key.
+
+#![allow(clippy::result_large_err)]
+
+use std::path::PathBuf;
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::{error::InstructionError, Instruction};
+use anchor_lang::solana_program::program_option::COption;
+use anchor_lang::solana_program::program_pack::Pack;
+use anchor_spl::associated_token::get_associated_token_address_with_program_id;
+use anchor_spl::token_2022::spl_token_2022::{self, state::AccountState};
+use litesvm::types::TransactionResult;
+use litesvm::LiteSVM;
+use solana_account::Account as SolanaAccount;
+use solana_sha256_hasher::hash;
+use solana_transaction::Transaction;
+
+pub use litesvm;
+pub use solana_keypair::Keypair;
+pub use solana_signer::Signer as _;
+pub use solana_transaction_error::TransactionError;
+
+pub const ADMIN: Pubkey = pubkey!("3c1gFBMmZFrDTgUz2HH8yhhbfqibdwfK14QtHRiQLYE1");
+pub const LAMPORTS_PER_SOL: u64 = 1_000_000_000;
+pub const DECIMALS: u8 = 6;
+
+pub struct TestContext {
+    pub svm: LiteSVM,
+    pub payer: Keypair,
+    pub mint: Pubkey,
+}
+
+impl TestContext {
+    /// Loads `target/deploy/<program>.so` at `program_id` and funds the payer and admin.
+    pub fn new(program: &str, program_id: Pubkey) -> Self {
+        let mut svm = LiteSVM::new()
+            .with_sigverify(false)
+            .with_transaction_history(0);
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("../../target/deploy")
+            .join(format!("{program}.so"));
+        svm.add_program_from_file(program_id, &path)
+            .unwrap_or_else(|_| panic!("{} not found, run `anchor build` first", path.display()));
+
+        let payer = Keypair::new();
+        svm.airdrop(&payer.pubkey(), 100 * LAMPORTS_PER_SOL)
+            .unwrap();
+        svm.airdrop(&ADMIN, 100 * LAMPORTS_PER_SOL).unwrap();
+
+        let mut ctx = TestContext {
+            svm,
+            payer,
+            mint: Pubkey::new_unique(),
+        };
+        ctx.create_mint();
+        ctx
+    }
+
+    pub fn warp_to(&mut self, unix_timestamp: i64) {
+        let mut clock: Clock = self.svm.get_sysvar();
+        clock.unix_timestamp = unix_timestamp;
+        clock.slot += 1;
+        self.svm.set_sysvar(&clock);
+    }
+
+    /// Funds a fresh wallet with `sol` SOL.
+    pub fn wallet(&mut self, sol: u64) -> Keypair {
+        let wallet = Keypair::new();
+        self.svm
+            .airdrop(&wallet.pubkey(), sol * LAMPORTS_PER_SOL)
+            .unwrap();
+        wallet
+    }
+
+    /// Writes a rent-exempt account with `data` owned by `owner`.
+    pub fn set_account(&mut self, address: Pubkey, owner: Pubkey, data: Vec<u8>) {
+        let lamports = self.svm.minimum_balance_for_rent_exemption(data.len());
+        self.svm
+            .set_account(
+                address,
+                SolanaAccount {
+                    lamports,
+                    data,
+                    owner,
+                    executable: false,
+                    rent_epoch: 0,
+                },
+            )
+            .unwrap();
+    }
+
+    fn create_mint(&mut self) {
+        let mut data = vec![0; spl_token_2022::state::Mint::LEN];
+        spl_token_2022::state::Mint {
+            mint_authority: COption::Some(ADMIN),
+            supply: u64::MAX / 2,
+            decimals: DECIMALS,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        }
+        .pack_into_slice(&mut data);
+        self.set_account(self.mint, spl_token_2022::ID, data);
+    }
+
+    /// Creates `owner`'s Token-2022 associated token account holding `amount` base units.
+    pub fn create_token_account(&mut self, owner: &Pubkey, amount: u64) -> Pubkey {
+        let address = self.ata(owner);
+        let mut data = vec![0; spl_token_2022::state::Account::LEN];
+        spl_token_2022::state::Account {
+            mint: self.mint,
+            owner: *owner,
+            amount,
+            delegate: COption::None,
+            state: AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        }
+        .pack_into_slice(&mut data);
+        self.set_account(address, spl_token_2022::ID, data);
+        address
+    }
+
+    pub fn ata(&self, owner: &Pubkey) -> Pubkey {
+        get_associated_token_address_with_program_id(owner, &self.mint, &spl_token_2022::ID)
+    }
+
+    pub fn token_balance(&self, owner: &Pubkey) -> u64 {
+        let account = self.svm.get_account(&self.ata(owner)).unwrap();
+        spl_token_2022::state::Account::unpack(&account.data)
+            .unwrap()
+            .amount
+    }
+
+    pub fn lamports(&self, address: &Pubkey) -> u64 {
+        self.svm.get_balance(address).unwrap_or(0)
+    }
+
+    pub fn account_data(&self, address: &Pubkey) -> Vec<u8> {
+        self.svm.get_account(address).unwrap().data
+    }
+
+    pub fn account_exists(&self, address: &Pubkey) -> bool {
+        self.svm
+            .get_account(address)
+            .is_some_and(|account| account.lamports > 0)
+    }
+
+    /// Sends `ixs` paid for by the test payer. Only `signers` actually sign;
+    /// any other signer, such as the admin, is accepted unsigned.
+    pub fn send(&mut self, ixs: &[Instruction], signers: &[&Keypair]) -> TransactionResult {
+        let mut tx = Transaction::new_with_payer(ixs, Some(&self.payer.pubkey()));
+        let mut keypairs = vec![&self.payer];
+        keypairs.extend_from_slice(signers);
+        tx.partial_sign(&keypairs, self.svm.latest_blockhash());
+        let result = self.svm.send_transaction(tx);
+        self.svm.expire_blockhash();
+        result
+    }
+
+    /// Simulates `ixs` without committing them and decodes the return data of the last one,
+    /// as a client reading a view instruction would.
+    pub fn simulate<T: AnchorDeserialize>(
+        &self,
+        ixs: &[Instruction],
+    ) -> std::result::Result<T, TransactionError> {
+        let mut tx = Transaction::new_with_payer(ixs, Some(&self.payer.pubkey()));
+        tx.partial_sign(&[&self.payer], self.svm.latest_blockhash());
+        let simulated = self
+            .svm
+            .simulate_transaction(tx)
+            .map_err(|failed| failed.err)?;
+        Ok(T::try_from_slice(&simulated.meta.return_data.data).unwrap())
+    }
+}
+
+/// Anchor instruction data: the `global:<name>` discriminator followed by the borsh-encoded args.
+pub fn instruction_data(name: &str, args: impl AnchorSerialize) -> Vec<u8> {
+    let mut data = hash(format!("global:{name}").as_bytes()).to_bytes()[..8].to_vec();
+    args.serialize(&mut data).unwrap();
+    data
+}
+
+/// Anchor account discriminator for the account type `name`.
+pub fn account_discriminator(name: &str) -> [u8; 8] {
+    hash(format!("account:{name}").as_bytes()).to_bytes()[..8]
+        .try_into()
+        .unwrap()
+}
+
+pub fn custom_error(code: impl Into<u32>) -> TransactionError {
+    TransactionError::InstructionError(0, InstructionError::Custom(code.into()))
+}
+
+/// Asserts the transaction failed with the custom program error `code`.
+pub fn assert_error(result: TransactionResult, code: impl Into<u32>) {
+    assert_error_at(result, 0, code);
+}
+
+/// Asserts instruction `index` failed with the custom program error `code`, for
+/// transactions that put another instruction, such as an ed25519 check, first.
+pub fn assert_error_at(result: TransactionResult, index: u8, code: impl Into<u32>) {
+    let code = code.into();
+    match result {
+        Err(failed) => assert_eq!(
+            failed.err,
+            TransactionError::InstructionError(index, InstructionError::Custom(code)),
+            "logs: {:#?}",
+            failed.meta.logs
+        ),
+        Ok(meta) => panic!("expected error {code}, got success: {:#?}", meta.logs),
+    }
+}
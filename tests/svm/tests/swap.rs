@@ -0,0 +1,2224 @@
+//! In-process tests for the swap program, with a mocked Pyth `PriceUpdateV2`
+//! account and a warped clock.
+//!
+//! The swap crate can't be linked here (see `Cargo.toml`), so instructions are
+//! encoded by hand and accounts are read through the minimal mirrors below.
+
+#![allow(clippy::result_large_err)]
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::sysvar;
+use anchor_lang::system_program;
+use anchor_spl::associated_token;
+use anchor_spl::token_2022::spl_token_2022;
+use svm_tests::litesvm::types::TransactionResult;
+use svm_tests::*;
+
+const SWAP_ID: Pubkey = pubkey!("SAUR5HUSEyf4LPRg3biDZ3Zxq2dbJvArnh7dwTmvfvj");
+const PYTH_RECEIVER_ID: Pubkey = pubkey!("rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ");
+/// SOL/USD, as `swap::FEED_ID`.
+const FEED_ID: &str = "ef0d8b6fda2ceba41da15d4095d1da392a0d2f8ed0c6c7bc0f4cfac8c280b56d";
+const MAXIMUM_AGE: i64 = 600;
+const PYTH_PUSH_ORACLE_ID: Pubkey = pubkey!("pythWSnswVUd12oZpeFP8e9CVaEqJg25g1Vtc2biRsT");
+const ED25519_ID: Pubkey = pubkey!("Ed25519SigVerify111111111111111111111111111");
+/// Mock KYC issuer program owning the attestation accounts.
+const ISSUER_PROGRAM: Pubkey = Pubkey::new_from_array([7; 32]);
+/// The mock attestation is an 8-byte header, the recipient, then the expiry.
+const ATTESTATION_EXPIRY_OFFSET: u16 = 40;
+/// `swap::LEGACY_VAULT_LEN`: a vault written before the `version` field.
+const VAULT_VERSION: u8 = 1;
+const LEGACY_VAULT_LEN: usize = 8 + 8 + 32 + 32 + 8 + 8 + 1 + 32;
+
+const ONE_TOKEN: u64 = 1_000_000;
+/// 2025-01-01T00:00:00Z
+const START_TIME: i64 = 1_735_689_600;
+/// $0.01 per token, in USD with 6 decimals.
+const PRICE_PER_TOKEN: u64 = 10_000;
+/// SOL/USD at $150 with Pyth's usual exponent.
+const SOL_PRICE: i64 = 15_000_000_000;
+const SOL_EXPO: i32 = -8;
+
+/// `swap::CustomError` codes.
+mod error {
+    pub const INVALID_AUTH: u32 = 6000;
+    pub const INSUFFICIENT_TOKENS: u32 = 6001;
+    pub const TOKEN_LIMIT: u32 = 6004;
+    pub const INVALID_PERCENTAGE: u32 = 6005;
+    pub const INVALID_UNLOCK_TIME: u32 = 6006;
+    pub const LIQUIDITY_LOCKED: u32 = 6007;
+    pub const INVALID_AUCTION_CONFIG: u32 = 6008;
+    pub const AUCTION_NOT_ACTIVE: u32 = 6009;
+    pub const AUCTION_NOT_ENDED: u32 = 6010;
+    pub const AUCTION_NOT_SETTLED: u32 = 6011;
+    pub const AUCTION_SETTLED: u32 = 6012;
+    pub const VAULT_OUT_OF_SYNC: u32 = 6013;
+    pub const ALREADY_MIGRATED: u32 = 6015;
+    pub const BUYBACK_DISABLED: u32 = 6016;
+    pub const DAILY_CAP_EXCEEDED: u32 = 6017;
+    pub const INSUFFICIENT_RESERVE: u32 = 6018;
+    pub const TIMELOCK_ACTIVE: u32 = 6019;
+    pub const TIMELOCK_NOT_EXPIRED: u32 = 6020;
+    pub const TIMELOCK_DECREASE: u32 = 6021;
+    pub const GATE_REQUIRED: u32 = 6022;
+    pub const INVALID_GATE_CONFIG: u32 = 6023;
+    pub const INVALID_ATTESTATION: u32 = 6024;
+    pub const ATTESTATION_EXPIRED: u32 = 6025;
+    pub const INVALID_VOUCHER: u32 = 6026;
+    pub const COOLDOWN_ACTIVE: u32 = 6027;
+    pub const RATE_LIMITED: u32 = 6028;
+    pub const INVALID_RATE_LIMIT: u32 = 6029;
+    pub const INVALID_PRICE_ACCOUNT: u32 = 6030;
+    pub const INVALID_DISTRIBUTION: u32 = 6031;
+    pub const INVALID_RECIPIENT: u32 = 6032;
+    pub const AUCTION_ACTIVE: u32 = 6033;
+    pub const BUYBACK_ACTIVE: u32 = 6034;
+    pub const WALLET_CAP_EXCEEDED: u32 = 6035;
+    pub const INVALID_PRICE: u32 = 6036;
+    pub const NO_PRICE_UPDATE_QUEUED: u32 = 6037;
+    pub const ADMIN_PURCHASE: u32 = 6038;
+    /// Anchor's `ConstraintTokenOwner`.
+    pub const CONSTRAINT_TOKEN_OWNER: u32 = 2015;
+    /// `GetPriceError::PriceTooOld` from the Pyth receiver SDK.
+    pub const PRICE_TOO_OLD: u32 = 16000;
+    pub const MISMATCHED_FEED_ID: u32 = 16002;
+}
+
+/// `swap::GateKind`.
+#[derive(AnchorSerialize)]
+enum GateKind {
+    Attestation,
+    Voucher,
+}
+
+/// `swap::GateSeed`.
+#[derive(AnchorSerialize, Clone)]
+enum GateSeed {
+    Literal { bytes: Vec<u8> },
+    Recipient,
+    Issuer,
+}
+
+/// How a purchase proves its recipient passed the gate.
+enum GateProof {
+    None,
+    Attestation(Pubkey),
+    Voucher(Instruction),
+}
+
+/// Leading fields of `swap::Vault`.
+#[derive(AnchorDeserialize)]
+struct VaultState {
+    _index: u64,
+    _token_mint: Pubkey,
+    _vault_token_account: Pubkey,
+    price_per_token: u64,
+    total_tokens: u64,
+    _bump: u8,
+    _owner: Pubkey,
+    _version: u8,
+    withdrawal_delay: i64,
+    withdrawal_nonce: u64,
+}
+
+/// Leading fields of `swap::SaleStats`.
+#[derive(AnchorDeserialize)]
+struct SaleStatsState {
+    _vault: Pubkey,
+    lamports_raised: u64,
+    usd_raised: u64,
+    tokens_sold: u64,
+    unique_buyers: u64,
+}
+
+/// Leading fields of `swap::Auction`.
+#[derive(AnchorDeserialize)]
+struct AuctionState {
+    _vault: Pubkey,
+    _start_price: u64,
+    _floor_price: u64,
+    _price_decay: u64,
+    _decay_interval: i64,
+    _start_time: i64,
+    _end_time: i64,
+    _uniform_clearing: bool,
+    settled: bool,
+    clearing_price: u64,
+    last_price: u64,
+    _tokens_sold: u64,
+    lamports_escrowed: u64,
+}
+
+/// Leading fields of `swap::BuybackConfig`.
+#[derive(AnchorDeserialize)]
+struct BuybackState {
+    _vault: Pubkey,
+    _enabled: bool,
+    _buyback_price: u64,
+    _discount_bps: u16,
+    _daily_cap: u64,
+    _current_day: i64,
+    day_volume: u64,
+    reserve_lamports: u64,
+}
+
+/// Leading fields of `swap::PendingWithdrawal`.
+#[derive(AnchorDeserialize)]
+struct PendingWithdrawalState {
+    _vault: Pubkey,
+    nonce: u64,
+    amount: u64,
+    destination: Pubkey,
+    eta: i64,
+}
+
+/// Leading fields of `swap::LiquidityReserve`.
+#[derive(AnchorDeserialize)]
+struct LiquidityReserveState {
+    _vault: Pubkey,
+    _token_mint: Pubkey,
+    lamports: u64,
+    token_amount: u64,
+    unlock_time: i64,
+}
+
+struct SwapTest {
+    ctx: TestContext,
+    price_update: Pubkey,
+    vault: Pubkey,
+}
+
+impl SwapTest {
+    fn new() -> Self {
+        let mut ctx = TestContext::new("swap", SWAP_ID);
+        ctx.create_token_account(&ADMIN, 10_000_000 * ONE_TOKEN);
+        let mut test = SwapTest {
+            ctx,
+            price_update: Pubkey::new_unique(),
+            vault: Pubkey::default(),
+        };
+        test.ctx.warp_to(START_TIME);
+        test.set_price(SOL_PRICE, SOL_EXPO, START_TIME);
+        test.initialize_as(ADMIN, 0).unwrap();
+        test.vault = test.vault_pda(0);
+        test
+    }
+
+    fn fetch<T: AnchorDeserialize>(&self, address: &Pubkey) -> T {
+        let data = self.ctx.account_data(address);
+        T::deserialize(&mut &data[8..]).unwrap()
+    }
+
+    /// Writes a fully verified `PriceUpdateV2` into the price account.
+    fn set_price_with_feed(
+        &mut self,
+        feed_id: [u8; 32],
+        price: i64,
+        exponent: i32,
+        publish_time: i64,
+    ) {
+        let mut data = account_discriminator("PriceUpdateV2").to_vec();
+        data.extend_from_slice(Pubkey::new_unique().as_ref()); // write_authority
+        data.push(1); // VerificationLevel::Full
+        data.extend_from_slice(&feed_id);
+        data.extend_from_slice(&price.to_le_bytes());
+        data.extend_from_slice(&0u64.to_le_bytes()); // conf
+        data.extend_from_slice(&exponent.to_le_bytes());
+        data.extend_from_slice(&publish_time.to_le_bytes());
+        data.extend_from_slice(&(publish_time - 1).to_le_bytes()); // prev_publish_time
+        data.extend_from_slice(&price.to_le_bytes()); // ema_price
+        data.extend_from_slice(&0u64.to_le_bytes()); // ema_conf
+        data.extend_from_slice(&0u64.to_le_bytes()); // posted_slot
+        self.ctx
+            .set_account(self.price_update, PYTH_RECEIVER_ID, data);
+    }
+
+    fn set_price(&mut self, price: i64, exponent: i32, publish_time: i64) {
+        self.set_price_with_feed(feed_id(), price, exponent, publish_time);
+    }
+
+    /// The sponsored pyth-push-oracle SOL/USD feed account for `shard`.
+    fn push_feed_pda(&self, shard: u16) -> Pubkey {
+        Pubkey::find_program_address(&[&shard.to_le_bytes(), &feed_id()], &PYTH_PUSH_ORACLE_ID).0
+    }
+
+    fn set_price_feed_shard_as(
+        &mut self,
+        authority: Pubkey,
+        shard: Option<u16>,
+    ) -> TransactionResult {
+        let ix = Instruction::new_with_bytes(
+            SWAP_ID,
+            &instruction_data("set_price_feed_shard", shard),
+            vec![
+                AccountMeta::new_readonly(authority, true),
+                AccountMeta::new(self.vault, false),
+            ],
+        );
+        self.ctx.send(&[ix], &[])
+    }
+
+    fn vault_pda(&self, index: u64) -> Pubkey {
+        self.pda(&[b"vault", self.ctx.mint.as_ref(), &index.to_le_bytes()])
+    }
+
+    fn pda(&self, seeds: &[&[u8]]) -> Pubkey {
+        Pubkey::find_program_address(seeds, &SWAP_ID).0
+    }
+
+    fn stats_pda(&self) -> Pubkey {
+        self.pda(&[b"stats", self.vault.as_ref()])
+    }
+
+    fn buyer_record_pda(&self, recipient: &Pubkey) -> Pubkey {
+        self.pda(&[b"buyer", self.vault.as_ref(), recipient.as_ref()])
+    }
+
+    fn auction_pda(&self) -> Pubkey {
+        self.pda(&[b"auction", self.vault.as_ref()])
+    }
+
+    fn bid_pda(&self, buyer: &Pubkey) -> Pubkey {
+        self.pda(&[b"bid", self.auction_pda().as_ref(), buyer.as_ref()])
+    }
+
+    fn initialize_as(&mut self, authority: Pubkey, index: u64) -> TransactionResult {
+        let vault = self.vault_pda(index);
+        let ix = Instruction::new_with_bytes(
+            SWAP_ID,
+            &instruction_data("initialize", (index, PRICE_PER_TOKEN)),
+            vec![
+                AccountMeta::new(authority, true),
+                AccountMeta::new(vault, false),
+                AccountMeta::new_readonly(self.ctx.mint, false),
+                AccountMeta::new(self.ctx.ata(&vault), false),
+                AccountMeta::new(self.pda(&[b"stats", vault.as_ref()]), false),
+                AccountMeta::new_readonly(system_program::ID, false),
+                AccountMeta::new_readonly(spl_token_2022::ID, false),
+                AccountMeta::new_readonly(associated_token::ID, false),
+            ],
+        );
+        self.ctx.send(&[ix], &[])
+    }
+
+    fn update_price_as(&mut self, authority: Pubkey, new_price: u64) -> TransactionResult {
+        let ix = Instruction::new_with_bytes(
+            SWAP_ID,
+            &instruction_data("update_price", new_price),
+            vec![
+                AccountMeta::new(authority, true),
+                AccountMeta::new(self.vault, false),
+            ],
+        );
+        self.ctx.send(&[ix], &[])
+    }
+
+    fn apply_price_update(&mut self) -> TransactionResult {
+        let ix = Instruction::new_with_bytes(
+            SWAP_ID,
+            &instruction_data("apply_price_update", ()),
+            vec![
+                AccountMeta::new_readonly(ADMIN, true),
+                AccountMeta::new(self.vault, false),
+            ],
+        );
+        self.ctx.send(&[ix], &[])
+    }
+
+    fn deposit_as(&mut self, authority: Pubkey, amount: u64) -> TransactionResult {
+        let ix = Instruction::new_with_bytes(
+            SWAP_ID,
+            &instruction_data("deposit_tokens", amount),
+            vec![
+                AccountMeta::new(authority, true),
+                AccountMeta::new(self.vault, false),
+                AccountMeta::new_readonly(self.ctx.mint, false),
+                AccountMeta::new(self.ctx.ata(&ADMIN), false),
+                AccountMeta::new(self.ctx.ata(&self.vault), false),
+                AccountMeta::new_readonly(spl_token_2022::ID, false),
+            ],
+        );
+        self.ctx.send(&[ix], &[])
+    }
+
+    fn deposit(&mut self, amount: u64) {
+        self.deposit_as(ADMIN, amount).unwrap();
+    }
+
+    fn purchase(&mut self, buyer: &Keypair, amount: u64) -> TransactionResult {
+        self.purchase_for(buyer, buyer.pubkey(), amount)
+    }
+
+    /// `buyer` pays for `amount` tokens delivered to `recipient`.
+    fn purchase_for(
+        &mut self,
+        buyer: &Keypair,
+        recipient: Pubkey,
+        amount: u64,
+    ) -> TransactionResult {
+        self.purchase_gated(buyer, recipient, amount, GateProof::None)
+    }
+
+    fn purchase_gated(
+        &mut self,
+        buyer: &Keypair,
+        recipient: Pubkey,
+        amount: u64,
+        proof: GateProof,
+    ) -> TransactionResult {
+        let ix = self.purchase_instruction(buyer.pubkey(), recipient, amount, &proof);
+        self.send_gated(ix, proof, buyer)
+    }
+
+    fn purchase_instruction(
+        &self,
+        buyer: Pubkey,
+        recipient: Pubkey,
+        amount: u64,
+        proof: &GateProof,
+    ) -> Instruction {
+        let mut accounts = vec![
+            AccountMeta::new(buyer, true),
+            AccountMeta::new(ADMIN, false),
+            AccountMeta::new(self.vault, false),
+            AccountMeta::new(self.stats_pda(), false),
+            AccountMeta::new_readonly(recipient, false),
+            AccountMeta::new(self.buyer_record_pda(&recipient), false),
+            AccountMeta::new_readonly(self.ctx.mint, false),
+            AccountMeta::new(self.ctx.ata(&self.vault), false),
+            AccountMeta::new(self.ctx.ata(&recipient), false),
+            AccountMeta::new_readonly(self.vault, false),
+            AccountMeta::new_readonly(spl_token_2022::ID, false),
+            AccountMeta::new_readonly(associated_token::ID, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new_readonly(self.price_update, false),
+        ];
+        accounts.extend(self.gate_metas(proof));
+        Instruction::new_with_bytes(
+            SWAP_ID,
+            &instruction_data("purchase_tokens", amount),
+            accounts,
+        )
+    }
+
+    /// The optional gate, attestation and instructions sysvar accounts of a purchase.
+    /// Omitted accounts are passed as the program id.
+    fn gate_metas(&self, proof: &GateProof) -> Vec<AccountMeta> {
+        let omitted = AccountMeta::new_readonly(SWAP_ID, false);
+        let gate = AccountMeta::new_readonly(self.gate_pda(), false);
+        match proof {
+            GateProof::None => vec![omitted.clone(), omitted.clone(), omitted],
+            GateProof::Attestation(attestation) => vec![
+                gate,
+                AccountMeta::new_readonly(*attestation, false),
+                omitted,
+            ],
+            GateProof::Voucher(_) => vec![
+                gate,
+                omitted,
+                AccountMeta::new_readonly(sysvar::instructions::ID, false),
+            ],
+        }
+    }
+
+    /// Sends `ix`, preceded by the ed25519 instruction of a voucher proof.
+    fn send_gated(
+        &mut self,
+        ix: Instruction,
+        proof: GateProof,
+        buyer: &Keypair,
+    ) -> TransactionResult {
+        let mut ixs = Vec::new();
+        if let GateProof::Voucher(voucher) = proof {
+            ixs.push(voucher);
+        }
+        ixs.push(ix);
+        self.ctx.send(&ixs, &[buyer])
+    }
+
+    fn gate_pda(&self) -> Pubkey {
+        self.pda(&[b"gate", self.vault.as_ref()])
+    }
+
+    /// Gates the vault on `issuer`, with mock-issuer attestations seeded by
+    /// `["attestation", issuer, recipient]`.
+    fn configure_gate(&mut self, kind: GateKind, issuer: Pubkey) -> TransactionResult {
+        let seeds = vec![
+            GateSeed::Literal {
+                bytes: b"attestation".to_vec(),
+            },
+            GateSeed::Issuer,
+            GateSeed::Recipient,
+        ];
+        self.configure_gate_with_seeds(kind, issuer, seeds)
+    }
+
+    fn configure_gate_with_seeds(
+        &mut self,
+        kind: GateKind,
+        issuer: Pubkey,
+        seeds: Vec<GateSeed>,
+    ) -> TransactionResult {
+        let ix = Instruction::new_with_bytes(
+            SWAP_ID,
+            &instruction_data(
+                "configure_gate",
+                (
+                    kind,
+                    issuer,
+                    ISSUER_PROGRAM,
+                    seeds,
+                    Some(ATTESTATION_EXPIRY_OFFSET),
+                    true,
+                ),
+            ),
+            vec![
+                AccountMeta::new(ADMIN, true),
+                AccountMeta::new(self.vault, false),
+                AccountMeta::new(self.gate_pda(), false),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+        );
+        self.ctx.send(&[ix], &[])
+    }
+
+    /// Writes the mock issuer's attestation of `recipient`, as `program` owns it.
+    fn attest(
+        &mut self,
+        program: Pubkey,
+        issuer: &Pubkey,
+        recipient: &Pubkey,
+        expires_at: i64,
+    ) -> Pubkey {
+        let address = Pubkey::find_program_address(
+            &[b"attestation", issuer.as_ref(), recipient.as_ref()],
+            &ISSUER_PROGRAM,
+        )
+        .0;
+        let mut data = vec![0; 8];
+        data.extend_from_slice(recipient.as_ref());
+        data.extend_from_slice(&expires_at.to_le_bytes());
+        self.ctx.set_account(address, program, data);
+        address
+    }
+
+    /// Distributes `amounts[i]` to the token account `destinations[i]`.
+    fn distribute(&mut self, destinations: &[Pubkey], amounts: Vec<u64>) -> TransactionResult {
+        let mut accounts = vec![
+            AccountMeta::new(ADMIN, true),
+            AccountMeta::new(self.vault, false),
+            AccountMeta::new(self.ctx.ata(&self.vault), false),
+            AccountMeta::new_readonly(self.ctx.mint, false),
+            AccountMeta::new_readonly(self.vault, false),
+            AccountMeta::new_readonly(spl_token_2022::ID, false),
+        ];
+        accounts.extend(destinations.iter().map(|d| AccountMeta::new(*d, false)));
+        let ix = Instruction::new_with_bytes(
+            SWAP_ID,
+            &instruction_data("distribute_from_vault", amounts),
+            accounts,
+        );
+        self.ctx.send(&[ix], &[])
+    }
+
+    fn liquidity_pda(&self) -> Pubkey {
+        self.pda(&[b"liquidity", self.vault.as_ref()])
+    }
+
+    fn set_liquidity_bps_as(&mut self, authority: Pubkey, liquidity_bps: u16) -> TransactionResult {
+        let ix = Instruction::new_with_bytes(
+            SWAP_ID,
+            &instruction_data("set_liquidity_bps", liquidity_bps),
+            vec![
+                AccountMeta::new_readonly(authority, true),
+                AccountMeta::new(self.vault, false),
+            ],
+        );
+        self.ctx.send(&[ix], &[])
+    }
+
+    fn finalize_sale(&mut self, unlock_time: i64) -> TransactionResult {
+        let reserve = self.liquidity_pda();
+        let ix = Instruction::new_with_bytes(
+            SWAP_ID,
+            &instruction_data("finalize_sale", unlock_time),
+            vec![
+                AccountMeta::new(ADMIN, true),
+                AccountMeta::new(self.vault, false),
+                AccountMeta::new_readonly(self.stats_pda(), false),
+                AccountMeta::new_readonly(self.ctx.mint, false),
+                AccountMeta::new(self.ctx.ata(&self.vault), false),
+                AccountMeta::new_readonly(self.vault, false),
+                AccountMeta::new(reserve, false),
+                AccountMeta::new(self.ctx.ata(&reserve), false),
+                AccountMeta::new_readonly(self.price_update, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+                AccountMeta::new_readonly(spl_token_2022::ID, false),
+                AccountMeta::new_readonly(associated_token::ID, false),
+            ],
+        );
+        self.ctx.send(&[ix], &[])
+    }
+
+    fn withdraw_liquidity(&mut self) -> TransactionResult {
+        let reserve = self.liquidity_pda();
+        let ix = Instruction::new_with_bytes(
+            SWAP_ID,
+            &instruction_data("withdraw_liquidity", ()),
+            vec![
+                AccountMeta::new(ADMIN, true),
+                AccountMeta::new(reserve, false),
+                AccountMeta::new_readonly(self.ctx.mint, false),
+                AccountMeta::new(self.ctx.ata(&reserve), false),
+                AccountMeta::new(self.ctx.ata(&ADMIN), false),
+                AccountMeta::new_readonly(spl_token_2022::ID, false),
+            ],
+        );
+        self.ctx.send(&[ix], &[])
+    }
+
+    fn buyback_pda(&self) -> Pubkey {
+        self.pda(&[b"buyback", self.vault.as_ref()])
+    }
+
+    /// Sets the anti-bot limits: cooldown, per-slot and per-minute volume, and the
+    /// launch window end with its per-purchase cap.
+    fn configure_anti_bot(
+        &mut self,
+        purchase_cooldown: i64,
+        max_tokens_per_slot: u64,
+        max_tokens_per_minute: u64,
+        launch: (i64, u64),
+    ) -> TransactionResult {
+        let ix = Instruction::new_with_bytes(
+            SWAP_ID,
+            &instruction_data(
+                "configure_anti_bot",
+                (
+                    purchase_cooldown,
+                    max_tokens_per_slot,
+                    max_tokens_per_minute,
+                    launch.0,
+                    launch.1,
+                ),
+            ),
+            vec![
+                AccountMeta::new_readonly(ADMIN, true),
+                AccountMeta::new(self.vault, false),
+            ],
+        );
+        self.ctx.send(&[ix], &[])
+    }
+
+    fn set_wallet_cap(&mut self, max_tokens_per_wallet: u64) -> TransactionResult {
+        let ix = Instruction::new_with_bytes(
+            SWAP_ID,
+            &instruction_data("set_wallet_cap", max_tokens_per_wallet),
+            vec![
+                AccountMeta::new_readonly(ADMIN, true),
+                AccountMeta::new(self.vault, false),
+            ],
+        );
+        self.ctx.send(&[ix], &[])
+    }
+
+    fn set_withdrawal_delay(&mut self, delay: i64) -> TransactionResult {
+        let ix = Instruction::new_with_bytes(
+            SWAP_ID,
+            &instruction_data("set_withdrawal_delay", delay),
+            vec![
+                AccountMeta::new_readonly(ADMIN, true),
+                AccountMeta::new(self.vault, false),
+            ],
+        );
+        self.ctx.send(&[ix], &[])
+    }
+
+    fn withdrawal_pda(&self, nonce: u64) -> Pubkey {
+        self.pda(&[b"withdrawal", self.vault.as_ref(), &nonce.to_le_bytes()])
+    }
+
+    fn withdraw_tokens(&mut self, amount: u64) -> TransactionResult {
+        let ix = Instruction::new_with_bytes(
+            SWAP_ID,
+            &instruction_data("withdraw_tokens", amount),
+            vec![
+                AccountMeta::new(ADMIN, true),
+                AccountMeta::new(self.vault, false),
+                AccountMeta::new_readonly(self.ctx.mint, false),
+                AccountMeta::new(self.ctx.ata(&ADMIN), false),
+                AccountMeta::new(self.ctx.ata(&self.vault), false),
+                AccountMeta::new_readonly(self.vault, false),
+                AccountMeta::new_readonly(spl_token_2022::ID, false),
+            ],
+        );
+        self.ctx.send(&[ix], &[])
+    }
+
+    fn queue_withdrawal_as(
+        &mut self,
+        authority: Pubkey,
+        amount: u64,
+        destination: Pubkey,
+    ) -> TransactionResult {
+        let vault: VaultState = self.fetch(&self.vault);
+        let nonce = vault.withdrawal_nonce;
+        let ix = Instruction::new_with_bytes(
+            SWAP_ID,
+            &instruction_data("queue_withdrawal", (amount, destination)),
+            vec![
+                AccountMeta::new(authority, true),
+                AccountMeta::new(self.vault, false),
+                AccountMeta::new(self.withdrawal_pda(nonce), false),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+        );
+        self.ctx.send(&[ix], &[])
+    }
+
+    fn execute_withdrawal(&mut self, nonce: u64, destination: Pubkey) -> TransactionResult {
+        let ix = Instruction::new_with_bytes(
+            SWAP_ID,
+            &instruction_data("execute_withdrawal", ()),
+            vec![
+                AccountMeta::new(ADMIN, true),
+                AccountMeta::new(self.vault, false),
+                AccountMeta::new(self.withdrawal_pda(nonce), false),
+                AccountMeta::new_readonly(self.ctx.mint, false),
+                AccountMeta::new(self.ctx.ata(&self.vault), false),
+                AccountMeta::new(destination, false),
+                AccountMeta::new_readonly(self.vault, false),
+                AccountMeta::new_readonly(spl_token_2022::ID, false),
+            ],
+        );
+        self.ctx.send(&[ix], &[])
+    }
+
+    fn cancel_withdrawal(&mut self, nonce: u64) -> TransactionResult {
+        let ix = Instruction::new_with_bytes(
+            SWAP_ID,
+            &instruction_data("cancel_withdrawal", ()),
+            vec![
+                AccountMeta::new(ADMIN, true),
+                AccountMeta::new(self.withdrawal_pda(nonce), false),
+            ],
+        );
+        self.ctx.send(&[ix], &[])
+    }
+
+    fn close_vault_as(&mut self, owner: Pubkey, owner_token_account: Pubkey) -> TransactionResult {
+        let ix = Instruction::new_with_bytes(
+            SWAP_ID,
+            &instruction_data("close_vault", ()),
+            vec![
+                AccountMeta::new(self.vault, false),
+                AccountMeta::new(owner, true),
+                AccountMeta::new(self.ctx.ata(&self.vault), false),
+                AccountMeta::new(owner_token_account, false),
+                AccountMeta::new_readonly(self.ctx.mint, false),
+                AccountMeta::new_readonly(self.vault, false),
+                AccountMeta::new_readonly(spl_token_2022::ID, false),
+            ],
+        );
+        self.ctx.send(&[ix], &[])
+    }
+
+    /// Configures buyback at a fixed `buyback_price`, or `discount_bps` off the sale price when zero.
+    fn configure_buyback(
+        &mut self,
+        buyback_price: u64,
+        discount_bps: u16,
+        daily_cap: u64,
+        enabled: bool,
+    ) -> TransactionResult {
+        let ix = Instruction::new_with_bytes(
+            SWAP_ID,
+            &instruction_data(
+                "configure_buyback",
+                (buyback_price, discount_bps, daily_cap, enabled),
+            ),
+            vec![
+                AccountMeta::new(ADMIN, true),
+                AccountMeta::new_readonly(self.vault, false),
+                AccountMeta::new(self.buyback_pda(), false),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+        );
+        self.ctx.send(&[ix], &[])
+    }
+
+    fn buyback_reserve_as(
+        &mut self,
+        name: &str,
+        authority: Pubkey,
+        lamports: u64,
+    ) -> TransactionResult {
+        let ix = Instruction::new_with_bytes(
+            SWAP_ID,
+            &instruction_data(name, lamports),
+            vec![
+                AccountMeta::new(authority, true),
+                AccountMeta::new_readonly(self.vault, false),
+                AccountMeta::new(self.buyback_pda(), false),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+        );
+        self.ctx.send(&[ix], &[])
+    }
+
+    fn fund_buyback(&mut self, lamports: u64) -> TransactionResult {
+        self.buyback_reserve_as("fund_buyback", ADMIN, lamports)
+    }
+
+    fn withdraw_buyback_as(&mut self, authority: Pubkey, lamports: u64) -> TransactionResult {
+        self.buyback_reserve_as("withdraw_buyback", authority, lamports)
+    }
+
+    fn sell(&mut self, seller: &Keypair, amount: u64) -> TransactionResult {
+        let ix = Instruction::new_with_bytes(
+            SWAP_ID,
+            &instruction_data("sell_tokens", amount),
+            vec![
+                AccountMeta::new(seller.pubkey(), true),
+                AccountMeta::new(self.vault, false),
+                AccountMeta::new(self.buyback_pda(), false),
+                AccountMeta::new_readonly(self.ctx.mint, false),
+                AccountMeta::new(self.ctx.ata(&self.vault), false),
+                AccountMeta::new(self.ctx.ata(&seller.pubkey()), false),
+                AccountMeta::new_readonly(self.price_update, false),
+                AccountMeta::new_readonly(spl_token_2022::ID, false),
+            ],
+        );
+        self.ctx.send(&[ix], &[seller])
+    }
+
+    /// A wallet holding `amount` tokens to sell back.
+    fn seller(&mut self, amount: u64) -> Keypair {
+        let seller = self.ctx.wallet(1);
+        self.ctx.create_token_account(&seller.pubkey(), amount);
+        seller
+    }
+
+    fn sync_vault_as(&mut self, authority: Pubkey) -> TransactionResult {
+        let ix = Instruction::new_with_bytes(
+            SWAP_ID,
+            &instruction_data("sync_vault", ()),
+            vec![
+                AccountMeta::new_readonly(authority, true),
+                AccountMeta::new(self.vault, false),
+                AccountMeta::new_readonly(self.ctx.ata(&self.vault), false),
+            ],
+        );
+        self.ctx.send(&[ix], &[])
+    }
+
+    fn migrate_vault_as(&mut self, authority: Pubkey, vault: Pubkey) -> TransactionResult {
+        let ix = Instruction::new_with_bytes(
+            SWAP_ID,
+            &instruction_data("migrate_vault", ()),
+            vec![
+                AccountMeta::new(authority, true),
+                AccountMeta::new(vault, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+        );
+        self.ctx.send(&[ix], &[])
+    }
+
+    fn start_auction(
+        &mut self,
+        start_price: u64,
+        floor_price: u64,
+        price_decay: u64,
+        decay_interval: i64,
+        window: (i64, i64),
+    ) -> TransactionResult {
+        self.start_auction_with_clearing(
+            start_price,
+            floor_price,
+            price_decay,
+            decay_interval,
+            window,
+            true,
+        )
+    }
+
+    fn start_auction_with_clearing(
+        &mut self,
+        start_price: u64,
+        floor_price: u64,
+        price_decay: u64,
+        decay_interval: i64,
+        window: (i64, i64),
+        uniform_clearing: bool,
+    ) -> TransactionResult {
+        let ix = Instruction::new_with_bytes(
+            SWAP_ID,
+            &instruction_data(
+                "start_auction",
+                (
+                    start_price,
+                    floor_price,
+                    price_decay,
+                    decay_interval,
+                    window.0,
+                    window.1,
+                    uniform_clearing,
+                ),
+            ),
+            vec![
+                AccountMeta::new(ADMIN, true),
+                AccountMeta::new(self.vault, false),
+                AccountMeta::new(self.auction_pda(), false),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+        );
+        self.ctx.send(&[ix], &[])
+    }
+
+    fn purchase_auction(&mut self, buyer: &Keypair, amount: u64) -> TransactionResult {
+        self.purchase_auction_gated(buyer, amount, GateProof::None)
+    }
+
+    fn purchase_auction_gated(
+        &mut self,
+        buyer: &Keypair,
+        amount: u64,
+        proof: GateProof,
+    ) -> TransactionResult {
+        let mut accounts = vec![
+            AccountMeta::new(buyer.pubkey(), true),
+            AccountMeta::new(ADMIN, false),
+            AccountMeta::new(self.vault, false),
+            AccountMeta::new(self.auction_pda(), false),
+            AccountMeta::new(self.bid_pda(&buyer.pubkey()), false),
+            AccountMeta::new(self.stats_pda(), false),
+            AccountMeta::new(self.buyer_record_pda(&buyer.pubkey()), false),
+            AccountMeta::new_readonly(self.ctx.mint, false),
+            AccountMeta::new(self.ctx.ata(&self.vault), false),
+            AccountMeta::new(self.ctx.ata(&buyer.pubkey()), false),
+            AccountMeta::new_readonly(self.vault, false),
+            AccountMeta::new_readonly(self.price_update, false),
+            AccountMeta::new_readonly(spl_token_2022::ID, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ];
+        accounts.extend(self.gate_metas(&proof));
+        let ix = Instruction::new_with_bytes(
+            SWAP_ID,
+            &instruction_data("purchase_auction_tokens", amount),
+            accounts,
+        );
+        self.send_gated(ix, proof, buyer)
+    }
+
+    fn settle_auction(&mut self) -> TransactionResult {
+        let ix = Instruction::new_with_bytes(
+            SWAP_ID,
+            &instruction_data("settle_auction", ()),
+            vec![
+                AccountMeta::new(self.vault, false),
+                AccountMeta::new(self.auction_pda(), false),
+                AccountMeta::new(self.stats_pda(), false),
+            ],
+        );
+        self.ctx.send(&[ix], &[])
+    }
+
+    fn claim_auction_refund(&mut self, buyer: &Keypair) -> TransactionResult {
+        let ix = Instruction::new_with_bytes(
+            SWAP_ID,
+            &instruction_data("claim_auction_refund", ()),
+            vec![
+                AccountMeta::new(buyer.pubkey(), true),
+                AccountMeta::new(self.auction_pda(), false),
+                AccountMeta::new(self.bid_pda(&buyer.pubkey()), false),
+            ],
+        );
+        self.ctx.send(&[ix], &[buyer])
+    }
+
+    fn withdraw_auction_proceeds(&mut self) -> TransactionResult {
+        let ix = Instruction::new_with_bytes(
+            SWAP_ID,
+            &instruction_data("withdraw_auction_proceeds", ()),
+            vec![
+                AccountMeta::new(ADMIN, true),
+                AccountMeta::new(self.auction_pda(), false),
+            ],
+        );
+        self.ctx.send(&[ix], &[])
+    }
+}
+
+/// `FEED_ID` as bytes.
+fn feed_id() -> [u8; 32] {
+    let mut feed_id = [0u8; 32];
+    for (i, byte) in feed_id.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&FEED_ID[2 * i..2 * i + 2], 16).unwrap();
+    }
+    feed_id
+}
+
+/// Lamports a buyer pays for `amount` base units at `price_per_token` and SOL/USD
+/// `price * 10^expo`, rounded up.
+fn expected_lamports(amount: u64, price_per_token: u64, price: i64, expo: i32) -> u64 {
+    let numerator =
+        amount as u128 * price_per_token as u128 * 10u128.pow(9) * 10u128.pow((-expo) as u32);
+    let denominator = price as u128 * 10u128.pow(6) * ONE_TOKEN as u128;
+    numerator.div_ceil(denominator) as u64
+}
+
+/// An ed25519 instruction carrying `issuer`'s voucher for `recipient` on `vault`,
+/// with all offsets pointing into the instruction itself.
+fn voucher(issuer: &Keypair, vault: &Pubkey, recipient: &Pubkey, expires_at: i64) -> Instruction {
+    let mut message = vault.to_bytes().to_vec();
+    message.extend_from_slice(recipient.as_ref());
+    message.extend_from_slice(&expires_at.to_le_bytes());
+    let signature = issuer.sign_message(&message);
+
+    let (pubkey_offset, signature_offset, message_offset) = (16u16, 48u16, 112u16);
+    let mut data = vec![1, 0];
+    for value in [
+        signature_offset,
+        u16::MAX,
+        pubkey_offset,
+        u16::MAX,
+        message_offset,
+        message.len() as u16,
+        u16::MAX,
+    ] {
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+    data.extend_from_slice(issuer.pubkey().as_ref());
+    data.extend_from_slice(signature.as_ref());
+    data.extend_from_slice(&message);
+    Instruction::new_with_bytes(ED25519_ID, &data, vec![])
+}
+
+#[test]
+fn initialize_rejects_non_admin() {
+    let mut test = SwapTest::new();
+    let intruder = test.ctx.wallet(1);
+
+    assert_error(
+        test.initialize_as(intruder.pubkey(), 1),
+        error::INVALID_AUTH,
+    );
+}
+
+#[test]
+fn admin_instructions_reject_non_admin() {
+    let mut test = SwapTest::new();
+    let intruder = test.ctx.wallet(1);
+
+    assert_error(
+        test.update_price_as(intruder.pubkey(), 1),
+        error::INVALID_AUTH,
+    );
+    assert_error(
+        test.deposit_as(intruder.pubkey(), ONE_TOKEN),
+        error::INVALID_AUTH,
+    );
+}
+
+#[test]
+fn purchase_charges_the_oracle_price() {
+    let mut test = SwapTest::new();
+    test.deposit(10_000 * ONE_TOKEN);
+    let buyer = test.ctx.wallet(1_000);
+    let admin_before = test.ctx.lamports(&ADMIN);
+
+    test.purchase(&buyer, 1_000 * ONE_TOKEN).unwrap();
+
+    // $10 at $150/SOL is 0.0666... SOL, rounded up to the lamport.
+    let paid = expected_lamports(1_000 * ONE_TOKEN, PRICE_PER_TOKEN, SOL_PRICE, SOL_EXPO);
+    assert_eq!(paid, 66_666_667);
+    assert_eq!(test.ctx.lamports(&ADMIN) - admin_before, paid);
+    assert_eq!(test.ctx.token_balance(&buyer.pubkey()), 1_000 * ONE_TOKEN);
+
+    let vault: VaultState = test.fetch(&test.vault);
+    assert_eq!(vault.total_tokens, 9_000 * ONE_TOKEN);
+    let stats: SaleStatsState = test.fetch(&test.stats_pda());
+    assert_eq!(stats.tokens_sold, 1_000 * ONE_TOKEN);
+    assert_eq!(stats.lamports_raised, paid);
+    assert_eq!(stats.usd_raised, 10_000_000);
+    assert_eq!(stats.unique_buyers, 1);
+}
+
+#[test]
+fn purchase_price_does_not_depend_on_the_oracle_exponent() {
+    let mut test = SwapTest::new();
+    test.deposit(10_000 * ONE_TOKEN);
+    let buyer = test.ctx.wallet(1_000);
+
+    // $150 expressed with exponent -5 instead of -8.
+    test.set_price(15_000_000, -5, START_TIME);
+    let admin_before = test.ctx.lamports(&ADMIN);
+    test.purchase(&buyer, 1_000 * ONE_TOKEN).unwrap();
+
+    assert_eq!(test.ctx.lamports(&ADMIN) - admin_before, 66_666_667);
+}
+
+#[test]
+fn purchase_enforces_the_per_transaction_token_limit() {
+    let mut test = SwapTest::new();
+    test.deposit(3_000_000 * ONE_TOKEN);
+    let buyer = test.ctx.wallet(1_000);
+
+    assert_error(
+        test.purchase(&buyer, 1_000_001 * ONE_TOKEN),
+        error::TOKEN_LIMIT,
+    );
+    test.purchase(&buyer, 1_000_000 * ONE_TOKEN).unwrap();
+    assert_eq!(
+        test.ctx.token_balance(&buyer.pubkey()),
+        1_000_000 * ONE_TOKEN
+    );
+}
+
+#[test]
+fn purchase_rejects_amounts_above_the_vault_inventory() {
+    let mut test = SwapTest::new();
+    test.deposit(100 * ONE_TOKEN);
+    let buyer = test.ctx.wallet(1_000);
+
+    assert_error(
+        test.purchase(&buyer, 101 * ONE_TOKEN),
+        error::INSUFFICIENT_TOKENS,
+    );
+}
+
+#[test]
+fn purchase_accepts_prices_up_to_the_maximum_age() {
+    let mut test = SwapTest::new();
+    test.deposit(100 * ONE_TOKEN);
+    let buyer = test.ctx.wallet(1_000);
+
+    test.ctx.warp_to(START_TIME + MAXIMUM_AGE + 1);
+    assert_error(test.purchase(&buyer, ONE_TOKEN), error::PRICE_TOO_OLD);
+
+    test.ctx.warp_to(START_TIME + MAXIMUM_AGE);
+    test.purchase(&buyer, ONE_TOKEN).unwrap();
+}
+
+#[test]
+fn purchase_rejects_other_price_feeds() {
+    let mut test = SwapTest::new();
+    test.deposit(100 * ONE_TOKEN);
+    let buyer = test.ctx.wallet(1_000);
+
+    test.set_price_with_feed([7; 32], SOL_PRICE, SOL_EXPO, START_TIME);
+    assert_error(test.purchase(&buyer, ONE_TOKEN), error::MISMATCHED_FEED_ID);
+}
+
+#[test]
+fn price_feed_shard_pins_the_sponsored_feed_account() {
+    let mut test = SwapTest::new();
+    test.deposit(1_000 * ONE_TOKEN);
+    let buyer = test.ctx.wallet(1_000);
+    let unpinned = test.price_update;
+
+    assert_error(
+        test.set_price_feed_shard_as(buyer.pubkey(), Some(0)),
+        error::INVALID_AUTH,
+    );
+    test.set_price_feed_shard_as(ADMIN, Some(0)).unwrap();
+    // A valid update for the right feed, but not at the sponsored address.
+    assert_error(
+        test.purchase(&buyer, ONE_TOKEN),
+        error::INVALID_PRICE_ACCOUNT,
+    );
+
+    test.price_update = test.push_feed_pda(0);
+    test.set_price(SOL_PRICE, SOL_EXPO, START_TIME);
+    test.purchase(&buyer, ONE_TOKEN).unwrap();
+
+    // Another shard's account is rejected once the vault moves shards.
+    test.set_price_feed_shard_as(ADMIN, Some(1)).unwrap();
+    assert_error(
+        test.purchase(&buyer, ONE_TOKEN),
+        error::INVALID_PRICE_ACCOUNT,
+    );
+    test.price_update = test.push_feed_pda(1);
+    test.set_price(SOL_PRICE, SOL_EXPO, START_TIME);
+    test.purchase(&buyer, ONE_TOKEN).unwrap();
+
+    // `None` accepts any update for the feed again.
+    test.set_price_feed_shard_as(ADMIN, None).unwrap();
+    test.price_update = unpinned;
+    test.purchase(&buyer, ONE_TOKEN).unwrap();
+}
+
+#[test]
+fn sold_out_phase_reopens_at_the_next_price() {
+    let mut test = SwapTest::new();
+    test.deposit(500 * ONE_TOKEN);
+    let buyer = test.ctx.wallet(1_000);
+
+    test.purchase(&buyer, 500 * ONE_TOKEN).unwrap();
+    assert_error(test.purchase(&buyer, ONE_TOKEN), error::INSUFFICIENT_TOKENS);
+
+    // Next phase: higher price and fresh inventory.
+    test.update_price_as(ADMIN, 2 * PRICE_PER_TOKEN).unwrap();
+    test.deposit(500 * ONE_TOKEN);
+    let vault: VaultState = test.fetch(&test.vault);
+    assert_eq!(vault.price_per_token, 2 * PRICE_PER_TOKEN);
+
+    let admin_before = test.ctx.lamports(&ADMIN);
+    test.purchase(&buyer, 100 * ONE_TOKEN).unwrap();
+    assert_eq!(
+        test.ctx.lamports(&ADMIN) - admin_before,
+        expected_lamports(100 * ONE_TOKEN, 2 * PRICE_PER_TOKEN, SOL_PRICE, SOL_EXPO)
+    );
+    let stats: SaleStatsState = test.fetch(&test.stats_pda());
+    assert_eq!(stats.tokens_sold, 600 * ONE_TOKEN);
+    assert_eq!(stats.unique_buyers, 1);
+}
+
+#[test]
+fn cooldown_is_keyed_on_the_recipient() {
+    let mut test = SwapTest::new();
+    test.deposit(10_000 * ONE_TOKEN);
+    test.configure_anti_bot(60, 0, 0, (0, 0)).unwrap();
+    let recipient = Keypair::new().pubkey();
+    let first = test.ctx.wallet(1_000);
+    let second = test.ctx.wallet(1_000);
+
+    test.purchase_for(&first, recipient, ONE_TOKEN).unwrap();
+    // Another payer can't skip the recipient's cooldown, but buys for itself.
+    assert_error(
+        test.purchase_for(&second, recipient, ONE_TOKEN),
+        error::COOLDOWN_ACTIVE,
+    );
+    test.purchase(&second, ONE_TOKEN).unwrap();
+
+    test.ctx.warp_to(START_TIME + 59);
+    assert_error(
+        test.purchase_for(&first, recipient, ONE_TOKEN),
+        error::COOLDOWN_ACTIVE,
+    );
+    test.ctx.warp_to(START_TIME + 60);
+    test.purchase_for(&second, recipient, ONE_TOKEN).unwrap();
+}
+
+#[test]
+fn cooldown_carries_over_to_auction_purchases() {
+    let mut test = SwapTest::new();
+    test.deposit(10_000 * ONE_TOKEN);
+    test.configure_anti_bot(60, 0, 0, (0, 0)).unwrap();
+    let buyer = test.ctx.wallet(1_000);
+    test.purchase(&buyer, ONE_TOKEN).unwrap();
+
+    // The auction records purchases under the same receiving wallet.
+    test.start_auction(20_000, 10_000, 1, 60, (START_TIME, START_TIME + 3_600))
+        .unwrap();
+    test.ctx.warp_to(START_TIME + 30);
+    assert_error(
+        test.purchase_auction(&buyer, ONE_TOKEN),
+        error::COOLDOWN_ACTIVE,
+    );
+    test.ctx.warp_to(START_TIME + 60);
+    test.purchase_auction(&buyer, ONE_TOKEN).unwrap();
+}
+
+#[test]
+fn per_slot_volume_is_capped_across_buyers() {
+    let mut test = SwapTest::new();
+    test.deposit(10_000 * ONE_TOKEN);
+    test.configure_anti_bot(0, 1_000 * ONE_TOKEN, 0, (0, 0))
+        .unwrap();
+    let first = test.ctx.wallet(1_000);
+    let second = test.ctx.wallet(1_000);
+
+    test.purchase(&first, 600 * ONE_TOKEN).unwrap();
+    assert_error(test.purchase(&second, 500 * ONE_TOKEN), error::RATE_LIMITED);
+    test.purchase(&second, 400 * ONE_TOKEN).unwrap();
+
+    // The next slot starts from zero.
+    test.ctx.warp_to(START_TIME + 1);
+    test.purchase(&second, 1_000 * ONE_TOKEN).unwrap();
+}
+
+#[test]
+fn per_minute_volume_is_capped_across_slots() {
+    let mut test = SwapTest::new();
+    test.deposit(10_000 * ONE_TOKEN);
+    test.configure_anti_bot(0, 0, 1_000 * ONE_TOKEN, (0, 0))
+        .unwrap();
+    let first = test.ctx.wallet(1_000);
+    let second = test.ctx.wallet(1_000);
+
+    test.purchase(&first, 600 * ONE_TOKEN).unwrap();
+    test.ctx.warp_to(START_TIME + 30);
+    assert_error(test.purchase(&second, 500 * ONE_TOKEN), error::RATE_LIMITED);
+    test.purchase(&second, 400 * ONE_TOKEN).unwrap();
+
+    test.ctx.warp_to(START_TIME + 60);
+    test.purchase(&second, 1_000 * ONE_TOKEN).unwrap();
+}
+
+#[test]
+fn launch_window_caps_each_purchase() {
+    let mut test = SwapTest::new();
+    test.deposit(10_000 * ONE_TOKEN);
+    let launch_end = START_TIME + 300;
+    test.configure_anti_bot(0, 0, 0, (launch_end, 100 * ONE_TOKEN))
+        .unwrap();
+    let buyer = test.ctx.wallet(1_000);
+
+    assert_error(
+        test.purchase(&buyer, 100 * ONE_TOKEN + 1),
+        error::RATE_LIMITED,
+    );
+    test.purchase(&buyer, 100 * ONE_TOKEN).unwrap();
+
+    test.ctx.warp_to(launch_end);
+    test.purchase(&buyer, 1_000 * ONE_TOKEN).unwrap();
+}
+
+#[test]
+fn anti_bot_rejects_a_negative_cooldown() {
+    let mut test = SwapTest::new();
+    assert_error(
+        test.configure_anti_bot(-1, 0, 0, (0, 0)),
+        error::INVALID_RATE_LIMIT,
+    );
+}
+
+#[test]
+fn wallet_cap_holds_across_payers() {
+    let mut test = SwapTest::new();
+    test.deposit(10_000 * ONE_TOKEN);
+    test.set_wallet_cap(1_000 * ONE_TOKEN).unwrap();
+    let recipient = Keypair::new().pubkey();
+    let first = test.ctx.wallet(1_000);
+    let second = test.ctx.wallet(1_000);
+
+    test.purchase_for(&first, recipient, 600 * ONE_TOKEN)
+        .unwrap();
+    // The cap follows the recipient, not the paying wallet.
+    assert_error(
+        test.purchase_for(&second, recipient, 401 * ONE_TOKEN),
+        error::WALLET_CAP_EXCEEDED,
+    );
+    test.purchase_for(&second, recipient, 400 * ONE_TOKEN)
+        .unwrap();
+    assert_error(
+        test.purchase_for(&first, recipient, 1),
+        error::WALLET_CAP_EXCEEDED,
+    );
+    assert_eq!(test.ctx.token_balance(&recipient), 1_000 * ONE_TOKEN);
+
+    // Payers still buy for themselves up to their own cap.
+    test.purchase(&second, 1_000 * ONE_TOKEN).unwrap();
+
+    // Zero lifts the cap.
+    test.set_wallet_cap(0).unwrap();
+    test.purchase_for(&first, recipient, ONE_TOKEN).unwrap();
+}
+
+#[test]
+fn purchase_prices_exact_base_units_rounding_up() {
+    let mut test = SwapTest::new();
+    test.deposit(10_000 * ONE_TOKEN);
+    let buyer = test.ctx.wallet(1_000);
+
+    // A fraction of a token is no longer free: 1 base unit costs 0.0667 lamports, rounded up.
+    let admin_before = test.ctx.lamports(&ADMIN);
+    test.purchase(&buyer, 1).unwrap();
+    assert_eq!(test.ctx.lamports(&ADMIN) - admin_before, 1);
+
+    // 1.5 tokens cost $0.015, not the $0.01 of one whole token.
+    let admin_before = test.ctx.lamports(&ADMIN);
+    test.purchase(&buyer, 1_500_000).unwrap();
+    assert_eq!(test.ctx.lamports(&ADMIN) - admin_before, 100_000);
+    assert_eq!(test.ctx.token_balance(&buyer.pubkey()), 1_500_001);
+}
+
+#[test]
+fn sell_tokens_pays_the_fixed_buyback_price() {
+    let mut test = SwapTest::new();
+    test.configure_buyback(8_000, 0, 10_000 * ONE_TOKEN, true)
+        .unwrap();
+    test.fund_buyback(LAMPORTS_PER_SOL).unwrap();
+    let seller = test.seller(1_000 * ONE_TOKEN);
+
+    let before = test.ctx.lamports(&seller.pubkey());
+    test.sell(&seller, 1_000 * ONE_TOKEN).unwrap();
+    // $8 at $150/SOL is 0.0533... SOL, rounded down to the lamport.
+    assert_eq!(test.ctx.lamports(&seller.pubkey()) - before, 53_333_333);
+    assert_eq!(test.ctx.token_balance(&seller.pubkey()), 0);
+    let vault: VaultState = test.fetch(&test.vault);
+    assert_eq!(vault.total_tokens, 1_000 * ONE_TOKEN);
+    let buyback: BuybackState = test.fetch(&test.buyback_pda());
+    assert_eq!(buyback.reserve_lamports, LAMPORTS_PER_SOL - 53_333_333);
+    assert_eq!(buyback.day_volume, 1_000 * ONE_TOKEN);
+}
+
+#[test]
+fn sell_tokens_applies_the_discount_to_the_sale_price() {
+    let mut test = SwapTest::new();
+    test.configure_buyback(0, 2_500, 10_000 * ONE_TOKEN, true)
+        .unwrap();
+    test.fund_buyback(LAMPORTS_PER_SOL).unwrap();
+    let seller = test.seller(1_000 * ONE_TOKEN);
+
+    let before = test.ctx.lamports(&seller.pubkey());
+    test.sell(&seller, 1_000 * ONE_TOKEN).unwrap();
+    // 25% off $0.01 is $7.50 for 1000 tokens, 0.05 SOL.
+    assert_eq!(test.ctx.lamports(&seller.pubkey()) - before, 50_000_000);
+
+    // A single base unit is worth less than a lamport and pays nothing.
+    let before = test.ctx.lamports(&seller.pubkey());
+    test.ctx.create_token_account(&seller.pubkey(), 1);
+    test.sell(&seller, 1).unwrap();
+    assert_eq!(test.ctx.lamports(&seller.pubkey()), before);
+}
+
+#[test]
+fn sell_tokens_enforces_the_daily_cap() {
+    let mut test = SwapTest::new();
+    test.configure_buyback(0, 0, 1_500 * ONE_TOKEN, true)
+        .unwrap();
+    test.fund_buyback(LAMPORTS_PER_SOL).unwrap();
+    let seller = test.seller(2_000 * ONE_TOKEN);
+
+    test.sell(&seller, 1_000 * ONE_TOKEN).unwrap();
+    assert_error(
+        test.sell(&seller, 600 * ONE_TOKEN),
+        error::DAILY_CAP_EXCEEDED,
+    );
+    test.sell(&seller, 500 * ONE_TOKEN).unwrap();
+
+    // The cap resets the next day.
+    let tomorrow = START_TIME + 86_400;
+    test.ctx.warp_to(tomorrow);
+    test.set_price(SOL_PRICE, SOL_EXPO, tomorrow);
+    test.sell(&seller, 500 * ONE_TOKEN).unwrap();
+    let buyback: BuybackState = test.fetch(&test.buyback_pda());
+    assert_eq!(buyback.day_volume, 500 * ONE_TOKEN);
+}
+
+#[test]
+fn sell_tokens_is_limited_by_the_reserve() {
+    let mut test = SwapTest::new();
+    test.configure_buyback(0, 0, 10_000 * ONE_TOKEN, true)
+        .unwrap();
+    test.fund_buyback(50_000_000).unwrap();
+    let seller = test.seller(1_000 * ONE_TOKEN);
+
+    // 1000 tokens pay 66_666_666 lamports, more than the reserve holds.
+    assert_error(
+        test.sell(&seller, 1_000 * ONE_TOKEN),
+        error::INSUFFICIENT_RESERVE,
+    );
+    test.sell(&seller, 750 * ONE_TOKEN).unwrap();
+    let buyback: BuybackState = test.fetch(&test.buyback_pda());
+    assert_eq!(buyback.reserve_lamports, 0);
+
+    test.configure_buyback(0, 0, 10_000 * ONE_TOKEN, false)
+        .unwrap();
+    assert_error(test.sell(&seller, ONE_TOKEN), error::BUYBACK_DISABLED);
+}
+
+#[test]
+fn withdraw_buyback_waits_out_the_withdrawal_delay() {
+    let mut test = SwapTest::new();
+    test.configure_buyback(0, 0, 10_000 * ONE_TOKEN, true)
+        .unwrap();
+    test.fund_buyback(LAMPORTS_PER_SOL).unwrap();
+    let intruder = test.ctx.wallet(1);
+    assert_error(
+        test.withdraw_buyback_as(intruder.pubkey(), 1),
+        error::INVALID_AUTH,
+    );
+
+    // Without a timelock the reserve can be withdrawn at any time.
+    test.withdraw_buyback_as(ADMIN, 100_000_000).unwrap();
+
+    test.set_withdrawal_delay(3_600).unwrap();
+    assert_error(
+        test.withdraw_buyback_as(ADMIN, 100_000_000),
+        error::BUYBACK_ACTIVE,
+    );
+    test.configure_buyback(0, 0, 10_000 * ONE_TOKEN, false)
+        .unwrap();
+    assert_error(
+        test.withdraw_buyback_as(ADMIN, 100_000_000),
+        error::TIMELOCK_NOT_EXPIRED,
+    );
+
+    test.ctx.warp_to(START_TIME + 3_600);
+    let before = test.ctx.lamports(&ADMIN);
+    test.withdraw_buyback_as(ADMIN, 900_000_000).unwrap();
+    assert_eq!(test.ctx.lamports(&ADMIN) - before, 900_000_000);
+    assert_error(
+        test.withdraw_buyback_as(ADMIN, 1),
+        error::INSUFFICIENT_RESERVE,
+    );
+}
+
+#[test]
+fn withdrawal_timelock_queues_executes_and_cancels() {
+    let mut test = SwapTest::new();
+    test.deposit(1_000 * ONE_TOKEN);
+    let recipient = test.ctx.wallet(1);
+    let destination = test.ctx.create_token_account(&recipient.pubkey(), 0);
+
+    // Without a delay the admin withdraws directly.
+    test.withdraw_tokens(100 * ONE_TOKEN).unwrap();
+    test.set_withdrawal_delay(3_600).unwrap();
+    let vault: VaultState = test.fetch(&test.vault);
+    assert_eq!(vault.withdrawal_delay, 3_600);
+    assert_error(
+        test.withdraw_tokens(100 * ONE_TOKEN),
+        error::TIMELOCK_ACTIVE,
+    );
+    assert_error(
+        test.queue_withdrawal_as(recipient.pubkey(), 100 * ONE_TOKEN, destination),
+        error::INVALID_AUTH,
+    );
+
+    test.queue_withdrawal_as(ADMIN, 100 * ONE_TOKEN, destination)
+        .unwrap();
+    let pending: PendingWithdrawalState = test.fetch(&test.withdrawal_pda(0));
+    assert_eq!(pending.nonce, 0);
+    assert_eq!(pending.amount, 100 * ONE_TOKEN);
+    assert_eq!(pending.destination, destination);
+    assert_eq!(pending.eta, START_TIME + 3_600);
+    assert_error(
+        test.execute_withdrawal(0, destination),
+        error::TIMELOCK_NOT_EXPIRED,
+    );
+
+    // A second withdrawal gets the next nonce and can be cancelled.
+    test.queue_withdrawal_as(ADMIN, 200 * ONE_TOKEN, destination)
+        .unwrap();
+    test.cancel_withdrawal(1).unwrap();
+    assert!(!test.ctx.account_exists(&test.withdrawal_pda(1)));
+
+    test.ctx.warp_to(START_TIME + 3_600);
+    test.execute_withdrawal(0, destination).unwrap();
+    assert_eq!(test.ctx.token_balance(&recipient.pubkey()), 100 * ONE_TOKEN);
+    let vault: VaultState = test.fetch(&test.vault);
+    assert_eq!(vault.total_tokens, 800 * ONE_TOKEN);
+    assert_eq!(vault.withdrawal_nonce, 2);
+    assert!(!test.ctx.account_exists(&test.withdrawal_pda(0)));
+}
+
+#[test]
+fn admin_cannot_empty_a_timelocked_vault() {
+    let mut test = SwapTest::new();
+    test.deposit(1_000 * ONE_TOKEN);
+    test.set_withdrawal_delay(3_600).unwrap();
+    let buyer = test.ctx.wallet(1_000);
+
+    // Price changes are queued behind the delay, and zero is never accepted.
+    assert_error(test.update_price_as(ADMIN, 0), error::INVALID_PRICE);
+    test.update_price_as(ADMIN, 1).unwrap();
+    let vault: VaultState = test.fetch(&test.vault);
+    assert_eq!(vault.price_per_token, PRICE_PER_TOKEN);
+    assert_error(test.apply_price_update(), error::TIMELOCK_NOT_EXPIRED);
+
+    // The admin can't buy the inventory with SOL that flows back to itself.
+    let ix = test.purchase_instruction(ADMIN, ADMIN, 1_000 * ONE_TOKEN, &GateProof::None);
+    assert_error(test.ctx.send(&[ix], &[]), error::ADMIN_PURCHASE);
+    assert_error(
+        test.purchase_for(&buyer, ADMIN, 1_000 * ONE_TOKEN),
+        error::ADMIN_PURCHASE,
+    );
+    let ix = test.purchase_instruction(ADMIN, buyer.pubkey(), ONE_TOKEN, &GateProof::None);
+    assert_error(test.ctx.send(&[ix], &[]), error::ADMIN_PURCHASE);
+
+    // Nor sidestep the price delay with an auction opening right away.
+    let now = START_TIME;
+    assert_error(
+        test.start_auction(20_000, 1, 1, 60, (now, now + 7_200)),
+        error::INVALID_AUCTION_CONFIG,
+    );
+
+    let vault: VaultState = test.fetch(&test.vault);
+    assert_eq!(vault.total_tokens, 1_000 * ONE_TOKEN);
+
+    test.ctx.warp_to(START_TIME + 3_600);
+    test.apply_price_update().unwrap();
+    let vault: VaultState = test.fetch(&test.vault);
+    assert_eq!(vault.price_per_token, 1);
+    assert_error(test.apply_price_update(), error::NO_PRICE_UPDATE_QUEUED);
+}
+
+#[test]
+fn withdrawal_delay_cannot_be_shortened() {
+    let mut test = SwapTest::new();
+    test.set_withdrawal_delay(3_600).unwrap();
+
+    assert_error(test.set_withdrawal_delay(60), error::TIMELOCK_DECREASE);
+    assert_error(test.set_withdrawal_delay(0), error::TIMELOCK_DECREASE);
+    test.set_withdrawal_delay(7_200).unwrap();
+    let vault: VaultState = test.fetch(&test.vault);
+    assert_eq!(vault.withdrawal_delay, 7_200);
+}
+
+#[test]
+fn finalize_sale_cannot_unlock_before_the_withdrawal_delay() {
+    let mut test = SwapTest::new();
+    test.deposit(10_000 * ONE_TOKEN);
+    let buyer = test.ctx.wallet(1_000);
+    test.purchase(&buyer, 1_000 * ONE_TOKEN).unwrap();
+    test.set_liquidity_bps_as(ADMIN, 2_000).unwrap();
+    test.set_withdrawal_delay(86_400).unwrap();
+
+    assert_error(
+        test.finalize_sale(START_TIME + 3_600),
+        error::INVALID_UNLOCK_TIME,
+    );
+    test.finalize_sale(START_TIME + 86_400).unwrap();
+}
+
+#[test]
+fn close_vault_is_admin_only() {
+    let mut test = SwapTest::new();
+    test.deposit(100 * ONE_TOKEN);
+    let intruder = test.ctx.wallet(1);
+    let intruder_tokens = test.ctx.create_token_account(&intruder.pubkey(), 0);
+
+    assert_error(
+        test.close_vault_as(intruder.pubkey(), intruder_tokens),
+        error::INVALID_AUTH,
+    );
+    // The admin can't sweep the inventory into someone else's account either.
+    assert_error(
+        test.close_vault_as(ADMIN, intruder_tokens),
+        error::CONSTRAINT_TOKEN_OWNER,
+    );
+
+    let admin_tokens = test.ctx.token_balance(&ADMIN);
+    test.close_vault_as(ADMIN, test.ctx.ata(&ADMIN)).unwrap();
+    assert_eq!(
+        test.ctx.token_balance(&ADMIN),
+        admin_tokens + 100 * ONE_TOKEN
+    );
+    assert!(!test.ctx.account_exists(&test.vault));
+}
+
+#[test]
+fn finalize_sale_locks_the_committed_share_of_the_raised_sol() {
+    let mut test = SwapTest::new();
+    test.deposit(10_000 * ONE_TOKEN);
+    let buyer = test.ctx.wallet(1_000);
+    test.purchase(&buyer, 1_000 * ONE_TOKEN).unwrap();
+    let unlock_time = START_TIME + 3_600;
+
+    // Nothing is committed yet.
+    assert_error(test.finalize_sale(unlock_time), error::INVALID_PERCENTAGE);
+    assert_error(
+        test.set_liquidity_bps_as(buyer.pubkey(), 2_000),
+        error::INVALID_AUTH,
+    );
+    assert_error(
+        test.set_liquidity_bps_as(ADMIN, 10_001),
+        error::INVALID_PERCENTAGE,
+    );
+    test.set_liquidity_bps_as(ADMIN, 2_000).unwrap();
+    assert_error(
+        test.set_liquidity_bps_as(ADMIN, 1_000),
+        error::INVALID_PERCENTAGE,
+    );
+    assert_error(test.finalize_sale(START_TIME), error::INVALID_UNLOCK_TIME);
+
+    test.finalize_sale(unlock_time).unwrap();
+    // 20% of the 66_666_667 lamports raised buys $2 of tokens at $150/SOL.
+    let reserve: LiquidityReserveState = test.fetch(&test.liquidity_pda());
+    assert_eq!(reserve.lamports, 13_333_333);
+    assert_eq!(reserve.token_amount, 199_999_995);
+    assert_eq!(reserve.unlock_time, unlock_time);
+    assert_eq!(test.ctx.token_balance(&test.liquidity_pda()), 199_999_995);
+    let vault: VaultState = test.fetch(&test.vault);
+    assert_eq!(vault.total_tokens, 9_000 * ONE_TOKEN - 199_999_995);
+
+    assert_error(test.withdraw_liquidity(), error::LIQUIDITY_LOCKED);
+    test.ctx.warp_to(unlock_time);
+    let admin_tokens = test.ctx.token_balance(&ADMIN);
+    let admin_lamports = test.ctx.lamports(&ADMIN);
+    test.withdraw_liquidity().unwrap();
+    assert_eq!(test.ctx.token_balance(&ADMIN), admin_tokens + 199_999_995);
+    assert!(test.ctx.lamports(&ADMIN) - admin_lamports > 13_333_333);
+    assert!(!test.ctx.account_exists(&test.liquidity_pda()));
+}
+
+#[test]
+fn distribute_sends_each_allocation() {
+    let mut test = SwapTest::new();
+    test.deposit(1_000 * ONE_TOKEN);
+    let recipients: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+    let destinations: Vec<Pubkey> = recipients
+        .iter()
+        .map(|r| test.ctx.create_token_account(r, 0))
+        .collect();
+
+    test.distribute(
+        &destinations,
+        vec![100 * ONE_TOKEN, 200 * ONE_TOKEN, 300 * ONE_TOKEN],
+    )
+    .unwrap();
+
+    for (recipient, amount) in recipients.iter().zip([100, 200, 300]) {
+        assert_eq!(test.ctx.token_balance(recipient), amount * ONE_TOKEN);
+    }
+    let vault: VaultState = test.fetch(&test.vault);
+    assert_eq!(vault.total_tokens, 400 * ONE_TOKEN);
+}
+
+#[test]
+fn distribute_rejects_invalid_batches() {
+    let mut test = SwapTest::new();
+    test.deposit(1_000 * ONE_TOKEN);
+    let recipient = Pubkey::new_unique();
+    let destination = test.ctx.create_token_account(&recipient, 0);
+
+    assert_error(
+        test.distribute(&[destination], vec![ONE_TOKEN, ONE_TOKEN]),
+        error::INVALID_DISTRIBUTION,
+    );
+    assert_error(
+        test.distribute(&[destination], vec![0]),
+        error::INVALID_DISTRIBUTION,
+    );
+    assert_error(
+        test.distribute(
+            &[destination, destination],
+            vec![600 * ONE_TOKEN, 600 * ONE_TOKEN],
+        ),
+        error::INSUFFICIENT_TOKENS,
+    );
+
+    // A token account for the right mint that isn't the owner's associated account.
+    let stray = Pubkey::new_unique();
+    let data = test.ctx.account_data(&destination);
+    test.ctx.set_account(stray, spl_token_2022::ID, data);
+    assert_error(
+        test.distribute(&[stray], vec![ONE_TOKEN]),
+        error::INVALID_RECIPIENT,
+    );
+    assert_eq!(test.ctx.token_balance(&recipient), 0);
+}
+
+#[test]
+fn sync_vault_reconciles_the_token_balance() {
+    let mut test = SwapTest::new();
+    test.deposit(1_000 * ONE_TOKEN);
+    let buyer = test.ctx.wallet(1_000);
+    let vault = test.vault;
+
+    // The vault account holds less than `total_tokens` claims.
+    test.ctx.create_token_account(&vault, 400 * ONE_TOKEN);
+    assert_error(test.purchase(&buyer, ONE_TOKEN), error::VAULT_OUT_OF_SYNC);
+    assert_error(test.sync_vault_as(buyer.pubkey()), error::INVALID_AUTH);
+    test.sync_vault_as(ADMIN).unwrap();
+    let state: VaultState = test.fetch(&vault);
+    assert_eq!(state.total_tokens, 400 * ONE_TOKEN);
+    test.purchase(&buyer, ONE_TOKEN).unwrap();
+
+    // Tokens sent straight to the vault account become sellable once synced.
+    test.ctx.create_token_account(&vault, 1_500 * ONE_TOKEN);
+    test.sync_vault_as(ADMIN).unwrap();
+    let state: VaultState = test.fetch(&vault);
+    assert_eq!(state.total_tokens, 1_500 * ONE_TOKEN);
+}
+
+#[test]
+fn migrate_vault_upgrades_a_legacy_layout() {
+    let mut test = SwapTest::new();
+    let legacy = test.vault_pda(1);
+    let mut legacy_data = account_discriminator("Vault").to_vec();
+    legacy_data.extend_from_slice(&1u64.to_le_bytes());
+    legacy_data.extend_from_slice(test.ctx.mint.as_ref());
+    legacy_data.extend_from_slice(test.ctx.ata(&legacy).as_ref());
+    legacy_data.extend_from_slice(&PRICE_PER_TOKEN.to_le_bytes());
+    legacy_data.extend_from_slice(&(500 * ONE_TOKEN).to_le_bytes());
+    legacy_data.push(255); // bump
+    legacy_data.extend_from_slice(ADMIN.as_ref());
+    assert_eq!(legacy_data.len(), LEGACY_VAULT_LEN);
+    test.ctx.set_account(legacy, SWAP_ID, legacy_data.clone());
+
+    let attacker = test.ctx.wallet(1);
+    assert_error(
+        test.migrate_vault_as(attacker.pubkey(), legacy),
+        error::INVALID_AUTH,
+    );
+    test.migrate_vault_as(ADMIN, legacy).unwrap();
+
+    // Resized to the current layout with the old fields kept and the new ones zeroed.
+    let data = test.ctx.account_data(&legacy);
+    assert_eq!(data.len(), test.ctx.account_data(&test.vault).len());
+    assert_eq!(data[..LEGACY_VAULT_LEN], legacy_data[..]);
+    assert_eq!(data[LEGACY_VAULT_LEN], VAULT_VERSION);
+    assert!(data[LEGACY_VAULT_LEN + 1..].iter().all(|&byte| byte == 0));
+    let vault: VaultState = test.fetch(&legacy);
+    assert_eq!(vault.total_tokens, 500 * ONE_TOKEN);
+    assert!(
+        test.ctx.lamports(&legacy) >= test.ctx.svm.minimum_balance_for_rent_exemption(data.len())
+    );
+
+    assert_error(
+        test.migrate_vault_as(ADMIN, legacy),
+        error::ALREADY_MIGRATED,
+    );
+    assert_error(
+        test.migrate_vault_as(ADMIN, test.vault),
+        error::ALREADY_MIGRATED,
+    );
+}
+
+#[test]
+fn auction_moves_through_its_phases() {
+    let mut test = SwapTest::new();
+    test.deposit(1_000 * ONE_TOKEN);
+    let early = test.ctx.wallet(1_000);
+    let late = test.ctx.wallet(1_000);
+    test.ctx.create_token_account(&early.pubkey(), 0);
+    test.ctx.create_token_account(&late.pubkey(), 0);
+
+    let start = START_TIME + 3_600;
+    let end = start + 4 * 3_600;
+    test.start_auction(20_000, 10_000, 2_500, 3_600, (start, end))
+        .unwrap();
+
+    // Not started yet.
+    assert_error(
+        test.purchase_auction(&early, 100 * ONE_TOKEN),
+        error::AUCTION_NOT_ACTIVE,
+    );
+
+    // Opening price of $0.02 with SOL at $100.
+    test.ctx.warp_to(start);
+    test.set_price(10_000_000_000, SOL_EXPO, start);
+    test.purchase_auction(&early, 100 * ONE_TOKEN).unwrap();
+
+    // Two decay steps later the price is $0.015.
+    let later = start + 2 * 3_600 + 59;
+    test.ctx.warp_to(later);
+    test.set_price(10_000_000_000, SOL_EXPO, later);
+    test.purchase_auction(&late, 100 * ONE_TOKEN).unwrap();
+    let auction: AuctionState = test.fetch(&test.auction_pda());
+    assert_eq!(auction.last_price, 15_000);
+    assert_eq!(auction.lamports_escrowed, 20_000_000 + 15_000_000);
+    let stats: SaleStatsState = test.fetch(&test.stats_pda());
+    assert_eq!(stats.lamports_raised, 35_000_000);
+
+    // A running auction can't be settled or refunded.
+    assert_error(test.settle_auction(), error::AUCTION_NOT_ENDED);
+    assert_error(
+        test.claim_auction_refund(&early),
+        error::AUCTION_NOT_SETTLED,
+    );
+
+    // Ended: no more purchases, settlement at the last price.
+    test.ctx.warp_to(end);
+    test.set_price(10_000_000_000, SOL_EXPO, end);
+    assert_error(
+        test.purchase_auction(&late, ONE_TOKEN),
+        error::AUCTION_NOT_ACTIVE,
+    );
+    test.settle_auction().unwrap();
+    assert_error(test.settle_auction(), error::AUCTION_SETTLED);
+
+    let auction: AuctionState = test.fetch(&test.auction_pda());
+    assert!(auction.settled);
+    assert_eq!(auction.clearing_price, 15_000);
+    // Only the clearing cost of 200 tokens at $0.015 counts as raised.
+    let stats: SaleStatsState = test.fetch(&test.stats_pda());
+    assert_eq!(stats.lamports_raised, 30_000_000);
+
+    // The early buyer paid $2 and is refunded down to the $1.50 clearing cost.
+    let bid_rent = test.ctx.lamports(&test.bid_pda(&early.pubkey()));
+    let before = test.ctx.lamports(&early.pubkey());
+    test.claim_auction_refund(&early).unwrap();
+    assert_eq!(
+        test.ctx.lamports(&early.pubkey()) - before,
+        5_000_000 + bid_rent
+    );
+
+    let bid_rent = test.ctx.lamports(&test.bid_pda(&late.pubkey()));
+    let before = test.ctx.lamports(&late.pubkey());
+    test.claim_auction_refund(&late).unwrap();
+    assert_eq!(test.ctx.lamports(&late.pubkey()) - before, bid_rent);
+}
+
+#[test]
+fn auction_proceeds_are_withdrawn_once_after_settlement() {
+    let mut test = SwapTest::new();
+    test.deposit(1_000 * ONE_TOKEN);
+    let early = test.ctx.wallet(1_000);
+    let late = test.ctx.wallet(1_000);
+    test.ctx.create_token_account(&early.pubkey(), 0);
+    test.ctx.create_token_account(&late.pubkey(), 0);
+
+    let start = START_TIME + 3_600;
+    let end = start + 4 * 3_600;
+    test.start_auction(20_000, 10_000, 2_500, 3_600, (start, end))
+        .unwrap();
+    test.ctx.warp_to(start);
+    test.set_price(10_000_000_000, SOL_EXPO, start);
+    test.purchase_auction(&early, 100 * ONE_TOKEN).unwrap();
+    let later = start + 2 * 3_600;
+    test.ctx.warp_to(later);
+    test.set_price(10_000_000_000, SOL_EXPO, later);
+    test.purchase_auction(&late, 100 * ONE_TOKEN).unwrap();
+
+    assert_error(test.withdraw_auction_proceeds(), error::AUCTION_NOT_SETTLED);
+
+    test.ctx.warp_to(end);
+    test.settle_auction().unwrap();
+    let before = test.ctx.lamports(&ADMIN);
+    test.withdraw_auction_proceeds().unwrap();
+    // 200 tokens at the $0.015 clearing price.
+    assert_eq!(test.ctx.lamports(&ADMIN) - before, 30_000_000);
+    assert_error(test.withdraw_auction_proceeds(), error::AUCTION_SETTLED);
+    assert_eq!(test.ctx.lamports(&ADMIN) - before, 30_000_000);
+
+    // The escrow still covers the early buyer's refund.
+    let bid_rent = test.ctx.lamports(&test.bid_pda(&early.pubkey()));
+    let before = test.ctx.lamports(&early.pubkey());
+    test.claim_auction_refund(&early).unwrap();
+    assert_eq!(
+        test.ctx.lamports(&early.pubkey()) - before,
+        5_000_000 + bid_rent
+    );
+}
+
+#[test]
+fn pay_as_bid_auction_bids_close_after_settlement() {
+    let mut test = SwapTest::new();
+    test.deposit(1_000 * ONE_TOKEN);
+    let buyer = test.ctx.wallet(1_000);
+    test.ctx.create_token_account(&buyer.pubkey(), 0);
+
+    let end = START_TIME + 3_600;
+    test.start_auction_with_clearing(20_000, 10_000, 1, 60, (START_TIME, end), false)
+        .unwrap();
+    test.purchase_auction(&buyer, 100 * ONE_TOKEN).unwrap();
+    assert_error(
+        test.claim_auction_refund(&buyer),
+        error::AUCTION_NOT_SETTLED,
+    );
+
+    test.ctx.warp_to(end);
+    test.settle_auction().unwrap();
+    let bid = test.bid_pda(&buyer.pubkey());
+    let bid_rent = test.ctx.lamports(&bid);
+    let before = test.ctx.lamports(&buyer.pubkey());
+    test.claim_auction_refund(&buyer).unwrap();
+    assert_eq!(test.ctx.lamports(&buyer.pubkey()) - before, bid_rent);
+    assert!(!test.ctx.account_exists(&bid));
+}
+
+#[test]
+fn fixed_price_sale_is_paused_while_an_auction_runs() {
+    let mut test = SwapTest::new();
+    test.deposit(1_000 * ONE_TOKEN);
+    let buyer = test.ctx.wallet(1_000);
+    test.purchase(&buyer, ONE_TOKEN).unwrap();
+
+    let end = START_TIME + 3_600;
+    test.start_auction(20_000, 10_000, 1, 60, (START_TIME, end))
+        .unwrap();
+    assert_error(test.purchase(&buyer, ONE_TOKEN), error::AUCTION_ACTIVE);
+
+    // Still paused after the window closes, until the auction is settled.
+    test.ctx.warp_to(end);
+    test.set_price(SOL_PRICE, SOL_EXPO, end);
+    assert_error(test.purchase(&buyer, ONE_TOKEN), error::AUCTION_ACTIVE);
+    test.settle_auction().unwrap();
+    test.purchase(&buyer, ONE_TOKEN).unwrap();
+}
+
+#[test]
+fn attestation_gates_derive_from_the_issuer_and_recipient() {
+    let mut test = SwapTest::new();
+    test.deposit(1_000 * ONE_TOKEN);
+    let issuer = Keypair::new().pubkey();
+    let literal = GateSeed::Literal {
+        bytes: b"attestation".to_vec(),
+    };
+
+    // Without the issuer seed any issuer's attestation would pass, and without
+    // the recipient seed anyone's.
+    assert_error(
+        test.configure_gate_with_seeds(
+            GateKind::Attestation,
+            issuer,
+            vec![literal.clone(), GateSeed::Recipient],
+        ),
+        error::INVALID_GATE_CONFIG,
+    );
+    assert_error(
+        test.configure_gate_with_seeds(
+            GateKind::Attestation,
+            issuer,
+            vec![literal.clone(), GateSeed::Issuer],
+        ),
+        error::INVALID_GATE_CONFIG,
+    );
+    test.configure_gate_with_seeds(
+        GateKind::Attestation,
+        issuer,
+        vec![literal, GateSeed::Issuer, GateSeed::Recipient],
+    )
+    .unwrap();
+
+    // An attestation written by the issuer program for a foreign issuer.
+    let buyer = test.ctx.wallet(1_000);
+    let expires_at = START_TIME + 3_600;
+    let foreign = Keypair::new().pubkey();
+    let attestation = test.attest(ISSUER_PROGRAM, &foreign, &buyer.pubkey(), expires_at);
+    assert_error(
+        test.purchase_gated(
+            &buyer,
+            buyer.pubkey(),
+            ONE_TOKEN,
+            GateProof::Attestation(attestation),
+        ),
+        error::INVALID_ATTESTATION,
+    );
+    let attestation = test.attest(ISSUER_PROGRAM, &issuer, &buyer.pubkey(), expires_at);
+    test.purchase_gated(
+        &buyer,
+        buyer.pubkey(),
+        ONE_TOKEN,
+        GateProof::Attestation(attestation),
+    )
+    .unwrap();
+}
+
+#[test]
+fn attestation_gate_checks_the_issuer_account() {
+    let mut test = SwapTest::new();
+    test.deposit(1_000 * ONE_TOKEN);
+    let issuer = Keypair::new().pubkey();
+    test.configure_gate(GateKind::Attestation, issuer).unwrap();
+    let buyer = test.ctx.wallet(1_000);
+    let recipient = buyer.pubkey();
+    let expires_at = START_TIME + 3_600;
+
+    assert_error(
+        test.purchase_gated(&buyer, recipient, ONE_TOKEN, GateProof::None),
+        error::GATE_REQUIRED,
+    );
+
+    // Someone else's attestation.
+    let other = Keypair::new().pubkey();
+    let attestation = test.attest(ISSUER_PROGRAM, &issuer, &other, expires_at);
+    assert_error(
+        test.purchase_gated(
+            &buyer,
+            recipient,
+            ONE_TOKEN,
+            GateProof::Attestation(attestation),
+        ),
+        error::INVALID_ATTESTATION,
+    );
+
+    // Issued by a different issuer key, or written by a different program.
+    let attestation = test.attest(ISSUER_PROGRAM, &other, &recipient, expires_at);
+    assert_error(
+        test.purchase_gated(
+            &buyer,
+            recipient,
+            ONE_TOKEN,
+            GateProof::Attestation(attestation),
+        ),
+        error::INVALID_ATTESTATION,
+    );
+    let attestation = test.attest(SWAP_ID, &issuer, &recipient, expires_at);
+    assert_error(
+        test.purchase_gated(
+            &buyer,
+            recipient,
+            ONE_TOKEN,
+            GateProof::Attestation(attestation),
+        ),
+        error::INVALID_ATTESTATION,
+    );
+
+    let attestation = test.attest(ISSUER_PROGRAM, &issuer, &recipient, expires_at);
+    test.purchase_gated(
+        &buyer,
+        recipient,
+        ONE_TOKEN,
+        GateProof::Attestation(attestation),
+    )
+    .unwrap();
+
+    // Expired once the clock passes its expiry.
+    test.ctx.warp_to(expires_at);
+    test.set_price(SOL_PRICE, SOL_EXPO, expires_at);
+    assert_error(
+        test.purchase_gated(
+            &buyer,
+            recipient,
+            ONE_TOKEN,
+            GateProof::Attestation(attestation),
+        ),
+        error::ATTESTATION_EXPIRED,
+    );
+}
+
+#[test]
+fn voucher_gate_checks_the_issuer_signature() {
+    let mut test = SwapTest::new();
+    test.deposit(1_000 * ONE_TOKEN);
+    let issuer = Keypair::new();
+    test.configure_gate(GateKind::Voucher, issuer.pubkey())
+        .unwrap();
+    let buyer = test.ctx.wallet(1_000);
+    let recipient = buyer.pubkey();
+    let vault = test.vault;
+    let expires_at = START_TIME + 3_600;
+
+    assert_error(
+        test.purchase_gated(&buyer, recipient, ONE_TOKEN, GateProof::None),
+        error::GATE_REQUIRED,
+    );
+
+    let other = Keypair::new();
+    let wrong_issuer = voucher(&other, &vault, &recipient, expires_at);
+    assert_error_at(
+        test.purchase_gated(
+            &buyer,
+            recipient,
+            ONE_TOKEN,
+            GateProof::Voucher(wrong_issuer),
+        ),
+        1,
+        error::INVALID_VOUCHER,
+    );
+    let wrong_recipient = voucher(&issuer, &vault, &other.pubkey(), expires_at);
+    assert_error_at(
+        test.purchase_gated(
+            &buyer,
+            recipient,
+            ONE_TOKEN,
+            GateProof::Voucher(wrong_recipient),
+        ),
+        1,
+        error::INVALID_VOUCHER,
+    );
+    let expired = voucher(&issuer, &vault, &recipient, START_TIME);
+    assert_error_at(
+        test.purchase_gated(&buyer, recipient, ONE_TOKEN, GateProof::Voucher(expired)),
+        1,
+        error::ATTESTATION_EXPIRED,
+    );
+
+    let valid = voucher(&issuer, &vault, &recipient, expires_at);
+    test.purchase_gated(&buyer, recipient, ONE_TOKEN, GateProof::Voucher(valid))
+        .unwrap();
+    assert_eq!(test.ctx.token_balance(&recipient), ONE_TOKEN);
+}
+
+#[test]
+fn auction_purchases_pass_the_attestation_gate() {
+    let mut test = SwapTest::new();
+    test.deposit(1_000 * ONE_TOKEN);
+    let issuer = Keypair::new().pubkey();
+    test.configure_gate(GateKind::Attestation, issuer).unwrap();
+    test.start_auction(20_000, 10_000, 1, 60, (START_TIME, START_TIME + 7_200))
+        .unwrap();
+    let buyer = test.ctx.wallet(1_000);
+    test.ctx.create_token_account(&buyer.pubkey(), 0);
+    let expires_at = START_TIME + 3_600;
+
+    assert_error(
+        test.purchase_auction_gated(&buyer, ONE_TOKEN, GateProof::None),
+        error::GATE_REQUIRED,
+    );
+    let other = Keypair::new().pubkey();
+    let attestation = test.attest(ISSUER_PROGRAM, &issuer, &other, expires_at);
+    assert_error(
+        test.purchase_auction_gated(&buyer, ONE_TOKEN, GateProof::Attestation(attestation)),
+        error::INVALID_ATTESTATION,
+    );
+    let attestation = test.attest(SWAP_ID, &issuer, &buyer.pubkey(), expires_at);
+    assert_error(
+        test.purchase_auction_gated(&buyer, ONE_TOKEN, GateProof::Attestation(attestation)),
+        error::INVALID_ATTESTATION,
+    );
+
+    let attestation = test.attest(ISSUER_PROGRAM, &issuer, &buyer.pubkey(), expires_at);
+    test.purchase_auction_gated(&buyer, ONE_TOKEN, GateProof::Attestation(attestation))
+        .unwrap();
+
+    test.ctx.warp_to(expires_at);
+    test.set_price(SOL_PRICE, SOL_EXPO, expires_at);
+    assert_error(
+        test.purchase_auction_gated(&buyer, ONE_TOKEN, GateProof::Attestation(attestation)),
+        error::ATTESTATION_EXPIRED,
+    );
+}
+
+#[test]
+fn auction_purchases_pass_the_voucher_gate() {
+    let mut test = SwapTest::new();
+    test.deposit(1_000 * ONE_TOKEN);
+    let issuer = Keypair::new();
+    test.configure_gate(GateKind::Voucher, issuer.pubkey())
+        .unwrap();
+    test.start_auction(20_000, 10_000, 1, 60, (START_TIME, START_TIME + 7_200))
+        .unwrap();
+    let buyer = test.ctx.wallet(1_000);
+    test.ctx.create_token_account(&buyer.pubkey(), 0);
+    let vault = test.vault;
+    let expires_at = START_TIME + 3_600;
+
+    assert_error(
+        test.purchase_auction_gated(&buyer, ONE_TOKEN, GateProof::None),
+        error::GATE_REQUIRED,
+    );
+    let other = Keypair::new();
+    let wrong_issuer = voucher(&other, &vault, &buyer.pubkey(), expires_at);
+    assert_error_at(
+        test.purchase_auction_gated(&buyer, ONE_TOKEN, GateProof::Voucher(wrong_issuer)),
+        1,
+        error::INVALID_VOUCHER,
+    );
+    let wrong_recipient = voucher(&issuer, &vault, &other.pubkey(), expires_at);
+    assert_error_at(
+        test.purchase_auction_gated(&buyer, ONE_TOKEN, GateProof::Voucher(wrong_recipient)),
+        1,
+        error::INVALID_VOUCHER,
+    );
+    let expired = voucher(&issuer, &vault, &buyer.pubkey(), START_TIME);
+    assert_error_at(
+        test.purchase_auction_gated(&buyer, ONE_TOKEN, GateProof::Voucher(expired)),
+        1,
+        error::ATTESTATION_EXPIRED,
+    );
+
+    let valid = voucher(&issuer, &vault, &buyer.pubkey(), expires_at);
+    test.purchase_auction_gated(&buyer, ONE_TOKEN, GateProof::Voucher(valid))
+        .unwrap();
+    assert_eq!(test.ctx.token_balance(&buyer.pubkey()), ONE_TOKEN);
+}
+
+#[test]
+fn auction_rejects_invalid_configuration() {
+    let mut test = SwapTest::new();
+    let window = (START_TIME, START_TIME + 60);
+
+    // Floor above the start price.
+    assert_error(
+        test.start_auction(10_000, 20_000, 1, 60, window),
+        error::INVALID_AUCTION_CONFIG,
+    );
+    // Zero decay interval.
+    assert_error(
+        test.start_auction(20_000, 10_000, 1, 0, window),
+        error::INVALID_AUCTION_CONFIG,
+    );
+    // Empty window.
+    assert_error(
+        test.start_auction(20_000, 10_000, 1, 60, (START_TIME, START_TIME)),
+        error::INVALID_AUCTION_CONFIG,
+    );
+}
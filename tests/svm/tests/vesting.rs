@@ -0,0 +1,2402 @@
+//! In-process tests for the vesting program, warping the clock across
+//! calendar-month boundaries.
+
+#![allow(clippy::result_large_err)]
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::system_program;
+use anchor_lang::{InstructionData, ToAccountMetas};
+use anchor_spl::associated_token;
+use anchor_spl::token_2022::spl_token_2022::{self, extension::StateWithExtensions};
+use chrono::{TimeZone, Utc};
+use pythnet_sdk::{accumulators::merkle::MerkleTree, hashers::keccak256_160::Keccak160};
+use svm_tests::litesvm::types::TransactionResult;
+use svm_tests::*;
+use vesting::{
+    sdk, Config, Distributor, DistributorGrant, ErrorCode as VestingError, ReserveAccount,
+    ReserveParams, ReserveStatus, ScheduleKind, TipVault, Tranche, TrancheSchedule, VestingAccount,
+    SECONDS_PER_DAY,
+};
+
+const ONE_TOKEN: u64 = 1_000_000;
+const RESERVE_TYPE: &str = "team";
+const MONTHLY: u64 = 100 * ONE_TOKEN;
+const TOTAL: u64 = 12 * MONTHLY;
+/// `SystemError::AccountAlreadyInUse`, raised when an `init` target already exists.
+const ACCOUNT_ALREADY_IN_USE: u32 = 0;
+
+fn timestamp(year: i32, month: u32, day: u32, hour: u32, min: u32, sec: u32) -> i64 {
+    Utc.with_ymd_and_hms(year, month, day, hour, min, sec)
+        .unwrap()
+        .timestamp()
+}
+
+/// Terms of a reserve created by `VestingTest::create_reserve_for`.
+#[derive(Clone, Copy)]
+struct Grant {
+    start_time: i64,
+    end_time: i64,
+    cliff_time: i64,
+    revocable: bool,
+    schedule: ScheduleKind,
+    tge_unlock_amount: u64,
+    cliff_release_amount: u64,
+}
+
+impl Grant {
+    fn monthly(start_time: i64, end_time: i64, cliff_time: i64) -> Self {
+        Grant {
+            start_time,
+            end_time,
+            cliff_time,
+            revocable: false,
+            schedule: ScheduleKind::Monthly,
+            tge_unlock_amount: 0,
+            cliff_release_amount: 0,
+        }
+    }
+}
+
+struct VestingTest {
+    ctx: TestContext,
+    beneficiary: Keypair,
+    vesting_account: Pubkey,
+}
+
+impl VestingTest {
+    /// A test context whose program config has `ADMIN` as its admin.
+    fn new() -> Self {
+        let mut test = Self::without_config();
+        test.initialize_config_as(ADMIN, ADMIN).unwrap();
+        test
+    }
+
+    fn without_config() -> Self {
+        let mut ctx = TestContext::new("vesting", vesting::ID);
+        ctx.create_token_account(&ADMIN, 1_000_000 * ONE_TOKEN);
+        let beneficiary = ctx.wallet(1);
+        ctx.create_token_account(&beneficiary.pubkey(), 0);
+        VestingTest {
+            ctx,
+            beneficiary,
+            vesting_account: Pubkey::find_program_address(&[RESERVE_TYPE.as_bytes()], &vesting::ID)
+                .0,
+        }
+    }
+
+    fn config_pda(&self) -> Pubkey {
+        Pubkey::find_program_address(&[b"config"], &vesting::ID).0
+    }
+
+    fn initialize_config_as(&mut self, signer: Pubkey, admin: Pubkey) -> TransactionResult {
+        let ix = Instruction {
+            program_id: vesting::ID,
+            accounts: vesting::accounts::InitializeConfig {
+                signer,
+                config: self.config_pda(),
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: vesting::instruction::InitializeConfig { admin }.data(),
+        };
+        self.ctx.send(&[ix], &[])
+    }
+
+    fn propose_admin_as(&mut self, admin: Pubkey, new_admin: Option<Pubkey>) -> TransactionResult {
+        let ix = Instruction {
+            program_id: vesting::ID,
+            accounts: vesting::accounts::ProposeAdmin {
+                admin,
+                config: self.config_pda(),
+            }
+            .to_account_metas(None),
+            data: vesting::instruction::ProposeAdmin { new_admin }.data(),
+        };
+        self.ctx.send(&[ix], &[])
+    }
+
+    fn accept_admin_as(&mut self, pending_admin: Pubkey) -> TransactionResult {
+        let ix = Instruction {
+            program_id: vesting::ID,
+            accounts: vesting::accounts::AcceptAdmin {
+                pending_admin,
+                config: self.config_pda(),
+            }
+            .to_account_metas(None),
+            data: vesting::instruction::AcceptAdmin {}.data(),
+        };
+        self.ctx.send(&[ix], &[])
+    }
+
+    fn fetch_config(&self) -> Config {
+        let data = self.ctx.account_data(&self.config_pda());
+        Config::try_deserialize(&mut data.as_slice()).unwrap()
+    }
+
+    /// The beneficiary's first reserve.
+    fn reserve_pda(&self) -> Pubkey {
+        self.reserve_pda_for(&self.beneficiary.pubkey(), 0)
+    }
+
+    fn reserve_pda_for(&self, beneficiary: &Pubkey, index: u64) -> Pubkey {
+        Pubkey::find_program_address(
+            &[
+                b"reserve",
+                self.vesting_account.as_ref(),
+                beneficiary.as_ref(),
+                &index.to_le_bytes(),
+            ],
+            &vesting::ID,
+        )
+        .0
+    }
+
+    /// Where reserves lived before they were seeded by beneficiary and index.
+    fn legacy_reserve_pda(&self) -> Pubkey {
+        Pubkey::find_program_address(&[b"reserve", self.vesting_account.as_ref()], &vesting::ID).0
+    }
+
+    fn fetch_reserve(&self) -> ReserveAccount {
+        let data = self.ctx.account_data(&self.reserve_pda());
+        ReserveAccount::try_deserialize(&mut data.as_slice()).unwrap()
+    }
+
+    /// Overwrites the beneficiary's first reserve with `reserve`.
+    fn write_reserve(&mut self, reserve: &ReserveAccount) {
+        let mut data = Vec::new();
+        reserve.try_serialize(&mut data).unwrap();
+        self.ctx.set_account(self.reserve_pda(), vesting::ID, data);
+    }
+
+    /// Simulates `get_reserve_status` for the beneficiary's first reserve.
+    fn reserve_status(&self, after: Option<i64>) -> ReserveStatus {
+        let reserve = self.reserve_pda();
+        let ix = Instruction {
+            program_id: vesting::ID,
+            accounts: vesting::accounts::GetReserveStatus {
+                reserve_account: reserve,
+                tranche_schedule: self.existing_tranche_pda(&reserve),
+            }
+            .to_account_metas(None),
+            data: vesting::instruction::GetReserveStatus { after }.data(),
+        };
+        self.ctx.simulate(&[ix]).unwrap()
+    }
+
+    fn fetch_vesting_account(&self) -> VestingAccount {
+        let data = self.ctx.account_data(&self.vesting_account);
+        VestingAccount::try_deserialize(&mut data.as_slice()).unwrap()
+    }
+
+    fn create_vesting_account_as(&mut self, signer: Pubkey) -> TransactionResult {
+        let ix = Instruction {
+            program_id: vesting::ID,
+            accounts: vesting::accounts::CreateVestingAccount {
+                signer,
+                config: self.config_pda(),
+                vesting_account: self.vesting_account,
+                mint: self.ctx.mint,
+                treasury_token_account: self.ctx.ata(&self.vesting_account),
+                token_program: spl_token_2022::ID,
+                system_program: system_program::ID,
+                associated_token_program: associated_token::ID,
+            }
+            .to_account_metas(None),
+            data: vesting::instruction::CreateVestingAccount {
+                reserve_type: RESERVE_TYPE.to_string(),
+            }
+            .data(),
+        };
+        self.ctx.send(&[ix], &[])
+    }
+
+    fn create_reserve_as(
+        &mut self,
+        owner: Pubkey,
+        start_time: i64,
+        end_time: i64,
+        cliff_time: i64,
+    ) -> TransactionResult {
+        let beneficiary = self.beneficiary.pubkey();
+        let grant = Grant::monthly(start_time, end_time, cliff_time);
+        self.create_reserve_for(owner, beneficiary, 0, grant)
+    }
+
+    /// Creates reserve `index` of `beneficiary`, funded with `TOTAL`.
+    fn create_reserve_for(
+        &mut self,
+        owner: Pubkey,
+        beneficiary: Pubkey,
+        index: u64,
+        grant: Grant,
+    ) -> TransactionResult {
+        self.create_reserve_with_amounts(owner, beneficiary, index, grant, TOTAL, MONTHLY)
+    }
+
+    /// Like `create_reserve_for`, with the total and per-period amounts given.
+    fn create_reserve_with_amounts(
+        &mut self,
+        owner: Pubkey,
+        beneficiary: Pubkey,
+        index: u64,
+        grant: Grant,
+        total_amount: u64,
+        monthly_claim: u64,
+    ) -> TransactionResult {
+        let ix = Instruction {
+            program_id: vesting::ID,
+            accounts: vesting::accounts::CreateReserveAccount {
+                owner,
+                beneficiary,
+                owner_token_account: self.ctx.ata(&ADMIN),
+                config: self.config_pda(),
+                vesting_account: self.vesting_account,
+                reserve_account: self.reserve_pda_for(&beneficiary, index),
+                treasury_token_account: self.ctx.ata(&self.vesting_account),
+                mint: self.ctx.mint,
+                token_program: spl_token_2022::ID,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: vesting::instruction::CreateReserve {
+                _index: index,
+                params: ReserveParams {
+                    start_time: grant.start_time,
+                    end_time: grant.end_time,
+                    total_amount,
+                    cliff_time: grant.cliff_time,
+                    monthly_claim,
+                    revocable: grant.revocable,
+                    schedule: grant.schedule,
+                    tge_unlock_amount: grant.tge_unlock_amount,
+                    cliff_release_amount: grant.cliff_release_amount,
+                },
+            }
+            .data(),
+        };
+        self.ctx.send(&[ix], &[])
+    }
+
+    /// Creates the vesting account and a 12 x `MONTHLY` reserve for the beneficiary.
+    fn setup_reserve(&mut self, start_time: i64, end_time: i64, cliff_time: i64) {
+        self.ctx.warp_to(start_time - 86_400);
+        self.create_vesting_account_as(ADMIN).unwrap();
+        self.create_reserve_as(ADMIN, start_time, end_time, cliff_time)
+            .unwrap();
+    }
+
+    fn claim(&mut self) -> TransactionResult {
+        let beneficiary = self.beneficiary.insecure_clone();
+        let reserve = self.reserve_pda();
+        self.claim_reserve(&beneficiary, reserve)
+    }
+
+    fn claim_reserve(&mut self, beneficiary: &Keypair, reserve: Pubkey) -> TransactionResult {
+        let ix = Instruction {
+            program_id: vesting::ID,
+            accounts: vesting::accounts::ClaimTokens {
+                beneficiary: beneficiary.pubkey(),
+                reserve_account: reserve,
+                vesting_account: self.vesting_account,
+                mint: self.ctx.mint,
+                treasury_token_account: self.ctx.ata(&self.vesting_account),
+                beneficiary_token_account: self.ctx.ata(&beneficiary.pubkey()),
+                token_program: spl_token_2022::ID,
+                associated_token_program: associated_token::ID,
+                system_program: system_program::ID,
+                tranche_schedule: self.existing_tranche_pda(&reserve),
+            }
+            .to_account_metas(None),
+            data: vesting::instruction::ClaimTokens {
+                _reserve_type: RESERVE_TYPE.to_string(),
+            }
+            .data(),
+        };
+        self.ctx.send(&[ix], &[beneficiary])
+    }
+
+    /// Cranks `reserve`'s claim to `beneficiary`'s associated token account on behalf of `cranker`.
+    fn crank(
+        &mut self,
+        cranker: &Keypair,
+        beneficiary: Pubkey,
+        reserve: Pubkey,
+    ) -> TransactionResult {
+        let tip_vault = self.tip_vault_pda();
+        let ix = Instruction {
+            program_id: vesting::ID,
+            accounts: vesting::accounts::CrankClaim {
+                cranker: cranker.pubkey(),
+                beneficiary,
+                reserve_account: reserve,
+                vesting_account: self.vesting_account,
+                mint: self.ctx.mint,
+                treasury_token_account: self.ctx.ata(&self.vesting_account),
+                beneficiary_token_account: self.ctx.ata(&beneficiary),
+                token_program: spl_token_2022::ID,
+                associated_token_program: associated_token::ID,
+                system_program: system_program::ID,
+                tranche_schedule: self.existing_tranche_pda(&reserve),
+                tip_vault: self.ctx.account_exists(&tip_vault).then_some(tip_vault),
+            }
+            .to_account_metas(None),
+            data: vesting::instruction::CrankClaim {
+                _reserve_type: RESERVE_TYPE.to_string(),
+            }
+            .data(),
+        };
+        self.ctx.send(&[ix], &[cranker])
+    }
+
+    fn transfer_beneficiary_as(
+        &mut self,
+        authority: Pubkey,
+        new_beneficiary: Option<Pubkey>,
+    ) -> TransactionResult {
+        let ix = Instruction {
+            program_id: vesting::ID,
+            accounts: vesting::accounts::TransferBeneficiary {
+                authority,
+                config: self.config_pda(),
+                reserve_account: self.reserve_pda(),
+            }
+            .to_account_metas(None),
+            data: vesting::instruction::TransferBeneficiary { new_beneficiary }.data(),
+        };
+        self.ctx.send(&[ix], &[])
+    }
+
+    fn accept_beneficiary_as(&mut self, pending_beneficiary: Pubkey) -> TransactionResult {
+        let ix = Instruction {
+            program_id: vesting::ID,
+            accounts: vesting::accounts::AcceptBeneficiary {
+                pending_beneficiary,
+                reserve_account: self.reserve_pda(),
+            }
+            .to_account_metas(None),
+            data: vesting::instruction::AcceptBeneficiary {}.data(),
+        };
+        self.ctx.send(&[ix], &[])
+    }
+
+    fn claim_delegate_pda(&self) -> Pubkey {
+        Pubkey::find_program_address(
+            &[b"claim_delegate", self.reserve_pda().as_ref()],
+            &vesting::ID,
+        )
+        .0
+    }
+
+    fn set_claim_delegate_as(
+        &mut self,
+        beneficiary: Pubkey,
+        delegate: Pubkey,
+    ) -> TransactionResult {
+        let ix = Instruction {
+            program_id: vesting::ID,
+            accounts: vesting::accounts::SetClaimDelegate {
+                beneficiary,
+                reserve_account: self.reserve_pda(),
+                claim_delegate: self.claim_delegate_pda(),
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: vesting::instruction::SetClaimDelegate { delegate }.data(),
+        };
+        self.ctx.send(&[ix], &[])
+    }
+
+    fn remove_claim_delegate_as(&mut self, beneficiary: Pubkey) -> TransactionResult {
+        let ix = Instruction {
+            program_id: vesting::ID,
+            accounts: vesting::accounts::RemoveClaimDelegate {
+                beneficiary,
+                reserve_account: self.reserve_pda(),
+                claim_delegate: self.claim_delegate_pda(),
+            }
+            .to_account_metas(None),
+            data: vesting::instruction::RemoveClaimDelegate {}.data(),
+        };
+        self.ctx.send(&[ix], &[])
+    }
+
+    /// Claims the first reserve as `delegate` into `destination`, which should belong to `beneficiary`.
+    fn claim_as_delegate(
+        &mut self,
+        delegate: Pubkey,
+        beneficiary: Pubkey,
+        destination: Pubkey,
+    ) -> TransactionResult {
+        let reserve = self.reserve_pda();
+        let ix = Instruction {
+            program_id: vesting::ID,
+            accounts: vesting::accounts::ClaimAsDelegate {
+                delegate,
+                beneficiary,
+                reserve_account: reserve,
+                claim_delegate: self.claim_delegate_pda(),
+                vesting_account: self.vesting_account,
+                mint: self.ctx.mint,
+                treasury_token_account: self.ctx.ata(&self.vesting_account),
+                beneficiary_token_account: destination,
+                token_program: spl_token_2022::ID,
+                tranche_schedule: self.existing_tranche_pda(&reserve),
+            }
+            .to_account_metas(None),
+            data: vesting::instruction::ClaimAsDelegate {
+                _reserve_type: RESERVE_TYPE.to_string(),
+            }
+            .data(),
+        };
+        self.ctx.send(&[ix], &[])
+    }
+
+    fn position_mint(&self) -> Pubkey {
+        vesting::position_mint_address(&self.reserve_pda())
+    }
+
+    /// `owner`'s associated token account for the first reserve's position NFT.
+    fn position_account(&self, owner: &Pubkey) -> Pubkey {
+        associated_token::get_associated_token_address_with_program_id(
+            owner,
+            &self.position_mint(),
+            &spl_token_2022::ID,
+        )
+    }
+
+    fn tokenize_reserve_as(
+        &mut self,
+        authority: Pubkey,
+        non_transferable: bool,
+    ) -> TransactionResult {
+        let beneficiary = self.beneficiary.pubkey();
+        let ix = Instruction {
+            program_id: vesting::ID,
+            accounts: vesting::accounts::TokenizeReserve {
+                authority,
+                config: self.config_pda(),
+                beneficiary,
+                reserve_account: self.reserve_pda(),
+                position_mint: self.position_mint(),
+                position_token_account: self.position_account(&beneficiary),
+                position_token_program: spl_token_2022::ID,
+                associated_token_program: associated_token::ID,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: vesting::instruction::TokenizeReserve { non_transferable }.data(),
+        };
+        self.ctx.send(&[ix], &[])
+    }
+
+    /// Moves the first reserve's position NFT from `from` to `to` with a Token-2022 transfer.
+    fn transfer_position(&mut self, from: &Keypair, to: Pubkey) -> TransactionResult {
+        let mint = self.position_mint();
+        let destination = self.position_account(&to);
+        let create = associated_token::spl_associated_token_account::instruction::create_associated_token_account(
+            &self.ctx.payer.pubkey(),
+            &to,
+            &mint,
+            &spl_token_2022::ID,
+        );
+        let transfer = spl_token_2022::instruction::transfer_checked(
+            &spl_token_2022::ID,
+            &self.position_account(&from.pubkey()),
+            &mint,
+            &destination,
+            &from.pubkey(),
+            &[],
+            1,
+            0,
+        )
+        .unwrap();
+        self.ctx.send(&[create, transfer], &[from])
+    }
+
+    /// Claims the first reserve for `holder`, presenting their position NFT account.
+    fn claim_position(&mut self, holder: Pubkey) -> TransactionResult {
+        let reserve = self.reserve_pda();
+        let ix = Instruction {
+            program_id: vesting::ID,
+            accounts: vesting::accounts::ClaimPosition {
+                holder,
+                reserve_account: reserve,
+                position_token_account: self.position_account(&holder),
+                vesting_account: self.vesting_account,
+                mint: self.ctx.mint,
+                treasury_token_account: self.ctx.ata(&self.vesting_account),
+                holder_token_account: self.ctx.ata(&holder),
+                token_program: spl_token_2022::ID,
+                tranche_schedule: self.existing_tranche_pda(&reserve),
+            }
+            .to_account_metas(None),
+            data: vesting::instruction::ClaimPosition {
+                _reserve_type: RESERVE_TYPE.to_string(),
+            }
+            .data(),
+        };
+        self.ctx.send(&[ix], &[])
+    }
+
+    fn distributor_pda(&self, index: u64) -> Pubkey {
+        Pubkey::find_program_address(
+            &[
+                b"distributor",
+                self.vesting_account.as_ref(),
+                &index.to_le_bytes(),
+            ],
+            &vesting::ID,
+        )
+        .0
+    }
+
+    fn distributor_claim_pda(&self, index: u64, beneficiary: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(
+            &[
+                b"distributor_claim",
+                self.distributor_pda(index).as_ref(),
+                beneficiary.as_ref(),
+            ],
+            &vesting::ID,
+        )
+        .0
+    }
+
+    fn distributor_reserve_pda(&self, index: u64, beneficiary: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(
+            &[
+                b"reserve",
+                self.distributor_pda(index).as_ref(),
+                beneficiary.as_ref(),
+            ],
+            &vesting::ID,
+        )
+        .0
+    }
+
+    fn fetch_distributor(&self, index: u64) -> Distributor {
+        let data = self.ctx.account_data(&self.distributor_pda(index));
+        Distributor::try_deserialize(&mut data.as_slice()).unwrap()
+    }
+
+    fn create_distributor_as(
+        &mut self,
+        owner: Pubkey,
+        index: u64,
+        root: [u8; 20],
+        total_amount: u64,
+        expiry_time: i64,
+    ) -> TransactionResult {
+        let ix = Instruction {
+            program_id: vesting::ID,
+            accounts: vesting::accounts::CreateDistributor {
+                owner,
+                owner_token_account: self.ctx.ata(&owner),
+                config: self.config_pda(),
+                vesting_account: self.vesting_account,
+                distributor: self.distributor_pda(index),
+                treasury_token_account: self.ctx.ata(&self.vesting_account),
+                mint: self.ctx.mint,
+                token_program: spl_token_2022::ID,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: vesting::instruction::CreateDistributor {
+                _index: index,
+                root,
+                total_amount,
+                expiry_time,
+            }
+            .data(),
+        };
+        self.ctx.send(&[ix], &[])
+    }
+
+    fn claim_from_distributor(
+        &mut self,
+        beneficiary: &Keypair,
+        index: u64,
+        grant: &DistributorGrant,
+        proof: Vec<[u8; 20]>,
+    ) -> TransactionResult {
+        let ix = Instruction {
+            program_id: vesting::ID,
+            accounts: vesting::accounts::ClaimFromDistributor {
+                beneficiary: beneficiary.pubkey(),
+                distributor: self.distributor_pda(index),
+                claim_receipt: self.distributor_claim_pda(index, &beneficiary.pubkey()),
+                reserve_account: self.distributor_reserve_pda(index, &beneficiary.pubkey()),
+                vesting_account: self.vesting_account,
+                mint: self.ctx.mint,
+                treasury_token_account: self.ctx.ata(&self.vesting_account),
+                beneficiary_token_account: self.ctx.ata(&beneficiary.pubkey()),
+                token_program: spl_token_2022::ID,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: vesting::instruction::ClaimFromDistributor {
+                _reserve_type: RESERVE_TYPE.to_string(),
+                grant: grant.clone(),
+                proof,
+            }
+            .data(),
+        };
+        self.ctx.send(&[ix], &[beneficiary])
+    }
+
+    fn clawback_distributor_as(&mut self, owner: Pubkey, index: u64) -> TransactionResult {
+        let ix = Instruction {
+            program_id: vesting::ID,
+            accounts: vesting::accounts::ClawbackDistributor {
+                owner,
+                config: self.config_pda(),
+                distributor: self.distributor_pda(index),
+                vesting_account: self.vesting_account,
+                mint: self.ctx.mint,
+                treasury_token_account: self.ctx.ata(&self.vesting_account),
+                owner_token_account: self.ctx.ata(&owner),
+                token_program: spl_token_2022::ID,
+            }
+            .to_account_metas(None),
+            data: vesting::instruction::ClawbackDistributor {}.data(),
+        };
+        self.ctx.send(&[ix], &[])
+    }
+
+    fn tip_vault_pda(&self) -> Pubkey {
+        Pubkey::find_program_address(&[b"tip_vault", self.vesting_account.as_ref()], &vesting::ID).0
+    }
+
+    fn fetch_tip_vault(&self) -> TipVault {
+        let data = self.ctx.account_data(&self.tip_vault_pda());
+        TipVault::try_deserialize(&mut data.as_slice()).unwrap()
+    }
+
+    fn configure_tip_vault_as(&mut self, admin: Pubkey, tip_lamports: u64) -> TransactionResult {
+        let ix = Instruction {
+            program_id: vesting::ID,
+            accounts: vesting::accounts::ConfigureTipVault {
+                admin,
+                config: self.config_pda(),
+                vesting_account: self.vesting_account,
+                tip_vault: self.tip_vault_pda(),
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: vesting::instruction::ConfigureTipVault { tip_lamports }.data(),
+        };
+        self.ctx.send(&[ix], &[])
+    }
+
+    fn fund_tip_vault(&mut self, lamports: u64) -> TransactionResult {
+        let ix = Instruction {
+            program_id: vesting::ID,
+            accounts: vesting::accounts::FundTipVault {
+                funder: self.ctx.payer.pubkey(),
+                tip_vault: self.tip_vault_pda(),
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: vesting::instruction::FundTipVault { lamports }.data(),
+        };
+        self.ctx.send(&[ix], &[])
+    }
+
+    fn withdraw_tips_as(&mut self, admin: Pubkey, lamports: u64) -> TransactionResult {
+        let ix = Instruction {
+            program_id: vesting::ID,
+            accounts: vesting::accounts::WithdrawTips {
+                admin,
+                config: self.config_pda(),
+                tip_vault: self.tip_vault_pda(),
+            }
+            .to_account_metas(None),
+            data: vesting::instruction::WithdrawTips { lamports }.data(),
+        };
+        self.ctx.send(&[ix], &[])
+    }
+
+    fn tranche_pda(&self, reserve: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(&[b"tranches", reserve.as_ref()], &vesting::ID).0
+    }
+
+    /// The reserve's tranche schedule, if it has one.
+    fn existing_tranche_pda(&self, reserve: &Pubkey) -> Option<Pubkey> {
+        let address = self.tranche_pda(reserve);
+        self.ctx.account_exists(&address).then_some(address)
+    }
+
+    fn initialize_tranches(&mut self, tranches: Vec<Tranche>) -> TransactionResult {
+        let reserve = self.reserve_pda();
+        let ix = Instruction {
+            program_id: vesting::ID,
+            accounts: vesting::accounts::InitializeTranches {
+                owner: ADMIN,
+                config: self.config_pda(),
+                reserve_account: reserve,
+                tranche_schedule: self.tranche_pda(&reserve),
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: vesting::instruction::InitializeTranches { tranches }.data(),
+        };
+        self.ctx.send(&[ix], &[])
+    }
+
+    fn update_tranches(&mut self, tranches: Vec<Tranche>) -> TransactionResult {
+        let reserve = self.reserve_pda();
+        let ix = Instruction {
+            program_id: vesting::ID,
+            accounts: vesting::accounts::UpdateTranches {
+                owner: ADMIN,
+                config: self.config_pda(),
+                reserve_account: reserve,
+                tranche_schedule: self.tranche_pda(&reserve),
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: vesting::instruction::UpdateTranches { tranches }.data(),
+        };
+        self.ctx.send(&[ix], &[])
+    }
+
+    fn fetch_tranches(&self) -> TrancheSchedule {
+        let data = self
+            .ctx
+            .account_data(&self.tranche_pda(&self.reserve_pda()));
+        TrancheSchedule::try_deserialize(&mut data.as_slice()).unwrap()
+    }
+
+    fn migrate_reserve(&mut self, reserve: Pubkey) -> TransactionResult {
+        let ix = Instruction {
+            program_id: vesting::ID,
+            accounts: vesting::accounts::MigrateReserve {
+                payer: self.ctx.payer.pubkey(),
+                reserve_account: reserve,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: vesting::instruction::MigrateReserve {}.data(),
+        };
+        self.ctx.send(&[ix], &[])
+    }
+
+    fn revoke_as(&mut self, owner: Pubkey) -> TransactionResult {
+        let beneficiary = self.beneficiary.pubkey();
+        self.revoke_to(owner, beneficiary, None)
+    }
+
+    /// Revokes the first reserve, settling vested tokens with `recipient`, who must hold the
+    /// `position` NFT account if the reserve is tokenized.
+    fn revoke_to(
+        &mut self,
+        owner: Pubkey,
+        recipient: Pubkey,
+        position: Option<Pubkey>,
+    ) -> TransactionResult {
+        let ix = Instruction {
+            program_id: vesting::ID,
+            accounts: vesting::accounts::RevokeReserve {
+                owner,
+                config: self.config_pda(),
+                reserve_account: self.reserve_pda(),
+                vesting_account: self.vesting_account,
+                mint: self.ctx.mint,
+                treasury_token_account: self.ctx.ata(&self.vesting_account),
+                beneficiary_token_account: self.ctx.ata(&recipient),
+                owner_token_account: self.ctx.ata(&owner),
+                token_program: spl_token_2022::ID,
+                tranche_schedule: self.existing_tranche_pda(&self.reserve_pda()),
+                position_token_account: position,
+            }
+            .to_account_metas(None),
+            data: vesting::instruction::RevokeReserve {}.data(),
+        };
+        self.ctx.send(&[ix], &[])
+    }
+
+    fn migrate_vesting_account(&mut self, legacy_reserve: Option<Pubkey>) -> TransactionResult {
+        let ix = Instruction {
+            program_id: vesting::ID,
+            accounts: vesting::accounts::MigrateVestingAccount {
+                admin: ADMIN,
+                config: self.config_pda(),
+                vesting_account: self.vesting_account,
+                legacy_reserve,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: vesting::instruction::MigrateVestingAccount {}.data(),
+        };
+        self.ctx.send(&[ix], &[])
+    }
+
+    /// Writes a pool and reserve in the layout used before a pool could hold several reserves.
+    fn setup_legacy_reserve(&mut self, start_time: i64, end_time: i64, amount_withdrawn: u64) {
+        let bump = Pubkey::find_program_address(&[RESERVE_TYPE.as_bytes()], &vesting::ID).1;
+        let treasury = self
+            .ctx
+            .create_token_account(&self.vesting_account, TOTAL - amount_withdrawn);
+        let mut data = VestingAccount::DISCRIMINATOR.to_vec();
+        (
+            ADMIN,
+            self.ctx.mint,
+            treasury,
+            RESERVE_TYPE.to_string(),
+            bump,
+        )
+            .serialize(&mut data)
+            .unwrap();
+        data.resize(8 + 32 * 3 + 4 + 50 + 1, 0);
+        self.ctx
+            .set_account(self.vesting_account, vesting::ID, data);
+
+        let (reserve_address, reserve_bump) = Pubkey::find_program_address(
+            &[b"reserve", self.vesting_account.as_ref()],
+            &vesting::ID,
+        );
+        let mut data = ReserveAccount::DISCRIMINATOR.to_vec();
+        (
+            self.beneficiary.pubkey(),
+            start_time,
+            end_time,
+            TOTAL,
+            amount_withdrawn,
+            0i64, // cliff_time
+            MONTHLY,
+            self.vesting_account,
+            reserve_bump,
+        )
+            .serialize(&mut data)
+            .unwrap();
+        assert_eq!(data.len(), vesting::LEGACY_RESERVE_LEN);
+        self.ctx.set_account(reserve_address, vesting::ID, data);
+    }
+
+    /// Claims at `unix_timestamp` and returns the amount received.
+    fn claim_at(&mut self, unix_timestamp: i64) -> std::result::Result<u64, TransactionError> {
+        self.ctx.warp_to(unix_timestamp);
+        let before = self.ctx.token_balance(&self.beneficiary.pubkey());
+        self.claim().map_err(|e| e.err)?;
+        Ok(self.ctx.token_balance(&self.beneficiary.pubkey()) - before)
+    }
+
+    fn close_reserve(&mut self) -> TransactionResult {
+        let beneficiary = self.beneficiary.insecure_clone();
+        let reserve = self.reserve_pda();
+        self.close_reserve_at(&beneficiary, reserve)
+    }
+
+    fn close_reserve_at(&mut self, beneficiary: &Keypair, reserve: Pubkey) -> TransactionResult {
+        let ix = Instruction {
+            program_id: vesting::ID,
+            accounts: vesting::accounts::CloseReserveAccount {
+                reserve_account: reserve,
+                vesting_account: self.vesting_account,
+                beneficiary: beneficiary.pubkey(),
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: vesting::instruction::CloseReserveAccount {}.data(),
+        };
+        self.ctx.send(&[ix], &[beneficiary])
+    }
+
+    fn close_vesting_account_as(&mut self, owner: Pubkey) -> TransactionResult {
+        let ix = Instruction {
+            program_id: vesting::ID,
+            accounts: vesting::accounts::CloseVestingAccount {
+                owner,
+                config: self.config_pda(),
+                vesting_account: self.vesting_account,
+                treasury_token_account: self.ctx.ata(&self.vesting_account),
+                owner_token_account: self.ctx.ata(&owner),
+                mint: self.ctx.mint,
+                token_program: spl_token_2022::ID,
+            }
+            .to_account_metas(None),
+            data: vesting::instruction::CloseVestingAccount {}.data(),
+        };
+        self.ctx.send(&[ix], &[])
+    }
+}
+
+#[test]
+fn create_vesting_account_rejects_non_admin() {
+    let mut test = VestingTest::new();
+    let intruder = test.ctx.wallet(1);
+
+    assert_error(
+        test.create_vesting_account_as(intruder.pubkey()),
+        VestingError::AccessDenied,
+    );
+}
+
+#[test]
+fn initialize_config_rejects_non_deployer() {
+    let mut test = VestingTest::without_config();
+    let intruder = test.ctx.wallet(1);
+
+    assert_error(
+        test.initialize_config_as(intruder.pubkey(), intruder.pubkey()),
+        VestingError::AccessDenied,
+    );
+    test.initialize_config_as(ADMIN, intruder.pubkey()).unwrap();
+    assert_eq!(test.fetch_config().admin, intruder.pubkey());
+}
+
+#[test]
+fn admin_handover_takes_effect_once_accepted() {
+    let mut test = VestingTest::new();
+    let new_admin = test.ctx.wallet(10);
+    let intruder = test.ctx.wallet(1);
+
+    assert_error(
+        test.propose_admin_as(intruder.pubkey(), Some(intruder.pubkey())),
+        VestingError::AccessDenied,
+    );
+    test.propose_admin_as(ADMIN, Some(new_admin.pubkey()))
+        .unwrap();
+    assert_error(
+        test.create_vesting_account_as(new_admin.pubkey()),
+        VestingError::AccessDenied,
+    );
+    assert_error(
+        test.accept_admin_as(intruder.pubkey()),
+        VestingError::AccessDenied,
+    );
+
+    test.accept_admin_as(new_admin.pubkey()).unwrap();
+    let config = test.fetch_config();
+    assert_eq!(config.admin, new_admin.pubkey());
+    assert_eq!(config.pending_admin, None);
+    assert_error(
+        test.create_vesting_account_as(ADMIN),
+        VestingError::AccessDenied,
+    );
+    test.create_vesting_account_as(new_admin.pubkey()).unwrap();
+}
+
+#[test]
+fn admin_handover_can_be_cancelled() {
+    let mut test = VestingTest::new();
+    let new_admin = test.ctx.wallet(1);
+
+    test.propose_admin_as(ADMIN, Some(new_admin.pubkey()))
+        .unwrap();
+    test.propose_admin_as(ADMIN, None).unwrap();
+
+    assert_error(
+        test.accept_admin_as(new_admin.pubkey()),
+        VestingError::AccessDenied,
+    );
+    assert_eq!(test.fetch_config().admin, ADMIN);
+}
+
+#[test]
+fn create_reserve_rejects_non_admin() {
+    let mut test = VestingTest::new();
+    let start = timestamp(2025, 1, 1, 0, 0, 0);
+    test.ctx.warp_to(start);
+    test.create_vesting_account_as(ADMIN).unwrap();
+
+    let intruder = test.ctx.wallet(1);
+    assert_error(
+        test.create_reserve_as(intruder.pubkey(), start, start + 365 * 86_400, 0),
+        VestingError::AccessDenied,
+    );
+}
+
+#[test]
+fn create_reserve_locks_the_total_in_the_treasury() {
+    let mut test = VestingTest::new();
+    let start = timestamp(2025, 1, 1, 0, 0, 0);
+    test.setup_reserve(start, timestamp(2026, 1, 1, 0, 0, 0), 0);
+
+    assert_eq!(test.ctx.token_balance(&test.vesting_account), TOTAL);
+    let reserve = test.fetch_reserve();
+    assert_eq!(reserve.beneficiary, test.beneficiary.pubkey());
+    assert_eq!(reserve.total_amount, TOTAL);
+    assert_eq!(reserve.amount_withdrawn, 0);
+}
+
+#[test]
+fn a_pool_holds_several_reserves() {
+    let mut test = VestingTest::new();
+    let start = timestamp(2025, 1, 1, 0, 0, 0);
+    let end = timestamp(2026, 1, 1, 0, 0, 0);
+    test.setup_reserve(start, end, 0);
+    let grant = Grant::monthly(start, end, 0);
+    let beneficiary = test.beneficiary.pubkey();
+    let advisor = test.ctx.wallet(1);
+    test.ctx.create_token_account(&advisor.pubkey(), 0);
+
+    test.create_reserve_for(ADMIN, beneficiary, 1, grant)
+        .unwrap();
+    test.create_reserve_for(ADMIN, advisor.pubkey(), 0, grant)
+        .unwrap();
+    assert_error(
+        test.create_reserve_for(ADMIN, beneficiary, 1, grant),
+        ACCOUNT_ALREADY_IN_USE,
+    );
+
+    assert_eq!(test.ctx.token_balance(&test.vesting_account), 3 * TOTAL);
+    let pool = test.fetch_vesting_account();
+    assert_eq!(pool.total_allocated, 3 * TOTAL);
+    assert_eq!(pool.reserve_count, 3);
+
+    test.ctx.warp_to(start);
+    let advisor_reserve = test.reserve_pda_for(&advisor.pubkey(), 0);
+    test.claim_reserve(&advisor, advisor_reserve).unwrap();
+    assert_eq!(test.ctx.token_balance(&advisor.pubkey()), MONTHLY);
+    assert_eq!(test.fetch_reserve().amount_withdrawn, 0);
+    assert_eq!(test.fetch_vesting_account().total_withdrawn, MONTHLY);
+
+    // Reserves can only be claimed by their own beneficiary.
+    let beneficiary_keypair = test.beneficiary.insecure_clone();
+    assert_error(
+        test.claim_reserve(&beneficiary_keypair, advisor_reserve),
+        anchor_lang::error::ErrorCode::ConstraintHasOne,
+    );
+}
+
+#[test]
+fn legacy_pools_are_migrated_from_their_reserve() {
+    let mut test = VestingTest::new();
+    let start = timestamp(2025, 1, 1, 0, 0, 0);
+    let end = timestamp(2026, 1, 1, 0, 0, 0);
+    test.ctx.warp_to(start);
+    test.setup_legacy_reserve(start, end, MONTHLY);
+    let advisor = Pubkey::new_unique();
+
+    assert_error(
+        test.create_reserve_for(ADMIN, advisor, 0, Grant::monthly(start, end, 0)),
+        VestingError::NotMigrated,
+    );
+    let legacy_reserve = test.legacy_reserve_pda();
+    test.migrate_vesting_account(Some(legacy_reserve)).unwrap();
+    assert_error(
+        test.migrate_vesting_account(Some(legacy_reserve)),
+        VestingError::AlreadyMigrated,
+    );
+
+    let pool = test.fetch_vesting_account();
+    assert_eq!(pool.version, vesting::VESTING_ACCOUNT_VERSION);
+    assert_eq!(pool.reserve_type, RESERVE_TYPE);
+    assert_eq!(pool.total_allocated, TOTAL);
+    assert_eq!(pool.total_withdrawn, MONTHLY);
+    assert_eq!(pool.reserve_count, 1);
+
+    // The legacy reserve keeps vesting at its old address once its layout is migrated.
+    test.ctx.warp_to(timestamp(2025, 2, 1, 0, 0, 0));
+    let beneficiary = test.beneficiary.insecure_clone();
+    assert_error(
+        test.claim_reserve(&beneficiary, legacy_reserve),
+        anchor_lang::error::ErrorCode::AccountDidNotDeserialize,
+    );
+    test.migrate_reserve(legacy_reserve).unwrap();
+    assert_error(
+        test.migrate_reserve(legacy_reserve),
+        VestingError::AlreadyMigrated,
+    );
+    test.claim_reserve(&beneficiary, legacy_reserve).unwrap();
+    assert_eq!(test.ctx.token_balance(&beneficiary.pubkey()), MONTHLY);
+
+    test.create_reserve_for(ADMIN, advisor, 0, Grant::monthly(start, end, 0))
+        .unwrap();
+    let pool = test.fetch_vesting_account();
+    assert_eq!(pool.total_allocated, 2 * TOTAL);
+    assert_eq!(pool.total_withdrawn, 2 * MONTHLY);
+    assert_eq!(pool.reserve_count, 2);
+}
+
+#[test]
+fn revoke_settles_vested_tokens_and_claws_back_the_rest() {
+    let mut test = VestingTest::new();
+    let start = timestamp(2025, 1, 1, 0, 0, 0);
+    test.ctx.warp_to(start);
+    test.create_vesting_account_as(ADMIN).unwrap();
+    let beneficiary = test.beneficiary.pubkey();
+    let grant = Grant {
+        revocable: true,
+        ..Grant::monthly(start, timestamp(2026, 1, 1, 0, 0, 0), 0)
+    };
+    test.create_reserve_for(ADMIN, beneficiary, 0, grant)
+        .unwrap();
+    assert_eq!(test.claim_at(start).unwrap(), MONTHLY);
+
+    // Three months have vested by mid-March; one was already claimed.
+    test.ctx.warp_to(timestamp(2025, 3, 15, 0, 0, 0));
+    let admin_before = test.ctx.token_balance(&ADMIN);
+    test.revoke_as(ADMIN).unwrap();
+
+    assert_eq!(test.ctx.token_balance(&beneficiary), 3 * MONTHLY);
+    assert_eq!(test.ctx.token_balance(&ADMIN) - admin_before, 9 * MONTHLY);
+    assert_eq!(test.ctx.token_balance(&test.vesting_account), 0);
+    let reserve = test.fetch_reserve();
+    assert!(reserve.terminated);
+    assert_eq!(reserve.amount_withdrawn, 3 * MONTHLY);
+    let pool = test.fetch_vesting_account();
+    assert_eq!(pool.total_allocated, 3 * MONTHLY);
+    assert_eq!(pool.total_withdrawn, 3 * MONTHLY);
+
+    assert_eq!(
+        test.claim_at(timestamp(2025, 6, 1, 0, 0, 0)).unwrap_err(),
+        custom_error(VestingError::ReserveTerminated)
+    );
+    assert_error(test.revoke_as(ADMIN), VestingError::ReserveTerminated);
+    test.close_reserve().unwrap();
+}
+
+#[test]
+fn revoke_requires_the_admin_and_a_revocable_reserve() {
+    let mut test = VestingTest::new();
+    let start = timestamp(2025, 1, 1, 0, 0, 0);
+    test.setup_reserve(start, timestamp(2026, 1, 1, 0, 0, 0), 0);
+    let intruder = test.ctx.wallet(1);
+    test.ctx.create_token_account(&intruder.pubkey(), 0);
+
+    assert_error(
+        test.revoke_as(intruder.pubkey()),
+        VestingError::AccessDenied,
+    );
+    assert_error(test.revoke_as(ADMIN), VestingError::NotRevocable);
+}
+
+#[test]
+fn claim_fails_until_the_cliff_has_passed() {
+    let mut test = VestingTest::new();
+    let start = timestamp(2025, 1, 1, 0, 0, 0);
+    let cliff = 90 * 86_400;
+    test.setup_reserve(start, timestamp(2026, 6, 1, 0, 0, 0), cliff);
+
+    assert_eq!(
+        test.claim_at(start).unwrap_err(),
+        custom_error(VestingError::CliffPeriodNotEnded)
+    );
+    assert_eq!(
+        test.claim_at(start + cliff - 1).unwrap_err(),
+        custom_error(VestingError::CliffPeriodNotEnded)
+    );
+
+    // The first period unlocks as soon as the cliff ends.
+    assert_eq!(test.claim_at(start + cliff).unwrap(), MONTHLY);
+}
+
+#[test]
+fn claims_follow_calendar_months() {
+    let mut test = VestingTest::new();
+    // Starting on the 31st: shorter months must not unlock early.
+    let start = timestamp(2025, 1, 31, 12, 0, 0);
+    test.setup_reserve(start, timestamp(2026, 1, 31, 12, 0, 0), 0);
+
+    assert_eq!(test.claim_at(start).unwrap(), MONTHLY);
+
+    // February has no 31st, so nothing more unlocks during it.
+    assert_eq!(
+        test.claim_at(timestamp(2025, 2, 28, 23, 59, 59))
+            .unwrap_err(),
+        custom_error(VestingError::ClaimNotAvailableYet)
+    );
+
+    // A month has elapsed once the calendar day is reached again.
+    assert_eq!(
+        test.claim_at(timestamp(2025, 3, 1, 0, 0, 0)).unwrap(),
+        MONTHLY
+    );
+
+    // Same day but before the start time of day is still the previous month.
+    assert_eq!(
+        test.claim_at(timestamp(2025, 3, 31, 11, 59, 59))
+            .unwrap_err(),
+        custom_error(VestingError::ClaimNotAvailableYet)
+    );
+    assert_eq!(
+        test.claim_at(timestamp(2025, 3, 31, 12, 0, 0)).unwrap(),
+        MONTHLY
+    );
+
+    // Skipped months accumulate.
+    assert_eq!(
+        test.claim_at(timestamp(2025, 7, 31, 12, 0, 0)).unwrap(),
+        4 * MONTHLY
+    );
+    assert_eq!(test.fetch_reserve().amount_withdrawn, 7 * MONTHLY);
+}
+
+#[test]
+fn claims_roll_over_year_boundaries() {
+    let mut test = VestingTest::new();
+    let start = timestamp(2024, 11, 15, 0, 0, 0);
+    test.setup_reserve(start, timestamp(2025, 11, 15, 0, 0, 0), 0);
+
+    assert_eq!(
+        test.claim_at(timestamp(2025, 1, 14, 23, 59, 59)).unwrap(),
+        2 * MONTHLY
+    );
+    assert_eq!(
+        test.claim_at(timestamp(2025, 1, 15, 0, 0, 0)).unwrap(),
+        MONTHLY
+    );
+}
+
+#[test]
+fn claims_are_capped_at_the_total_amount() {
+    let mut test = VestingTest::new();
+    let start = timestamp(2025, 1, 1, 0, 0, 0);
+    test.setup_reserve(start, timestamp(2026, 1, 1, 0, 0, 0), 0);
+
+    assert_eq!(
+        test.claim_at(timestamp(2027, 1, 1, 0, 0, 0)).unwrap(),
+        TOTAL
+    );
+    assert_eq!(
+        test.claim_at(timestamp(2027, 6, 1, 0, 0, 0)).unwrap_err(),
+        custom_error(VestingError::ClaimNotAvailableYet)
+    );
+    assert_eq!(test.ctx.token_balance(&test.vesting_account), 0);
+}
+
+#[test]
+fn close_reserve_requires_the_vesting_to_be_over_and_claimed() {
+    let mut test = VestingTest::new();
+    let start = timestamp(2025, 1, 1, 0, 0, 0);
+    let end = timestamp(2026, 1, 1, 0, 0, 0);
+    test.setup_reserve(start, end, 0);
+
+    test.ctx.warp_to(end - 1);
+    assert_error(test.close_reserve(), VestingError::VestingNotOver);
+
+    test.ctx.warp_to(end);
+    assert_error(test.close_reserve(), VestingError::FundsRemaining);
+
+    test.claim_at(end).unwrap();
+    test.close_reserve().unwrap();
+    assert!(!test.ctx.account_exists(&test.reserve_pda()));
+}
+
+#[test]
+fn close_vesting_account_requires_an_empty_treasury() {
+    let mut test = VestingTest::new();
+    let start = timestamp(2025, 1, 1, 0, 0, 0);
+    let end = timestamp(2026, 1, 1, 0, 0, 0);
+    test.setup_reserve(start, end, 0);
+
+    assert_error(
+        test.close_vesting_account_as(ADMIN),
+        VestingError::FundsRemaining,
+    );
+
+    test.claim_at(end).unwrap();
+    test.close_vesting_account_as(ADMIN).unwrap();
+}
+
+#[test]
+fn close_vesting_account_follows_an_admin_handover() {
+    let mut test = VestingTest::new();
+    let start = timestamp(2025, 1, 1, 0, 0, 0);
+    let end = timestamp(2026, 1, 1, 0, 0, 0);
+    test.setup_reserve(start, end, 0);
+    test.claim_at(end).unwrap();
+
+    let new_admin = test.ctx.wallet(10);
+    test.ctx.create_token_account(&new_admin.pubkey(), 0);
+    test.propose_admin_as(ADMIN, Some(new_admin.pubkey()))
+        .unwrap();
+    test.accept_admin_as(new_admin.pubkey()).unwrap();
+
+    assert_error(
+        test.close_vesting_account_as(ADMIN),
+        VestingError::AccessDenied,
+    );
+    test.close_vesting_account_as(new_admin.pubkey()).unwrap();
+    assert!(!test.ctx.account_exists(&test.vesting_account));
+}
+
+#[test]
+fn linear_reserves_stream_per_second() {
+    let mut test = VestingTest::new();
+    let start = timestamp(2025, 1, 1, 0, 0, 0);
+    let cliff = 30 * 86_400;
+    let end = start + cliff + 1_000_000;
+    test.ctx.warp_to(start);
+    test.create_vesting_account_as(ADMIN).unwrap();
+    let beneficiary = test.beneficiary.pubkey();
+    let grant = Grant {
+        schedule: ScheduleKind::Linear,
+        ..Grant::monthly(start, end, cliff)
+    };
+    test.create_reserve_for(ADMIN, beneficiary, 0, grant)
+        .unwrap();
+
+    assert_eq!(
+        test.claim_at(start + cliff).unwrap_err(),
+        custom_error(VestingError::ClaimNotAvailableYet)
+    );
+    assert_eq!(test.claim_at(start + cliff + 250_000).unwrap(), TOTAL / 4);
+    assert_eq!(test.claim_at(start + cliff + 250_001).unwrap(), 1_200);
+    assert_eq!(test.claim_at(end + 1).unwrap(), TOTAL * 3 / 4 - 1_200);
+    assert_eq!(test.fetch_reserve().amount_withdrawn, TOTAL);
+}
+
+#[test]
+fn linear_reserves_need_time_after_the_cliff() {
+    let mut test = VestingTest::new();
+    let start = timestamp(2025, 1, 1, 0, 0, 0);
+    test.ctx.warp_to(start);
+    test.create_vesting_account_as(ADMIN).unwrap();
+    let beneficiary = test.beneficiary.pubkey();
+    let grant = Grant {
+        schedule: ScheduleKind::Linear,
+        ..Grant::monthly(start, start + 86_400, 86_400)
+    };
+
+    assert_error(
+        test.create_reserve_for(ADMIN, beneficiary, 0, grant),
+        VestingError::InvalidSchedule,
+    );
+}
+
+#[test]
+fn periodic_schedules_release_one_amount_per_started_period() {
+    let start = timestamp(2025, 1, 31, 12, 0, 0);
+    let end = timestamp(2030, 1, 1, 0, 0, 0);
+    let vested =
+        |schedule, now| vesting::vested_amount(schedule, start, end, 1_000, 10, now).unwrap();
+
+    assert_eq!(vested(ScheduleKind::Daily, start - 1), 0);
+    assert_eq!(vested(ScheduleKind::Daily, start), 10);
+    assert_eq!(vested(ScheduleKind::Daily, start + 86_399), 10);
+    assert_eq!(vested(ScheduleKind::Daily, start + 86_400), 20);
+    assert_eq!(vested(ScheduleKind::Weekly, start + 6 * 86_400), 10);
+    assert_eq!(vested(ScheduleKind::Weekly, start + 7 * 86_400), 20);
+    assert_eq!(
+        vested(ScheduleKind::Monthly, timestamp(2025, 3, 31, 12, 0, 0)),
+        30
+    );
+    assert_eq!(
+        vested(ScheduleKind::Quarterly, timestamp(2025, 4, 30, 12, 0, 0)),
+        10
+    );
+    assert_eq!(
+        vested(
+            ScheduleKind::Quarterly,
+            timestamp(2025, 4, 30, 12, 0, 0) + 86_400
+        ),
+        20
+    );
+    assert_eq!(vested(ScheduleKind::Daily, start + 1_000 * 86_400), 1_000);
+
+    let next = |schedule, now| vesting::next_unlock_time(schedule, start, now).unwrap();
+    assert_eq!(next(ScheduleKind::Daily, start + 10), start + 86_400);
+    assert_eq!(next(ScheduleKind::Weekly, start + 10), start + 7 * 86_400);
+    // Months without a 31st complete at the start of the following month.
+    assert_eq!(
+        next(ScheduleKind::Monthly, start),
+        timestamp(2025, 3, 1, 0, 0, 0)
+    );
+    assert_eq!(
+        next(ScheduleKind::Monthly, timestamp(2025, 3, 1, 0, 0, 0)),
+        timestamp(2025, 3, 31, 12, 0, 0)
+    );
+    assert_eq!(
+        next(ScheduleKind::Quarterly, start),
+        timestamp(2025, 5, 1, 0, 0, 0)
+    );
+    assert_eq!(next(ScheduleKind::Linear, start + 10), start + 11);
+}
+
+#[test]
+fn reserve_status_reports_claimable_amounts_and_upcoming_unlocks() {
+    let mut test = VestingTest::new();
+    let start = timestamp(2025, 1, 31, 12, 0, 0);
+    test.setup_reserve(start, timestamp(2026, 1, 31, 12, 0, 0), 0);
+    let mid_february = timestamp(2025, 2, 15, 0, 0, 0);
+    test.ctx.warp_to(mid_february);
+
+    let status = test.reserve_status(None);
+    assert_eq!(status.now, mid_february);
+    assert_eq!(status.vested_amount, MONTHLY);
+    assert_eq!(status.claimable_amount, MONTHLY);
+    assert_eq!(
+        status.next_unlock_time,
+        Some(timestamp(2025, 3, 1, 0, 0, 0))
+    );
+    assert!(!status.truncated);
+    assert_eq!(status.remaining_schedule.len(), 11);
+    assert_eq!(
+        status.remaining_schedule[..2],
+        [
+            tranche(timestamp(2025, 3, 1, 0, 0, 0), 2 * MONTHLY),
+            tranche(timestamp(2025, 3, 31, 12, 0, 0), 3 * MONTHLY),
+        ]
+    );
+    assert_eq!(
+        status.remaining_schedule.last(),
+        Some(&tranche(timestamp(2025, 12, 31, 12, 0, 0), TOTAL))
+    );
+
+    assert_eq!(test.claim_at(mid_february).unwrap(), MONTHLY);
+    // Every projected unlock is exactly when a claim starts paying out.
+    for unlock in &status.remaining_schedule[..3] {
+        assert_eq!(
+            test.claim_at(unlock.unlock_time - 1).unwrap_err(),
+            custom_error(VestingError::ClaimNotAvailableYet)
+        );
+        assert_eq!(test.claim_at(unlock.unlock_time).unwrap(), MONTHLY);
+    }
+    let status = test.reserve_status(Some(timestamp(2025, 10, 15, 0, 0, 0)));
+    assert_eq!(status.vested_amount, 4 * MONTHLY);
+    assert_eq!(status.claimable_amount, 0);
+    assert_eq!(
+        status.remaining_schedule,
+        [
+            tranche(timestamp(2025, 10, 31, 12, 0, 0), 10 * MONTHLY),
+            tranche(timestamp(2025, 12, 1, 0, 0, 0), 11 * MONTHLY),
+            tranche(timestamp(2025, 12, 31, 12, 0, 0), TOTAL),
+        ]
+    );
+
+    test.claim_at(timestamp(2026, 1, 1, 0, 0, 0)).unwrap();
+    let status = test.reserve_status(None);
+    assert_eq!(status.next_unlock_time, None);
+    assert!(status.remaining_schedule.is_empty());
+}
+
+#[test]
+fn reserve_status_pages_through_long_schedules() {
+    let mut test = VestingTest::new();
+    let start = timestamp(2025, 1, 1, 0, 0, 0);
+    test.ctx.warp_to(start);
+    test.create_vesting_account_as(ADMIN).unwrap();
+    let beneficiary = test.beneficiary.pubkey();
+    let grant = Grant {
+        schedule: ScheduleKind::Daily,
+        ..Grant::monthly(start, timestamp(2026, 1, 1, 0, 0, 0), 0)
+    };
+    test.create_reserve_with_amounts(ADMIN, beneficiary, 0, grant, TOTAL, TOTAL / 100)
+        .unwrap();
+
+    let status = test.reserve_status(None);
+    assert!(status.truncated);
+    assert_eq!(
+        status.remaining_schedule.len(),
+        vesting::MAX_PROJECTED_UNLOCKS
+    );
+    let first_page_end = status.remaining_schedule.last().unwrap().unlock_time;
+    assert_eq!(first_page_end, start + 48 * SECONDS_PER_DAY);
+
+    let mut unlocks = status.remaining_schedule;
+    let mut truncated = status.truncated;
+    while truncated {
+        let status = test.reserve_status(unlocks.last().map(|unlock| unlock.unlock_time));
+        truncated = status.truncated;
+        unlocks.extend(status.remaining_schedule);
+    }
+    assert_eq!(unlocks.len(), 99);
+    assert_eq!(
+        unlocks.last(),
+        Some(&tranche(start + 99 * SECONDS_PER_DAY, TOTAL))
+    );
+}
+
+#[test]
+fn sdk_projects_upfront_and_linear_unlocks() {
+    let start = timestamp(2025, 1, 1, 0, 0, 0);
+    let cliff = 90 * SECONDS_PER_DAY;
+    let end = start + 365 * SECONDS_PER_DAY;
+    let reserve = ReserveAccount {
+        beneficiary: Pubkey::new_unique(),
+        start_time: start,
+        end_time: end,
+        total_amount: TOTAL,
+        amount_withdrawn: 0,
+        cliff_time: cliff,
+        monthly_claim: 0,
+        vesting_account: Pubkey::new_unique(),
+        bump: 255,
+        version: vesting::RESERVE_VERSION,
+        revocable: false,
+        terminated: false,
+        schedule: ScheduleKind::Linear,
+        tge_unlock_amount: MONTHLY,
+        cliff_release_amount: 2 * MONTHLY,
+        last_crank_tip_time: 0,
+        pending_beneficiary: None,
+        tokenized: false,
+        reserved: [0; 5],
+    };
+
+    assert_eq!(
+        sdk::remaining_schedule(&reserve, None, start - 1, 10).unwrap(),
+        [
+            tranche(start, MONTHLY),
+            tranche(start + cliff, 3 * MONTHLY),
+            tranche(end, TOTAL),
+        ]
+    );
+    assert_eq!(
+        sdk::next_unlock_time(&reserve, None, start + cliff).unwrap(),
+        Some(start + cliff + 1)
+    );
+    assert_eq!(
+        sdk::claimable_amount(&reserve, None, start + cliff).unwrap(),
+        3 * MONTHLY
+    );
+
+    let status = sdk::reserve_status(&reserve, None, start + cliff, None, 1).unwrap();
+    assert_eq!(status.remaining_schedule, [tranche(end, TOTAL)]);
+    assert!(!status.truncated);
+
+    let revoked = ReserveAccount {
+        terminated: true,
+        amount_withdrawn: 3 * MONTHLY,
+        ..reserve
+    };
+    let status = sdk::reserve_status(&revoked, None, end, None, 10).unwrap();
+    assert_eq!(status.vested_amount, 3 * MONTHLY);
+    assert_eq!(status.claimable_amount, 0);
+    assert_eq!(status.next_unlock_time, None);
+    assert!(status.remaining_schedule.is_empty());
+}
+
+#[test]
+fn anyone_can_crank_a_claim_to_the_beneficiary_ata() {
+    let mut test = VestingTest::new();
+    let start = timestamp(2025, 1, 1, 0, 0, 0);
+    let end = timestamp(2026, 1, 1, 0, 0, 0);
+    test.setup_reserve(start, end, 0);
+    // A holder who never created a token account for the mint.
+    let holder = Pubkey::new_unique();
+    test.create_reserve_for(ADMIN, holder, 0, Grant::monthly(start, end, 0))
+        .unwrap();
+    let reserve = test.reserve_pda_for(&holder, 0);
+    let cranker = test.ctx.wallet(1);
+
+    test.ctx.warp_to(start);
+    test.crank(&cranker, holder, reserve).unwrap();
+    assert_eq!(test.ctx.token_balance(&holder), MONTHLY);
+    assert_error(
+        test.crank(&cranker, holder, reserve),
+        VestingError::ClaimNotAvailableYet,
+    );
+
+    // The beneficiary can't be swapped for the cranker.
+    test.ctx.warp_to(timestamp(2025, 2, 1, 0, 0, 0));
+    assert_error(
+        test.crank(&cranker, cranker.pubkey(), reserve),
+        anchor_lang::error::ErrorCode::ConstraintHasOne,
+    );
+    test.crank(&cranker, holder, reserve).unwrap();
+    assert_eq!(test.ctx.token_balance(&holder), 2 * MONTHLY);
+    assert_eq!(test.fetch_vesting_account().total_withdrawn, 2 * MONTHLY);
+}
+
+#[test]
+fn cranks_are_tipped_from_the_tip_vault_once_a_day() {
+    let mut test = VestingTest::new();
+    let start = timestamp(2025, 1, 1, 0, 0, 0);
+    test.setup_reserve(start, timestamp(2026, 1, 1, 0, 0, 0), 0);
+    let intruder = test.ctx.wallet(1);
+    assert_error(
+        test.configure_tip_vault_as(intruder.pubkey(), 5_000),
+        VestingError::AccessDenied,
+    );
+    assert_error(
+        test.configure_tip_vault_as(ADMIN, vesting::MAX_CRANK_TIP + 1),
+        VestingError::InvalidTip,
+    );
+    test.configure_tip_vault_as(ADMIN, 5_000).unwrap();
+    test.fund_tip_vault(12_000).unwrap();
+
+    let beneficiary = test.beneficiary.pubkey();
+    let reserve = test.reserve_pda();
+    let cranker = test.ctx.wallet(1);
+    let crank_at = |test: &mut VestingTest, unix_timestamp| {
+        test.ctx.warp_to(unix_timestamp);
+        let before = test.ctx.lamports(&cranker.pubkey());
+        test.crank(&cranker, beneficiary, reserve).unwrap();
+        test.ctx.lamports(&cranker.pubkey()) - before
+    };
+
+    assert_eq!(crank_at(&mut test, start), 5_000);
+    assert_eq!(crank_at(&mut test, timestamp(2025, 2, 1, 0, 0, 0)), 5_000);
+    assert_eq!(test.fetch_tip_vault().balance, 2_000);
+    // An underfunded vault leaves the crank unpaid but still delivers the tokens.
+    assert_eq!(crank_at(&mut test, timestamp(2025, 3, 1, 0, 0, 0)), 0);
+    assert_eq!(test.ctx.token_balance(&beneficiary), 3 * MONTHLY);
+
+    assert_error(
+        test.withdraw_tips_as(intruder.pubkey(), 2_000),
+        VestingError::AccessDenied,
+    );
+    assert_error(
+        test.withdraw_tips_as(ADMIN, 2_001),
+        VestingError::InsufficientTipBalance,
+    );
+    let admin_before = test.ctx.lamports(&ADMIN);
+    test.withdraw_tips_as(ADMIN, 2_000).unwrap();
+    assert_eq!(test.ctx.lamports(&ADMIN) - admin_before, 2_000);
+    assert_eq!(test.fetch_tip_vault().balance, 0);
+}
+
+#[test]
+fn streaming_reserves_pay_one_crank_tip_a_day() {
+    let mut test = VestingTest::new();
+    let start = timestamp(2025, 1, 1, 0, 0, 0);
+    test.ctx.warp_to(start);
+    test.create_vesting_account_as(ADMIN).unwrap();
+    let beneficiary = test.beneficiary.pubkey();
+    let grant = Grant {
+        schedule: ScheduleKind::Linear,
+        ..Grant::monthly(start, timestamp(2026, 1, 1, 0, 0, 0), 0)
+    };
+    test.create_reserve_for(ADMIN, beneficiary, 0, grant)
+        .unwrap();
+    test.configure_tip_vault_as(ADMIN, 5_000).unwrap();
+    test.fund_tip_vault(100_000).unwrap();
+    let reserve = test.reserve_pda();
+    let cranker = test.ctx.wallet(1);
+
+    for (offset, balance) in [
+        (60, 95_000),
+        (120, 95_000),
+        (SECONDS_PER_DAY + 59, 95_000),
+        (SECONDS_PER_DAY + 60, 90_000),
+    ] {
+        test.ctx.warp_to(start + offset);
+        test.crank(&cranker, beneficiary, reserve).unwrap();
+        assert_eq!(test.fetch_tip_vault().balance, balance);
+    }
+}
+
+#[test]
+fn beneficiary_transfers_take_effect_once_accepted() {
+    let mut test = VestingTest::new();
+    let start = timestamp(2025, 1, 1, 0, 0, 0);
+    test.setup_reserve(start, timestamp(2026, 1, 1, 0, 0, 0), 0);
+    let old_wallet = test.beneficiary.insecure_clone();
+    let new_wallet = test.ctx.wallet(1);
+    test.ctx.create_token_account(&new_wallet.pubkey(), 0);
+    let intruder = Pubkey::new_unique();
+
+    assert_error(
+        test.transfer_beneficiary_as(intruder, Some(intruder)),
+        VestingError::AccessDenied,
+    );
+    test.transfer_beneficiary_as(old_wallet.pubkey(), Some(new_wallet.pubkey()))
+        .unwrap();
+    assert_eq!(
+        test.fetch_reserve().pending_beneficiary,
+        Some(new_wallet.pubkey())
+    );
+    assert_error(
+        test.accept_beneficiary_as(intruder),
+        VestingError::AccessDenied,
+    );
+
+    // The old wallet keeps claiming until the transfer is accepted.
+    assert_eq!(test.claim_at(start).unwrap(), MONTHLY);
+    test.accept_beneficiary_as(new_wallet.pubkey()).unwrap();
+    let reserve = test.fetch_reserve();
+    assert_eq!(reserve.beneficiary, new_wallet.pubkey());
+    assert_eq!(reserve.pending_beneficiary, None);
+
+    test.ctx.warp_to(timestamp(2025, 2, 1, 0, 0, 0));
+    let reserve = test.reserve_pda();
+    assert_error(
+        test.claim_reserve(&old_wallet, reserve),
+        anchor_lang::error::ErrorCode::ConstraintHasOne,
+    );
+    test.claim_reserve(&new_wallet, reserve).unwrap();
+    assert_eq!(test.ctx.token_balance(&new_wallet.pubkey()), MONTHLY);
+}
+
+#[test]
+fn the_admin_can_move_a_revocable_reserve_off_a_lost_key() {
+    let mut test = VestingTest::new();
+    let start = timestamp(2025, 1, 1, 0, 0, 0);
+    test.ctx.warp_to(start);
+    test.create_vesting_account_as(ADMIN).unwrap();
+    let beneficiary = test.beneficiary.pubkey();
+    let grant = Grant {
+        revocable: true,
+        ..Grant::monthly(start, timestamp(2026, 1, 1, 0, 0, 0), 0)
+    };
+    test.create_reserve_for(ADMIN, beneficiary, 0, grant)
+        .unwrap();
+    let custodian = Pubkey::new_unique();
+
+    test.transfer_beneficiary_as(ADMIN, Some(Pubkey::new_unique()))
+        .unwrap();
+    // A later proposal replaces the pending one, and `None` cancels it.
+    test.transfer_beneficiary_as(ADMIN, None).unwrap();
+    assert_error(
+        test.accept_beneficiary_as(custodian),
+        VestingError::AccessDenied,
+    );
+    test.transfer_beneficiary_as(ADMIN, Some(custodian))
+        .unwrap();
+    test.accept_beneficiary_as(custodian).unwrap();
+    assert_eq!(test.fetch_reserve().beneficiary, custodian);
+}
+
+#[test]
+fn the_admin_cannot_move_a_non_revocable_reserve_alone() {
+    let mut test = VestingTest::new();
+    let start = timestamp(2025, 1, 1, 0, 0, 0);
+    test.setup_reserve(start, timestamp(2026, 1, 1, 0, 0, 0), 0);
+    let beneficiary = test.beneficiary.pubkey();
+    let custodian = Pubkey::new_unique();
+
+    assert_error(
+        test.transfer_beneficiary_as(ADMIN, Some(custodian)),
+        VestingError::NotRevocable,
+    );
+    assert_error(
+        test.accept_beneficiary_as(custodian),
+        VestingError::AccessDenied,
+    );
+    assert_eq!(test.fetch_reserve().beneficiary, beneficiary);
+
+    // The beneficiary can still hand it over themselves.
+    test.transfer_beneficiary_as(beneficiary, Some(custodian))
+        .unwrap();
+    test.accept_beneficiary_as(custodian).unwrap();
+    assert_eq!(test.fetch_reserve().beneficiary, custodian);
+}
+
+#[test]
+fn claim_delegates_claim_into_the_beneficiary_account() {
+    let mut test = VestingTest::new();
+    let start = timestamp(2025, 1, 1, 0, 0, 0);
+    test.setup_reserve(start, timestamp(2026, 1, 1, 0, 0, 0), 0);
+    let beneficiary = test.beneficiary.pubkey();
+    let delegate = test.ctx.wallet(1).pubkey();
+    let delegate_account = test.ctx.create_token_account(&delegate, 0);
+    let beneficiary_account = test.ctx.ata(&beneficiary);
+    test.ctx.warp_to(start);
+
+    assert_error(
+        test.set_claim_delegate_as(delegate, delegate),
+        anchor_lang::error::ErrorCode::ConstraintHasOne,
+    );
+    test.set_claim_delegate_as(beneficiary, delegate).unwrap();
+    assert_error(
+        test.claim_as_delegate(delegate, beneficiary, delegate_account),
+        anchor_lang::error::ErrorCode::ConstraintTokenOwner,
+    );
+    assert_error(
+        test.claim_as_delegate(Pubkey::new_unique(), beneficiary, beneficiary_account),
+        anchor_lang::error::ErrorCode::ConstraintHasOne,
+    );
+    test.claim_as_delegate(delegate, beneficiary, beneficiary_account)
+        .unwrap();
+    assert_eq!(test.ctx.token_balance(&beneficiary), MONTHLY);
+    assert_eq!(test.ctx.token_balance(&delegate), 0);
+
+    // The delegation lapses when the reserve changes hands.
+    let new_wallet = Pubkey::new_unique();
+    let new_account = test.ctx.create_token_account(&new_wallet, 0);
+    test.transfer_beneficiary_as(beneficiary, Some(new_wallet))
+        .unwrap();
+    test.accept_beneficiary_as(new_wallet).unwrap();
+    test.ctx.warp_to(timestamp(2025, 2, 1, 0, 0, 0));
+    assert_error(
+        test.claim_as_delegate(delegate, new_wallet, new_account),
+        anchor_lang::error::ErrorCode::ConstraintHasOne,
+    );
+
+    test.remove_claim_delegate_as(new_wallet).unwrap();
+    assert!(!test.ctx.account_exists(&test.claim_delegate_pda()));
+}
+
+#[test]
+fn claims_follow_the_holder_of_a_tokenized_reserve() {
+    let mut test = VestingTest::new();
+    let start = timestamp(2025, 1, 1, 0, 0, 0);
+    test.setup_reserve(start, timestamp(2026, 1, 1, 0, 0, 0), 0);
+    let beneficiary = test.beneficiary.insecure_clone();
+    test.ctx.warp_to(start);
+
+    assert_error(
+        test.claim_position(beneficiary.pubkey()),
+        VestingError::ReserveNotTokenized,
+    );
+    assert_error(
+        test.tokenize_reserve_as(Pubkey::new_unique(), false),
+        VestingError::AccessDenied,
+    );
+    test.tokenize_reserve_as(beneficiary.pubkey(), false)
+        .unwrap();
+    assert!(test.fetch_reserve().tokenized);
+    assert_error(
+        test.tokenize_reserve_as(beneficiary.pubkey(), false),
+        VestingError::ReserveTokenized,
+    );
+    let position = test
+        .ctx
+        .account_data(&test.position_account(&beneficiary.pubkey()));
+    assert_eq!(
+        StateWithExtensions::<spl_token_2022::state::Account>::unpack(&position)
+            .unwrap()
+            .base
+            .amount,
+        1
+    );
+
+    // The beneficiary's own claim paths are closed once the reserve is tokenized.
+    assert_error(test.claim(), VestingError::ReserveTokenized);
+    assert_error(
+        test.transfer_beneficiary_as(beneficiary.pubkey(), Some(Pubkey::new_unique())),
+        VestingError::ReserveTokenized,
+    );
+    test.claim_position(beneficiary.pubkey()).unwrap();
+    assert_eq!(test.ctx.token_balance(&beneficiary.pubkey()), MONTHLY);
+
+    // Whoever holds the NFT claims from then on.
+    let holder = test.ctx.wallet(1).pubkey();
+    test.ctx.create_token_account(&holder, 0);
+    test.transfer_position(&beneficiary, holder).unwrap();
+    test.ctx.warp_to(timestamp(2025, 2, 1, 0, 0, 0));
+    assert_error(
+        test.claim_position(beneficiary.pubkey()),
+        VestingError::InvalidPositionAccount,
+    );
+    test.claim_position(holder).unwrap();
+    assert_eq!(test.ctx.token_balance(&holder), MONTHLY);
+    assert_eq!(test.ctx.token_balance(&beneficiary.pubkey()), MONTHLY);
+}
+
+#[test]
+fn non_transferable_positions_stay_with_the_beneficiary() {
+    let mut test = VestingTest::new();
+    let start = timestamp(2025, 1, 1, 0, 0, 0);
+    test.setup_reserve(start, timestamp(2026, 1, 1, 0, 0, 0), 0);
+    let beneficiary = test.beneficiary.insecure_clone();
+    test.ctx.warp_to(start);
+
+    test.tokenize_reserve_as(beneficiary.pubkey(), true)
+        .unwrap();
+    let holder = test.ctx.wallet(1).pubkey();
+    assert!(test.transfer_position(&beneficiary, holder).is_err());
+    test.claim_position(beneficiary.pubkey()).unwrap();
+    assert_eq!(test.ctx.token_balance(&beneficiary.pubkey()), MONTHLY);
+}
+
+#[test]
+fn tokenizing_survives_a_pre_funded_position_mint() {
+    let mut test = VestingTest::new();
+    let start = timestamp(2025, 1, 1, 0, 0, 0);
+    test.setup_reserve(start, timestamp(2026, 1, 1, 0, 0, 0), 0);
+    let beneficiary = test.beneficiary.insecure_clone();
+    test.ctx.warp_to(start);
+    // Anyone can send lamports to the position mint's address before it is created.
+    let position_mint = test.position_mint();
+    test.ctx.svm.airdrop(&position_mint, 1_000).unwrap();
+
+    test.tokenize_reserve_as(beneficiary.pubkey(), true)
+        .unwrap();
+    let mint = test.ctx.svm.get_account(&position_mint).unwrap();
+    assert_eq!(mint.owner, spl_token_2022::ID);
+    assert_eq!(
+        StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&mint.data)
+            .unwrap()
+            .base
+            .supply,
+        1
+    );
+    test.claim_position(beneficiary.pubkey()).unwrap();
+    assert_eq!(test.ctx.token_balance(&beneficiary.pubkey()), MONTHLY);
+}
+
+#[test]
+fn revoking_a_tokenized_reserve_pays_the_position_holder() {
+    let mut test = VestingTest::new();
+    let start = timestamp(2025, 1, 1, 0, 0, 0);
+    test.setup_reserve(start, timestamp(2026, 1, 1, 0, 0, 0), 0);
+    let beneficiary = test.beneficiary.insecure_clone();
+    test.ctx.warp_to(start);
+    test.tokenize_reserve_as(beneficiary.pubkey(), false)
+        .unwrap();
+    let holder = test.ctx.wallet(1).pubkey();
+    test.ctx.create_token_account(&holder, 0);
+    test.transfer_position(&beneficiary, holder).unwrap();
+    test.ctx.warp_to(timestamp(2025, 2, 15, 0, 0, 0));
+
+    assert_error(test.revoke_as(ADMIN), VestingError::InvalidPositionAccount);
+    let beneficiary_position = test.position_account(&beneficiary.pubkey());
+    assert_error(
+        test.revoke_to(ADMIN, beneficiary.pubkey(), Some(beneficiary_position)),
+        VestingError::InvalidPositionAccount,
+    );
+    let holder_position = test.position_account(&holder);
+    assert_error(
+        test.revoke_to(ADMIN, beneficiary.pubkey(), Some(holder_position)),
+        anchor_lang::error::ErrorCode::ConstraintTokenOwner,
+    );
+    test.revoke_to(ADMIN, holder, Some(holder_position))
+        .unwrap();
+    assert_eq!(test.ctx.token_balance(&holder), 2 * MONTHLY);
+    assert_eq!(test.ctx.token_balance(&beneficiary.pubkey()), 0);
+}
+
+/// A 12 x `MONTHLY` grant to `beneficiary` starting at `start`.
+fn distributor_grant(beneficiary: Pubkey, start: i64, cliff_time: i64) -> DistributorGrant {
+    DistributorGrant {
+        beneficiary,
+        start_time: start,
+        end_time: timestamp(2026, 1, 1, 0, 0, 0),
+        total_amount: TOTAL,
+        cliff_time,
+        monthly_claim: MONTHLY,
+        revocable: false,
+        schedule: ScheduleKind::Monthly,
+        tge_unlock_amount: 0,
+        cliff_release_amount: 0,
+    }
+}
+
+/// The Merkle tree a distributor for `grants` commits to, with each grant's proof.
+fn distributor_tree(grants: &[DistributorGrant]) -> ([u8; 20], Vec<Vec<[u8; 20]>>) {
+    let leaves: Vec<Vec<u8>> = grants.iter().map(|grant| grant.leaf().unwrap()).collect();
+    let items: Vec<&[u8]> = leaves.iter().map(Vec::as_slice).collect();
+    let tree = MerkleTree::<Keccak160>::new(&items).unwrap();
+    let first_leaf = tree.nodes.len() / 2;
+    let proofs = (0..grants.len())
+        .map(|i| tree.find_path(first_leaf + i).to_vec())
+        .collect();
+    let mut root = [0; 20];
+    root.copy_from_slice(tree.root.as_bytes());
+    (root, proofs)
+}
+
+#[test]
+fn distributor_grants_become_reserves_on_their_first_claim() {
+    let mut test = VestingTest::new();
+    let start = timestamp(2025, 1, 1, 0, 0, 0);
+    test.ctx.warp_to(start - SECONDS_PER_DAY);
+    test.create_vesting_account_as(ADMIN).unwrap();
+    let beneficiary = test.beneficiary.insecure_clone();
+    let other = test.ctx.wallet(1);
+    test.ctx.create_token_account(&other.pubkey(), 0);
+    let grants = [
+        distributor_grant(beneficiary.pubkey(), start, 0),
+        distributor_grant(other.pubkey(), start, 90 * SECONDS_PER_DAY),
+        distributor_grant(Pubkey::new_unique(), start, 0),
+    ];
+    let (root, proofs) = distributor_tree(&grants);
+    let expiry = timestamp(2025, 6, 1, 0, 0, 0);
+
+    assert_error(
+        test.create_distributor_as(beneficiary.pubkey(), 0, root, 3 * TOTAL, expiry),
+        VestingError::AccessDenied,
+    );
+    test.create_distributor_as(ADMIN, 0, root, 3 * TOTAL, expiry)
+        .unwrap();
+    assert_eq!(test.ctx.token_balance(&test.vesting_account), 3 * TOTAL);
+    test.ctx.warp_to(start);
+
+    assert_error(
+        test.claim_from_distributor(&beneficiary, 0, &grants[0], proofs[1].clone()),
+        VestingError::InvalidProof,
+    );
+    let inflated = DistributorGrant {
+        total_amount: 2 * TOTAL,
+        monthly_claim: 2 * MONTHLY,
+        ..grants[0].clone()
+    };
+    assert_error(
+        test.claim_from_distributor(&beneficiary, 0, &inflated, proofs[0].clone()),
+        VestingError::InvalidProof,
+    );
+    assert_error(
+        test.claim_from_distributor(&other, 0, &grants[0], proofs[0].clone()),
+        VestingError::InvalidProof,
+    );
+
+    test.claim_from_distributor(&beneficiary, 0, &grants[0], proofs[0].clone())
+        .unwrap();
+    assert_eq!(test.ctx.token_balance(&beneficiary.pubkey()), MONTHLY);
+    let reserve = test.distributor_reserve_pda(0, &beneficiary.pubkey());
+    assert_error(
+        test.claim_from_distributor(&beneficiary, 0, &grants[0], proofs[0].clone()),
+        ACCOUNT_ALREADY_IN_USE,
+    );
+
+    // Reserves still in their cliff are created without paying anything out.
+    test.claim_from_distributor(&other, 0, &grants[1], proofs[1].clone())
+        .unwrap();
+    assert_eq!(test.ctx.token_balance(&other.pubkey()), 0);
+    let distributor = test.fetch_distributor(0);
+    assert_eq!(distributor.claimed_amount, 2 * TOTAL);
+    assert_eq!(distributor.claimed_count, 2);
+    assert_eq!(test.fetch_vesting_account().total_allocated, 2 * TOTAL);
+
+    // From then on the reserve is claimed like any other.
+    test.ctx.warp_to(timestamp(2025, 2, 1, 0, 0, 0));
+    test.claim_reserve(&beneficiary, reserve).unwrap();
+    assert_eq!(test.ctx.token_balance(&beneficiary.pubkey()), 2 * MONTHLY);
+}
+
+#[test]
+fn closing_a_distributor_reserve_does_not_allow_a_second_claim() {
+    let mut test = VestingTest::new();
+    let start = timestamp(2025, 1, 1, 0, 0, 0);
+    let end = timestamp(2026, 1, 1, 0, 0, 0);
+    test.ctx.warp_to(start - SECONDS_PER_DAY);
+    test.create_vesting_account_as(ADMIN).unwrap();
+    let beneficiary = test.beneficiary.insecure_clone();
+    let grants = [
+        distributor_grant(beneficiary.pubkey(), start, 0),
+        distributor_grant(Pubkey::new_unique(), start, 0),
+    ];
+    let (root, proofs) = distributor_tree(&grants);
+    test.create_distributor_as(ADMIN, 0, root, 2 * TOTAL, timestamp(2026, 6, 1, 0, 0, 0))
+        .unwrap();
+
+    // Claimed once the grant has fully vested, then closed for its rent.
+    test.ctx.warp_to(end);
+    test.claim_from_distributor(&beneficiary, 0, &grants[0], proofs[0].clone())
+        .unwrap();
+    assert_eq!(test.ctx.token_balance(&beneficiary.pubkey()), TOTAL);
+    let reserve = test.distributor_reserve_pda(0, &beneficiary.pubkey());
+    test.close_reserve_at(&beneficiary, reserve).unwrap();
+    assert!(!test.ctx.account_exists(&reserve));
+
+    // The claim receipt outlives the reserve.
+    assert_error(
+        test.claim_from_distributor(&beneficiary, 0, &grants[0], proofs[0].clone()),
+        ACCOUNT_ALREADY_IN_USE,
+    );
+    assert_eq!(test.ctx.token_balance(&beneficiary.pubkey()), TOTAL);
+    let distributor = test.fetch_distributor(0);
+    assert_eq!(distributor.claimed_amount, TOTAL);
+    assert_eq!(distributor.claimed_count, 1);
+}
+
+#[test]
+fn unclaimed_distributor_funds_are_clawed_back_after_expiry() {
+    let mut test = VestingTest::new();
+    let start = timestamp(2025, 1, 1, 0, 0, 0);
+    test.ctx.warp_to(start - SECONDS_PER_DAY);
+    test.create_vesting_account_as(ADMIN).unwrap();
+    let beneficiary = test.beneficiary.insecure_clone();
+    let other = test.ctx.wallet(1);
+    test.ctx.create_token_account(&other.pubkey(), 0);
+    let late = test.ctx.wallet(1);
+    test.ctx.create_token_account(&late.pubkey(), 0);
+    let grants = [
+        distributor_grant(beneficiary.pubkey(), start, 0),
+        distributor_grant(other.pubkey(), start, 0),
+        distributor_grant(late.pubkey(), start, 0),
+    ];
+    let (root, proofs) = distributor_tree(&grants);
+    let expiry = timestamp(2025, 6, 1, 0, 0, 0);
+    // Funded for one and a half grants.
+    test.create_distributor_as(ADMIN, 0, root, TOTAL + TOTAL / 2, expiry)
+        .unwrap();
+    test.ctx.warp_to(start);
+
+    test.claim_from_distributor(&beneficiary, 0, &grants[0], proofs[0].clone())
+        .unwrap();
+    assert_error(
+        test.claim_from_distributor(&other, 0, &grants[1], proofs[1].clone()),
+        VestingError::DistributorExhausted,
+    );
+    assert_error(
+        test.clawback_distributor_as(ADMIN, 0),
+        VestingError::DistributorNotExpired,
+    );
+
+    test.ctx.warp_to(expiry);
+    assert_error(
+        test.claim_from_distributor(&late, 0, &grants[2], proofs[2].clone()),
+        VestingError::DistributorExpired,
+    );
+    assert_error(
+        test.clawback_distributor_as(beneficiary.pubkey(), 0),
+        VestingError::AccessDenied,
+    );
+    let admin_balance = test.ctx.token_balance(&ADMIN);
+    test.clawback_distributor_as(ADMIN, 0).unwrap();
+    assert_eq!(test.ctx.token_balance(&ADMIN), admin_balance + TOTAL / 2);
+    assert_error(
+        test.clawback_distributor_as(ADMIN, 0),
+        VestingError::DistributorClawedBack,
+    );
+
+    // The claimed reserve is unaffected and keeps vesting from the treasury.
+    let reserve = test.distributor_reserve_pda(0, &beneficiary.pubkey());
+    test.claim_reserve(&beneficiary, reserve).unwrap();
+    assert_eq!(test.ctx.token_balance(&beneficiary.pubkey()), 6 * MONTHLY);
+}
+
+fn tranche(unlock_time: i64, cumulative_amount: u64) -> Tranche {
+    Tranche {
+        unlock_time,
+        cumulative_amount,
+    }
+}
+
+/// Creates a `Custom` reserve for the beneficiary starting at `start`.
+fn setup_custom_reserve(test: &mut VestingTest, start: i64) {
+    test.ctx.warp_to(start);
+    test.create_vesting_account_as(ADMIN).unwrap();
+    let beneficiary = test.beneficiary.pubkey();
+    let grant = Grant {
+        schedule: ScheduleKind::Custom,
+        ..Grant::monthly(start, timestamp(2026, 1, 1, 0, 0, 0), 0)
+    };
+    test.create_reserve_for(ADMIN, beneficiary, 0, grant)
+        .unwrap();
+}
+
+#[test]
+fn custom_tranches_release_up_to_the_latest_matured_tranche() {
+    let mut test = VestingTest::new();
+    let start = timestamp(2025, 1, 1, 0, 0, 0);
+    setup_custom_reserve(&mut test, start);
+    assert_eq!(
+        test.claim_at(start).unwrap_err(),
+        custom_error(VestingError::MissingTranches)
+    );
+
+    // 10% at TGE, 15% more at month 3, the rest at month 6.
+    let april = timestamp(2025, 4, 1, 0, 0, 0);
+    let july = timestamp(2025, 7, 1, 0, 0, 0);
+    test.initialize_tranches(vec![
+        tranche(start, TOTAL / 10),
+        tranche(april, TOTAL / 4),
+        tranche(july, TOTAL),
+    ])
+    .unwrap();
+
+    assert_eq!(test.claim_at(start).unwrap(), TOTAL / 10);
+    assert_eq!(
+        test.claim_at(april - 1).unwrap_err(),
+        custom_error(VestingError::ClaimNotAvailableYet)
+    );
+    assert_eq!(test.claim_at(july + 1).unwrap(), TOTAL * 9 / 10);
+    assert_error(
+        test.update_tranches(vec![tranche(start, TOTAL)]),
+        VestingError::TranchesLocked,
+    );
+}
+
+#[test]
+fn tranches_can_be_replaced_before_the_first_claim() {
+    let mut test = VestingTest::new();
+    let start = timestamp(2025, 1, 1, 0, 0, 0);
+    setup_custom_reserve(&mut test, start);
+    test.initialize_tranches(vec![tranche(start + 86_400, TOTAL)])
+        .unwrap();
+
+    let monthly: Vec<Tranche> = (1..=12)
+        .map(|month| tranche(timestamp(2025, month, 15, 0, 0, 0), month as u64 * MONTHLY))
+        .collect();
+    test.update_tranches(monthly.clone()).unwrap();
+    assert_eq!(test.fetch_tranches().tranches, monthly);
+
+    assert_eq!(
+        test.claim_at(timestamp(2025, 2, 20, 0, 0, 0)).unwrap(),
+        2 * MONTHLY
+    );
+}
+
+#[test]
+fn tranches_must_increase_and_add_up_to_the_total() {
+    let mut test = VestingTest::new();
+    let start = timestamp(2025, 1, 1, 0, 0, 0);
+    setup_custom_reserve(&mut test, start);
+
+    for tranches in [
+        vec![],
+        vec![tranche(start, TOTAL / 2)],
+        vec![tranche(start, TOTAL / 2), tranche(start, TOTAL)],
+        vec![tranche(start + 1, TOTAL / 2), tranche(start, TOTAL)],
+        vec![
+            tranche(start, TOTAL / 2),
+            tranche(start + 1, TOTAL / 2),
+            tranche(start + 2, TOTAL),
+        ],
+        vec![tranche(start, TOTAL), tranche(start + 1, TOTAL + 1)],
+    ] {
+        assert_error(
+            test.initialize_tranches(tranches),
+            VestingError::InvalidTranches,
+        );
+    }
+}
+
+#[test]
+fn tge_and_cliff_release_amounts_unlock_ahead_of_the_schedule() {
+    let mut test = VestingTest::new();
+    let start = timestamp(2025, 1, 1, 0, 0, 0);
+    let cliff_end = timestamp(2025, 4, 1, 0, 0, 0);
+    test.ctx.warp_to(start - 86_400);
+    test.create_vesting_account_as(ADMIN).unwrap();
+    let beneficiary = test.beneficiary.pubkey();
+    let grant = Grant {
+        tge_unlock_amount: MONTHLY,
+        cliff_release_amount: 2 * MONTHLY,
+        ..Grant::monthly(start, timestamp(2026, 1, 1, 0, 0, 0), cliff_end - start)
+    };
+    test.create_reserve_for(ADMIN, beneficiary, 0, grant)
+        .unwrap();
+
+    assert_eq!(
+        test.claim_at(start - 1).unwrap_err(),
+        custom_error(VestingError::CliffPeriodNotEnded)
+    );
+    assert_eq!(test.claim_at(start).unwrap(), MONTHLY);
+    assert_eq!(
+        test.claim_at(cliff_end - 1).unwrap_err(),
+        custom_error(VestingError::CliffPeriodNotEnded)
+    );
+    // The cliff release plus the first monthly period.
+    assert_eq!(test.claim_at(cliff_end).unwrap(), 3 * MONTHLY);
+    assert_eq!(
+        test.claim_at(timestamp(2025, 5, 1, 0, 0, 0)).unwrap(),
+        MONTHLY
+    );
+    // The schedule covers what is left after the upfront amounts.
+    assert_eq!(
+        test.claim_at(timestamp(2027, 1, 1, 0, 0, 0)).unwrap(),
+        7 * MONTHLY
+    );
+    assert_eq!(test.fetch_reserve().amount_withdrawn, TOTAL);
+}
+
+#[test]
+fn upfront_amounts_must_fit_the_total() {
+    let mut test = VestingTest::new();
+    let start = timestamp(2025, 1, 1, 0, 0, 0);
+    test.ctx.warp_to(start);
+    test.create_vesting_account_as(ADMIN).unwrap();
+    let beneficiary = test.beneficiary.pubkey();
+    let grant = Grant::monthly(start, timestamp(2026, 1, 1, 0, 0, 0), 0);
+
+    assert_error(
+        test.create_reserve_for(
+            ADMIN,
+            beneficiary,
+            0,
+            Grant {
+                tge_unlock_amount: TOTAL / 2,
+                cliff_release_amount: TOTAL / 2 + 1,
+                ..grant
+            },
+        ),
+        VestingError::InvalidUpfrontAmount,
+    );
+    assert_error(
+        test.create_reserve_for(
+            ADMIN,
+            beneficiary,
+            0,
+            Grant {
+                schedule: ScheduleKind::Custom,
+                tge_unlock_amount: MONTHLY,
+                ..grant
+            },
+        ),
+        VestingError::InvalidSchedule,
+    );
+}
+
+#[test]
+fn create_reserve_rejects_grants_that_never_fully_vest() {
+    let mut test = VestingTest::new();
+    let start = timestamp(2025, 1, 1, 0, 0, 0);
+    let end = timestamp(2026, 1, 1, 0, 0, 0);
+    test.ctx.warp_to(start);
+    test.create_vesting_account_as(ADMIN).unwrap();
+    let beneficiary = test.beneficiary.pubkey();
+
+    for (grant, error) in [
+        (
+            Grant::monthly(start, start, 0),
+            VestingError::InvalidEndTime,
+        ),
+        (Grant::monthly(end, start, 0), VestingError::InvalidEndTime),
+        (Grant::monthly(start, end, -1), VestingError::InvalidCliff),
+        (
+            Grant::monthly(start, end, end - start + 1),
+            VestingError::InvalidCliff,
+        ),
+        (
+            Grant::monthly(start, end, i64::MAX),
+            VestingError::InvalidCliff,
+        ),
+        // Twelve monthly periods need eleven months after the cliff.
+        (
+            Grant::monthly(start, timestamp(2025, 11, 1, 0, 0, 0), 0),
+            VestingError::ScheduleIncomplete,
+        ),
+        (
+            Grant {
+                schedule: ScheduleKind::Quarterly,
+                ..Grant::monthly(start, end, 0)
+            },
+            VestingError::ScheduleIncomplete,
+        ),
+    ] {
+        assert_error(test.create_reserve_for(ADMIN, beneficiary, 0, grant), error);
+    }
+
+    let grant = Grant::monthly(start, end, 0);
+    assert_error(
+        test.create_reserve_with_amounts(ADMIN, beneficiary, 0, grant, 0, MONTHLY),
+        VestingError::InvalidAmount,
+    );
+    assert_error(
+        test.create_reserve_with_amounts(ADMIN, beneficiary, 0, grant, TOTAL, 0),
+        VestingError::InvalidPeriodAmount,
+    );
+    // A grant paid out entirely upfront needs no period amount.
+    test.create_reserve_with_amounts(
+        ADMIN,
+        beneficiary,
+        0,
+        Grant {
+            tge_unlock_amount: TOTAL,
+            ..grant
+        },
+        TOTAL,
+        0,
+    )
+    .unwrap();
+    assert_eq!(test.claim_at(start).unwrap(), TOTAL);
+}
+
+#[test]
+fn reserves_with_signed_amounts_are_migrated_before_claiming() {
+    let mut test = VestingTest::new();
+    let start = timestamp(2025, 1, 1, 0, 0, 0);
+    test.setup_reserve(start, timestamp(2026, 1, 1, 0, 0, 0), 0);
+    let reserve = test.reserve_pda();
+    let v2 = test.fetch_reserve();
+    assert_eq!(v2.version, vesting::RESERVE_VERSION);
+
+    test.write_reserve(&ReserveAccount { version: 1, ..v2 });
+    assert_eq!(
+        test.claim_at(start).unwrap_err(),
+        custom_error(VestingError::NotMigrated)
+    );
+    test.migrate_reserve(reserve).unwrap();
+    assert_eq!(test.fetch_reserve().version, vesting::RESERVE_VERSION);
+    assert_eq!(test.claim_at(start).unwrap(), MONTHLY);
+
+    // A negative `i64` period amount would read as a huge `u64` one.
+    test.write_reserve(&ReserveAccount {
+        version: 1,
+        monthly_claim: -1i64 as u64,
+        ..test.fetch_reserve()
+    });
+    assert_error(
+        test.migrate_reserve(reserve),
+        VestingError::InvalidReserveAccount,
+    );
+}
@@ -24,6 +24,7 @@ describe("swap", () => {
   let vault: PublicKey;
   let vaultTokenAccount: PublicKey;
   let userTokenAccount: PublicKey;
+  let stats: PublicKey;
   const index = new BN(Date.now()); // Use this index for PDA derivation
 
   before(async () => {
@@ -91,6 +92,10 @@ describe("swap", () => {
       TOKEN_2022_PROGRAM_ID,
       ASSOCIATED_TOKEN_PROGRAM_ID
     );
+    [stats] = PublicKey.findProgramAddressSync(
+      [Buffer.from("stats"), vault.toBuffer()],
+      program.programId
+    );
 
     await program.methods
       .initialize(index, new BN(1000))
@@ -99,6 +104,7 @@ describe("swap", () => {
         vault: vault,
         tokenMint: tokenMint,
         vaultTokenAccount: vaultTokenAccount,
+        stats: stats,
         systemProgram: SystemProgram.programId,
         tokenProgram: TOKEN_2022_PROGRAM_ID,
         associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
@@ -245,6 +251,10 @@ if (
       program.programId
     );
     const solUsdPriceAccount = new PublicKey("7UVimffxr9ow1uXYxsr4LHAcV58mLzhmwaeKvJ1pjLiE");
+    const [buyerRecord] = PublicKey.findProgramAddressSync(
+      [Buffer.from("buyer"), vaultPda.toBuffer(), buyer.publicKey.toBuffer()],
+      program.programId
+    );
 
     await program.methods
       .purchaseTokens(new BN(10_000))
@@ -252,12 +262,19 @@ if (
         buyer: buyer.publicKey,
         admin: wallet.publicKey,
         vault: vaultPda,
+        stats: stats,
+        recipient: buyer.publicKey,
+        buyerRecord: buyerRecord,
         tokenMint: tokenMint,
         vaultTokenAccount: vaultTokenAccount,
         userTokenAccount: userTokenAccount,
         vaultSigner: vaultPda,
         solUsdPrice: solUsdPriceAccount,
+        gate: null,
+        attestation: null,
+        instructionsSysvar: null,
         tokenProgram: TOKEN_2022_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
         systemProgram: SystemProgram.programId,
       })
       .signers([buyer])
@@ -265,8 +282,161 @@ if (
 
     const vaultAccount = await program.account.vault.fetch(vaultPda);
     console.log("User purchased tokens. Vault state:", vaultAccount);
+
+    const saleStats = await program.account.saleStats.fetch(stats);
+    if (saleStats.uniqueBuyers.toNumber() !== 1) {
+      throw new Error("Expected one unique buyer in sale stats");
+    }
+  });
+
+
+  it("User purchases tokens on behalf of a recipient", async () => {
+    const recipient = Keypair.generate();
+    const recipientTokenAccount = await getAssociatedTokenAddress(
+      tokenMint,
+      recipient.publicKey,
+      false,
+      TOKEN_2022_PROGRAM_ID,
+      ASSOCIATED_TOKEN_PROGRAM_ID
+    );
+    const [recipientRecord] = PublicKey.findProgramAddressSync(
+      [Buffer.from("buyer"), vault.toBuffer(), recipient.publicKey.toBuffer()],
+      program.programId
+    );
+    const solUsdPriceAccount = new PublicKey("7UVimffxr9ow1uXYxsr4LHAcV58mLzhmwaeKvJ1pjLiE");
+
+    await program.methods
+      .purchaseTokens(new BN(10_000))
+      .accounts({
+        buyer: buyer.publicKey,
+        admin: wallet.publicKey,
+        vault: vault,
+        stats: stats,
+        recipient: recipient.publicKey,
+        buyerRecord: recipientRecord,
+        tokenMint: tokenMint,
+        vaultTokenAccount: vaultTokenAccount,
+        userTokenAccount: recipientTokenAccount,
+        vaultSigner: vault,
+        solUsdPrice: solUsdPriceAccount,
+        gate: null,
+        attestation: null,
+        instructionsSysvar: null,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([buyer])
+      .rpc();
+
+    const recipientAccount = await getAccount(
+      provider.connection,
+      recipientTokenAccount,
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+    if (recipientAccount.amount !== 10_000n) {
+      throw new Error("Recipient did not receive purchased tokens");
+    }
+    const record = await program.account.buyerRecord.fetch(recipientRecord);
+    if (!record.buyer.equals(recipient.publicKey)) {
+      throw new Error("Purchase was not recorded against the recipient");
+    }
   });
 
+  it("Syncs the vault inventory with its token account", async () => {
+    await program.methods
+      .syncVault()
+      .accounts({
+        authority: wallet.publicKey,
+        vault: vault,
+        vaultTokenAccount: vaultTokenAccount,
+      })
+      .signers([wallet.payer])
+      .rpc();
+
+    const vaultAccount = await program.account.vault.fetch(vault);
+    const vaultBalance = await getAccount(
+      provider.connection,
+      vaultTokenAccount,
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+    if (BigInt(vaultAccount.totalTokens.toString()) !== vaultBalance.amount) {
+      throw new Error("Vault total_tokens does not match token account balance");
+    }
+  });
+
+  it("Finalizes the sale into a locked liquidity reserve", async () => {
+    const solUsdPriceAccount = new PublicKey("7UVimffxr9ow1uXYxsr4LHAcV58mLzhmwaeKvJ1pjLiE");
+    const [liquidityReserve] = PublicKey.findProgramAddressSync(
+      [Buffer.from("liquidity"), vault.toBuffer()],
+      program.programId
+    );
+    const liquidityTokenAccount = await getAssociatedTokenAddress(
+      tokenMint,
+      liquidityReserve,
+      true,
+      TOKEN_2022_PROGRAM_ID,
+      ASSOCIATED_TOKEN_PROGRAM_ID
+    );
+    const unlockTime = new BN(Math.floor(Date.now() / 1000) + 3600);
+
+    await program.methods
+      .setLiquidityBps(2000)
+      .accounts({
+        authority: wallet.publicKey,
+        vault: vault,
+      })
+      .signers([wallet.payer])
+      .rpc();
+
+    await program.methods
+      .finalizeSale(unlockTime)
+      .accounts({
+        authority: wallet.publicKey,
+        vault: vault,
+        stats: stats,
+        tokenMint: tokenMint,
+        vaultTokenAccount: vaultTokenAccount,
+        vaultSigner: vault,
+        liquidityReserve: liquidityReserve,
+        liquidityTokenAccount: liquidityTokenAccount,
+        solUsdPrice: solUsdPriceAccount,
+        systemProgram: SystemProgram.programId,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      })
+      .signers([wallet.payer])
+      .rpc();
+
+    const reserve = await program.account.liquidityReserve.fetch(liquidityReserve);
+    console.log("Liquidity reserved:", reserve);
+
+    let withdrawFailed = false;
+    try {
+      await program.methods
+        .withdrawLiquidity()
+        .accounts({
+          authority: wallet.publicKey,
+          liquidityReserve: liquidityReserve,
+          tokenMint: tokenMint,
+          liquidityTokenAccount: liquidityTokenAccount,
+          adminTokenAccount: adminTokenAccount,
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+        })
+        .signers([wallet.payer])
+        .rpc();
+    } catch (err: any) {
+      if (!err.toString().includes("LiquidityLocked")) {
+        throw new Error("Expected LiquidityLocked error, got: " + err.toString());
+      }
+      withdrawFailed = true;
+    }
+    if (!withdrawFailed) {
+      throw new Error("Expected LiquidityLocked error, got success");
+    }
+  });
 
 it("Closes the vault and admin receives remaining tokens after expiry", async () => {
         const indexBuffer = Buffer.alloc(8);